@@ -0,0 +1,359 @@
+//! Command-line interface for running Honk scripts without the overlay GUI.
+//! Exit codes: `0` on success, `1` when a step fails, `2` when the script or templates cannot be
+//! loaded.
+use crate::errors::HonkSyntaxError;
+use crate::honk::ast::Script;
+use crate::honk::interpreter::{Interpreter, RunResult, StepResult};
+use crate::honk::parser::parse;
+use crate::honk::registry::TemplateRegistry;
+use crate::nav::coordinate::ScreenRect;
+use crate::nav::grid::detect_grid;
+use crate::nav::parse::parse_screen;
+use crate::nav::strategy::ScaleRange;
+use crate::nav::table::Table;
+use autopilot::bitmap;
+use clap::{Args, Parser, Subcommand};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+const EXIT_STEP_FAILED: u8 = 1;
+const EXIT_INVALID_INPUT: u8 = 2;
+
+#[derive(Parser)]
+#[command(
+    name = "goose",
+    version,
+    about = "Scripted GUI automation for clinical applications"
+)]
+pub struct Cli {
+    /// Directory holding template images referenced as `template<name>`.
+    #[arg(short, long, global = true, default_value = "templates")]
+    pub templates: PathBuf,
+
+    /// Scales to try templates at as `min,max,step`, e.g. `0.75,1.5,0.05` for templates
+    /// captured on a screen with different scaling. Default is to match templates at their own
+    /// scale only.
+    #[arg(long, global = true, value_parser = parse_scale_range)]
+    pub scales: Option<ScaleRange>,
+
+    /// Launches the overlay GUI when no subcommand is given.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Execute a .honk script.
+    Run(RunArgs),
+    /// Parse a .honk script and verify that every template it references exists.
+    Check { script: PathBuf },
+    /// List the templates available in the template directory.
+    Templates,
+    /// Capture a region of the screen and save it as a template.
+    Capture {
+        /// Name used to reference the template in scripts.
+        name: String,
+        /// Region to capture as `x,y,width,height`.
+        #[arg(long, value_parser = parse_rect)]
+        rect: [f64; 4],
+        /// Overwrite an existing template with the same name.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Parse the screen into a hierarchy of GUI elements and print it as JSON.
+    Parse {
+        /// Region to parse as `x,y,width,height`. Default is the entire screen.
+        #[arg(long, value_parser = parse_rect)]
+        rect: Option<[f64; 4]>,
+        /// Write the JSON to this file instead of printing it.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Detect the rows, columns and header of a table on screen and print them as JSON.
+    Table {
+        /// Region holding the table as `x,y,width,height`.
+        #[arg(long, value_parser = parse_rect)]
+        rect: [f64; 4],
+        /// Write the JSON to this file instead of printing it.
+        #[arg(long)]
+        output: Option<PathBuf>,
+        /// Save the region with the detected grid drawn over it to this PNG file.
+        #[arg(long)]
+        overlay: Option<PathBuf>,
+        /// Read the detected table with OCR and export it to this CSV file.
+        #[arg(long)]
+        csv: Option<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    pub script: PathBuf,
+    /// Default timeout in ms for each verb. Statements can override it with `timeout=`.
+    #[arg(long)]
+    pub timeout: Option<u64>,
+    /// Parse the script and resolve templates without moving the mouse or typing.
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Start execution at the statement with this label.
+    #[arg(long)]
+    pub from: Option<String>,
+    /// Append step results to this file.
+    #[arg(long)]
+    pub log: Option<PathBuf>,
+}
+
+/// Runs `command` and maps its outcome to a process exit code.
+pub fn execute(command: Command, templates: &Path, scales: Option<ScaleRange>) -> ExitCode {
+    let registry = TemplateRegistry::new(templates, None, scales);
+    let result = match command {
+        Command::Run(args) => run(args, registry),
+        Command::Check { script } => check(&script, &registry),
+        Command::Templates => list_templates(&registry),
+        Command::Capture { name, rect, force } => capture(&name, rect, force, &registry),
+        Command::Parse { rect, output } => parse_elements(rect, output.as_deref()),
+        Command::Table {
+            rect,
+            output,
+            overlay,
+            csv,
+        } => detect_table(rect, output.as_deref(), overlay.as_deref(), csv.as_deref()),
+    };
+    match result {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::from(EXIT_INVALID_INPUT)
+        }
+    }
+}
+
+fn parse_rect(value: &str) -> Result<[f64; 4], String> {
+    let parts = value
+        .split(',')
+        .map(|part| part.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    parts
+        .try_into()
+        .map_err(|_| "expected x,y,width,height".to_string())
+}
+
+fn parse_scale_range(value: &str) -> Result<ScaleRange, String> {
+    let parts = value
+        .split(',')
+        .map(|part| part.trim().parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    let [min, max, step]: [f64; 3] = parts
+        .try_into()
+        .map_err(|_| "expected min,max,step".to_string())?;
+    if min <= 0.0 || max < min || step <= 0.0 {
+        return Err("expected 0 < min <= max and step > 0".to_string());
+    }
+    Ok(ScaleRange { min, max, step })
+}
+
+/// Reads and parses `path`, printing syntax errors with the offending line.
+fn load_script(path: &Path) -> Result<Option<Script>, Box<dyn Error>> {
+    let source = fs::read_to_string(path)?;
+    match parse(&source) {
+        Ok(script) => Ok(Some(script)),
+        Err(error) => {
+            report_syntax_error(path, &source, &error);
+            Ok(None)
+        }
+    }
+}
+
+fn report_syntax_error(path: &Path, source: &str, error: &HonkSyntaxError) {
+    eprintln!(
+        "{}:{}:{}: {}",
+        path.display(),
+        error.line,
+        error.column,
+        error.message
+    );
+    if let Some(line) = source.lines().nth(error.line.saturating_sub(1)) {
+        eprintln!("    {}", line);
+        eprintln!("    {}^", " ".repeat(error.column.saturating_sub(1)));
+    }
+}
+
+fn run(args: RunArgs, registry: TemplateRegistry) -> Result<ExitCode, Box<dyn Error>> {
+    let Some(script) = load_script(&args.script)? else {
+        return Ok(ExitCode::from(EXIT_INVALID_INPUT));
+    };
+
+    let mut interpreter = Interpreter::new(registry, args.timeout, Some(args.dry_run));
+    let result = match &args.from {
+        Some(label) => interpreter.run_from(&script, label)?,
+        None => interpreter.run(&script),
+    };
+
+    let report = format_report(&args.script, &result, args.dry_run);
+    print!("{}", report);
+    if let Some(log_path) = &args.log {
+        let mut log = File::options().create(true).append(true).open(log_path)?;
+        log.write_all(report.as_bytes())?;
+    }
+
+    if result.is_success() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(EXIT_STEP_FAILED))
+    }
+}
+
+fn format_report(script: &Path, result: &RunResult, dry_run: bool) -> String {
+    let mut report = format!(
+        "{}{}\n",
+        script.display(),
+        if dry_run { " (dry run)" } else { "" }
+    );
+    write_steps(&mut report, &result.steps, 1);
+    let outcome = match result.failure() {
+        Some(step) => format!("failed at line {}", step.root_cause().line),
+        None => "passed".to_string(),
+    };
+    report.push_str(&format!(
+        "{} in {}ms\n",
+        outcome,
+        result.duration.as_millis()
+    ));
+    report
+}
+
+/// Appends one line per step, nesting loop rows and `if` branches under their statement.
+fn write_steps(report: &mut String, steps: &[StepResult], depth: usize) {
+    let indent = "  ".repeat(depth);
+    for step in steps {
+        report.push_str(&format!("{}{}\n", indent, step));
+        write_steps(report, &step.body, depth + 1);
+        for iteration in &step.iterations {
+            let values: Vec<String> = iteration
+                .values
+                .iter()
+                .map(|(column, value)| format!("{}={}", column, value))
+                .collect();
+            let outcome = match iteration.failure() {
+                Some(failed) => format!("failed at line {}", failed.root_cause().line),
+                None => "passed".to_string(),
+            };
+            report.push_str(&format!(
+                "{}  row {} ({}): {}\n",
+                indent,
+                iteration.row,
+                values.join(", "),
+                outcome
+            ));
+            write_steps(report, &iteration.steps, depth + 2);
+        }
+    }
+}
+
+fn check(path: &Path, registry: &TemplateRegistry) -> Result<ExitCode, Box<dyn Error>> {
+    let Some(script) = load_script(path)? else {
+        return Ok(ExitCode::from(EXIT_INVALID_INPUT));
+    };
+
+    let missing: Vec<String> = script
+        .template_names()
+        .into_iter()
+        .filter(|name| registry.path_for(name).is_none())
+        .collect();
+    for name in &missing {
+        eprintln!(
+            "{}: template<{}> not found in {}",
+            path.display(),
+            name,
+            registry.directory.display()
+        );
+    }
+
+    if missing.is_empty() {
+        println!("{}: ok", path.display());
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(EXIT_INVALID_INPUT))
+    }
+}
+
+fn list_templates(registry: &TemplateRegistry) -> Result<ExitCode, Box<dyn Error>> {
+    for name in registry.names()? {
+        println!("{}", name);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn capture(
+    name: &str,
+    rect: [f64; 4],
+    force: bool,
+    registry: &TemplateRegistry,
+) -> Result<ExitCode, Box<dyn Error>> {
+    if !force && registry.path_for(name).is_some() {
+        eprintln!(
+            "template<{}> already exists; pass --force to overwrite it",
+            name
+        );
+        return Ok(ExitCode::from(EXIT_INVALID_INPUT));
+    }
+
+    let [x, y, width, height] = rect;
+    let region = ScreenRect::on_screen(x, y, width, height)?;
+    let capture = bitmap::capture_screen_portion(region.into())?;
+
+    fs::create_dir_all(&registry.directory)?;
+    let path = registry.directory.join(format!("{}.png", name));
+    capture.image.save(&path)?;
+    println!("saved template<{}> to {}", name, path.display());
+    Ok(ExitCode::SUCCESS)
+}
+
+fn parse_elements(
+    rect: Option<[f64; 4]>,
+    output: Option<&Path>,
+) -> Result<ExitCode, Box<dyn Error>> {
+    let region = rect
+        .map(|[x, y, width, height]| ScreenRect::on_screen(x, y, width, height))
+        .transpose()?;
+    let json = serde_json::to_string_pretty(&parse_screen(region, None)?)?;
+    match output {
+        Some(path) => {
+            fs::write(path, json)?;
+            println!("saved elements to {}", path.display());
+        }
+        None => println!("{}", json),
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn detect_table(
+    rect: [f64; 4],
+    output: Option<&Path>,
+    overlay: Option<&Path>,
+    csv: Option<&Path>,
+) -> Result<ExitCode, Box<dyn Error>> {
+    let [x, y, width, height] = rect;
+    let region = ScreenRect::on_screen(x, y, width, height)?;
+    let json = serde_json::to_string_pretty(&detect_grid(region, overlay)?)?;
+    match output {
+        Some(path) => {
+            fs::write(path, json)?;
+            println!("saved grid to {}", path.display());
+        }
+        None => println!("{}", json),
+    }
+    if let Some(path) = overlay {
+        println!("saved overlay to {}", path.display());
+    }
+    if let Some(path) = csv {
+        let rows = Table::detected(region, None, None)?.export_csv(path)?;
+        println!("saved {} rows to {}", rows, path.display());
+    }
+    Ok(ExitCode::SUCCESS)
+}
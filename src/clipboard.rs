@@ -0,0 +1,26 @@
+//! Access to the system clipboard for verbs that copy and paste text.
+use arboard::Clipboard;
+use autopilot::key::Flag;
+use std::error::Error;
+
+/// Modifier held for the copy and paste shortcuts.
+pub const SHORTCUT_MODIFIER: Flag = if cfg!(target_os = "macos") {
+    Flag::Meta
+} else {
+    Flag::Control
+};
+
+/// Returns the text currently on the clipboard. Errors if the clipboard holds no text.
+pub fn read_clipboard() -> Result<String, Box<dyn Error>> {
+    Ok(Clipboard::new()?.get_text()?)
+}
+
+/// Places `text` on the clipboard.
+/// Returns:
+/// * The clipboard handle. Keep it until the text has been pasted: on X11 and Wayland this process
+///   serves the clipboard's contents, which are lost once the handle is dropped.
+pub fn write_clipboard(text: &str) -> Result<Clipboard, Box<dyn Error>> {
+    let mut clipboard = Clipboard::new()?;
+    clipboard.set_text(text)?;
+    Ok(clipboard)
+}
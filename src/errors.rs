@@ -21,3 +21,34 @@ macro_rules! define_error {
 define_error!(ScreenCoordinateError);
 define_error!(OutOfBoundsError);
 define_error!(UIActionTimeOutError);
+define_error!(AssertionError);
+define_error!(OcrError);
+define_error!(KeyChordError);
+define_error!(HonkRuntimeError);
+define_error!(ScrollLimitError);
+define_error!(ControlStateError);
+define_error!(TemplateNotFoundError);
+define_error!(ExtractionError);
+define_error!(TableError);
+define_error!(TemplateLoadError);
+
+/// Error raised while tokenizing or parsing a Honk script.
+/// Carries the 1-based line and column of the offending token so the script author can find it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HonkSyntaxError {
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for HonkSyntaxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "HonkSyntaxError (line {}, column {}): {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl Error for HonkSyntaxError {}
@@ -0,0 +1,730 @@
+//! Typed syntax tree for Honk scripts.
+//! Verb nodes map one-to-one onto the GUI verbs in `crate::verb`.
+use std::fmt::{self, Display, Formatter};
+
+/// Location of a token or statement in the script source. Line and column are 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, column: usize, length: usize) -> Self {
+        Span {
+            line,
+            column,
+            length,
+        }
+    }
+}
+
+/// A parsed `.honk` file: statements in the order they should be executed.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Script {
+    pub statements: Vec<Statement>,
+}
+
+impl Script {
+    /// Index of the statement carrying `label`, if any.
+    pub fn find_label(&self, label: &str) -> Option<usize> {
+        self.statements
+            .iter()
+            .position(|statement| statement.label.as_deref() == Some(label))
+    }
+
+    /// Names of all templates referenced by the script, including inside blocks, in order of
+    /// first use.
+    pub fn template_names(&self) -> Vec<String> {
+        fn collect(statements: &[Statement], names: &mut Vec<String>) {
+            for statement in statements {
+                for target in statement.kind.targets() {
+                    if let Some(name) = target.template_name() {
+                        if !names.contains(name) {
+                            names.push(name.clone());
+                        }
+                    }
+                }
+                for block in statement.kind.blocks() {
+                    collect(block, names);
+                }
+            }
+        }
+
+        let mut names = Vec::new();
+        collect(&self.statements, &mut names);
+        names
+    }
+}
+
+/// A single line of a script, optionally prefixed by a label (`begin: check template<Epic EHR>`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement {
+    pub label: Option<String>,
+    pub kind: StatementKind,
+    pub options: StepOptions,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatementKind {
+    Click(ClickNode),
+    Input(InputNode),
+    Scroll(ScrollNode),
+    Hover(HoverNode),
+    Copy(CopyNode),
+    Read(ReadNode),
+    Paste(PasteNode),
+    Press(PressNode),
+    Drag(DragNode),
+    Check(CheckNode),
+    Wait(WaitNode),
+    Select(SelectNode),
+    Checkbox(CheckboxNode),
+    Set(SetNode),
+    Table(TableNode),
+    Export(ExportNode),
+    For(ForNode),
+    If(IfNode),
+}
+
+impl StatementKind {
+    /// Verb keyword as written in the script, used when reporting step results.
+    pub fn verb_name(&self) -> &'static str {
+        match self {
+            StatementKind::Click(_) => "click",
+            StatementKind::Input(_) => "input",
+            StatementKind::Scroll(_) => "scroll",
+            StatementKind::Hover(_) => "hover",
+            StatementKind::Copy(_) => "copy",
+            StatementKind::Read(_) => "read",
+            StatementKind::Paste(_) => "paste",
+            StatementKind::Press(_) => "press",
+            StatementKind::Drag(_) => "drag",
+            StatementKind::Check(_) => "check",
+            StatementKind::Wait(_) => "wait",
+            StatementKind::Select(_) => "select",
+            StatementKind::Checkbox(_) => "checkbox",
+            StatementKind::Set(_) => "set",
+            StatementKind::Table(_) => "table",
+            StatementKind::Export(_) => "export",
+            StatementKind::For(_) => "for",
+            StatementKind::If(_) => "if",
+        }
+    }
+
+    /// Every target the verb acts on or waits for.
+    pub fn targets(&self) -> Vec<&Target> {
+        match self {
+            StatementKind::Click(node) => match &node.menu_item {
+                Some(item) => vec![&node.target, item],
+                None => vec![&node.target],
+            },
+            StatementKind::Input(node) => vec![&node.target],
+            StatementKind::Scroll(node) => match &node.mode {
+                ScrollMode::Seek(target) => vec![target],
+                ScrollMode::Iterative(_) => vec![],
+            },
+            StatementKind::Hover(node) => match &node.until {
+                HoverUntil::Visible(template) => vec![&node.target, template],
+                HoverUntil::Changed(_) => vec![&node.target],
+            },
+            StatementKind::Copy(node) => vec![&node.from, &node.to],
+            StatementKind::Read(node) => match &node.area {
+                ReadArea::Relative { anchor, .. } => vec![anchor],
+                ReadArea::Region(_) | ReadArea::Cell(_) => vec![],
+            },
+            StatementKind::Paste(node) => vec![&node.target],
+            StatementKind::Drag(node) => vec![&node.from, &node.to],
+            StatementKind::Check(node) => node.probe.targets(),
+            StatementKind::Select(node) => match &node.option {
+                SelectOption::Template(option) => vec![&node.target, option],
+                SelectOption::Text(_) => vec![&node.target],
+            },
+            StatementKind::Checkbox(node) => vec![&node.checked, &node.unchecked],
+            StatementKind::Wait(node) => match &node.until {
+                WaitUntil::Visible(target) | WaitUntil::Gone(target) => vec![target],
+                WaitUntil::Stable { .. } => vec![],
+            },
+            StatementKind::If(node) => node.condition.targets(),
+            StatementKind::For(node) => match &node.source {
+                LoopSource::Matches(target) => vec![target],
+                LoopSource::Csv(_) | LoopSource::Table(_) => vec![],
+            },
+            StatementKind::Press(_)
+            | StatementKind::Set(_)
+            | StatementKind::Table(_)
+            | StatementKind::Export(_) => vec![],
+        }
+    }
+
+    /// Nested statement blocks, e.g. the body of a `for` loop.
+    pub fn blocks(&self) -> Vec<&[Statement]> {
+        match self {
+            StatementKind::For(node) => vec![&node.body],
+            StatementKind::If(node) => vec![&node.then_body, &node.else_body],
+            StatementKind::Scroll(node) => vec![&node.body],
+            _ => vec![],
+        }
+    }
+}
+
+/// Options accepted by every verb, written as trailing `name=value` pairs.
+/// * `timeout`: Overrides the interpreter's default timeout in ms for this step.
+/// * `zone`: Overrides the verb's default `check_zone`, written as `rect(x, y, width, height)`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StepOptions {
+    pub timeout: Option<u64>,
+    pub check_zone: Option<Region>,
+}
+
+/// Where a verb should act.
+/// * `Template`: `template<name>`, resolved to an `ImageTemplate` by the interpreter.
+/// * `Indexed`: `template<name>[N]`, the `N`th match of the template in reading order, counting
+///   from 1. `N` may be a `${variable}`.
+/// * `Absolute`: two bare numbers (`click 120 40`), resolved to an `AbsoluteLocation`.
+/// * `Edges`: `template<name>.edges()`, the template located by its outline rather than its
+///   pixels, see `EdgeParsingStrategy`. `template<name>.edges("Save")` only matches outlines
+///   holding the text `Save`, which may contain `${variable}` references.
+/// * `Text`: `text<"...">`, text on screen read with OCR.
+/// * `Cell`: `NAME[N].col(COLUMN)`, the centre of a cell of a table defined with `table`.
+/// * `Element`: `panel("TITLE").KIND[N]`, an element found by parsing the screen, e.g.
+///   `panel("Demographics").text_field[2]`.
+/// * `Relative`: a template, text or cell target followed by offsets, e.g.
+///   `template<mrn-label>.right(40).below(10)`, resolved to a point beside the anchor's match.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Target {
+    Template(String),
+    Indexed {
+        name: String,
+        index: Text,
+    },
+    Absolute {
+        x: f64,
+        y: f64,
+    },
+    Edges {
+        name: String,
+        label: Option<Text>,
+    },
+    Text(Text),
+    Cell(CellRef),
+    Element(ElementRef),
+    Relative {
+        anchor: Box<Target>,
+        offsets: Vec<Offset>,
+    },
+}
+
+impl Target {
+    /// Name of the template the target refers to, if any.
+    pub fn template_name(&self) -> Option<&String> {
+        match self {
+            Target::Template(name) | Target::Indexed { name, .. } | Target::Edges { name, .. } => {
+                Some(name)
+            }
+            Target::Relative { anchor, .. } => anchor.template_name(),
+            Target::Absolute { .. } | Target::Text(_) | Target::Cell(_) | Target::Element(_) => {
+                None
+            }
+        }
+    }
+}
+
+/// `NAME[N].col(COLUMN)`: the cell in data row `N` of table `NAME`, counting from 1, and the column
+/// headed `COLUMN`. Both `N` and `COLUMN` may contain `${variable}` references.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellRef {
+    pub table: String,
+    pub row: Text,
+    pub column: Text,
+}
+
+/// `panel("TITLE").KIND[N]`: the `N`th element of a kind inside the panel titled `TITLE`, counting
+/// from 1 in reading order. `[N]` is optional and defaults to 1. Both `TITLE` and `N` may contain
+/// `${variable}` references.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementRef {
+    pub panel: Text,
+    pub kind: ElementKind,
+    pub index: Text,
+}
+
+/// Kind of element an `ElementRef` picks, written `button`, `text_field`, `checkbox` or `text`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementKind {
+    Button,
+    TextField,
+    Checkbox,
+    Text,
+}
+
+/// `.left(N)`, `.right(N)`, `.above(N)` or `.below(N)` after a target: `N` pixels beyond that edge
+/// of the target's match.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Offset {
+    pub side: Side,
+    pub distance: f64,
+}
+
+/// A rectangle on screen written as `rect(x, y, width, height)`; converted into a `ScreenRect`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Region {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+/// `click <target> [double|triple] [with MODIFIERS] [button=left|right|middle]`
+/// -> `verb::click::Click`, where `MODIFIERS` are joined by `+`, e.g. `with ctrl+shift`.
+/// `click <target> menu <template>` -> `verb::menu::ContextMenu`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClickNode {
+    pub target: Target,
+    pub button: MouseButton,
+    pub clicks: u32,
+    pub modifiers: Option<String>,
+    pub menu_item: Option<Target>,
+}
+
+/// A string that may contain `${variable}` references, resolved when the statement runs.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Text {
+    pub parts: Vec<TextPart>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TextPart {
+    Literal(String),
+    Variable(String),
+}
+
+impl Text {
+    pub fn literal(value: &str) -> Self {
+        let mut text = Text::default();
+        text.push_str(value);
+        text
+    }
+
+    pub fn push_str(&mut self, value: &str) {
+        if value.is_empty() {
+            return;
+        }
+        match self.parts.last_mut() {
+            Some(TextPart::Literal(literal)) => literal.push_str(value),
+            _ => self.parts.push(TextPart::Literal(value.to_string())),
+        }
+    }
+
+    pub fn push_char(&mut self, c: char) {
+        self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    pub fn push_variable(&mut self, name: String) {
+        self.parts.push(TextPart::Variable(name));
+    }
+
+    /// The text as a plain string, if it contains no variable references.
+    pub fn as_literal(&self) -> Option<String> {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                TextPart::Literal(literal) => Some(literal.as_str()),
+                TextPart::Variable(_) => None,
+            })
+            .collect()
+    }
+}
+
+impl Display for Text {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for part in &self.parts {
+            match part {
+                TextPart::Literal(literal) => write!(f, "{}", literal)?,
+                TextPart::Variable(name) => write!(f, "${{{}}}", name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `input <target> <text> [submit]` -> `verb::input::Input`
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputNode {
+    pub target: Target,
+    pub text: Text,
+    pub submit: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// The two scroll variants implemented in `verb::scroll`.
+/// * `Seek`: `scroll down until template<name>` scrolls until the template appears.
+/// * `Iterative`: `scroll down in rect(...)` scrolls until the region stops changing.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScrollMode {
+    Seek(Target),
+    Iterative(Region),
+}
+
+/// `scroll <direction> (until <template> | in <region>) [max=N] [step=N] [do ... end]`
+/// An iterative scroll may end with `do`, in which case `body` runs once for the initial view and
+/// again after every scroll that moved the region.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScrollNode {
+    pub direction: ScrollDirection,
+    pub mode: ScrollMode,
+    pub max_scrolls: Option<u32>,
+    pub step: Option<u32>,
+    pub body: Vec<Statement>,
+}
+
+/// What a `hover` waits for after resting on its target.
+/// * `Visible`: `until template<name>`, e.g. a tooltip or flyout.
+/// * `Changed`: `until changed rect(...)`, a region separate from the target.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HoverUntil {
+    Visible(Target),
+    Changed(Region),
+}
+
+/// `hover <target> until (<template> | changed <region>) [dwell=N]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct HoverNode {
+    pub target: Target,
+    pub until: HoverUntil,
+    /// Time in ms to rest on the target before checking.
+    pub dwell: Option<u64>,
+}
+
+/// `copy <target> to <target> into NAME [hold=N]`: drag-selects between the two targets, copies
+/// the selection and stores the clipboard text in variable `NAME`. A `zone` must be stable before
+/// the selection starts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CopyNode {
+    pub from: Target,
+    pub to: Target,
+    pub variable: String,
+    /// Time in ms to hold the button at `to` so scrolling text frames extend the selection.
+    pub hold: Option<u64>,
+}
+
+/// Side of an anchor that an offset or a `read` region lies on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+    Above,
+    Below,
+}
+
+/// Corner of a `read` region placed at a target, written as the `anchor` option.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RectAnchor {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+/// How a `read` region is placed against its target.
+/// * `Beside`: `right of <target>`, or likewise `left`, `above` or `below`: next to the target's
+///   match.
+/// * `At`: `at <target>`: with the corner given by the `anchor` option (default `top-left`) at the
+///   target's location, usually a relative target such as `template<label>.right(10)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Beside(Side),
+    At(RectAnchor),
+}
+
+/// Where a `read` statement reads.
+/// * `Region`: `rect(...)`.
+/// * `Relative`: a box placed against a target, sized by the `width` and `height` options.
+/// * `Cell`: a cell of a table, e.g. `read labs[3].col("Result") into potassium`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReadArea {
+    Region(Region),
+    Cell(CellRef),
+    Relative {
+        placement: Placement,
+        anchor: Target,
+        width: Option<u64>,
+        height: Option<u64>,
+    },
+}
+
+/// Type a `read` statement converts its value to, written as `as number` or `as date`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    Text,
+    Number,
+    Date,
+}
+
+/// `read <area> into NAME [as text|number|date] [matching /pattern/] [width=N] [height=N]
+/// [anchor=CORNER]`
+/// -> `verb::read::ReadText`: reads the area with OCR and stores the value in variable `NAME`.
+/// With a pattern, the value is its first capture group, or the whole match if it has none.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadNode {
+    pub area: ReadArea,
+    pub variable: String,
+    pub value_type: ValueType,
+    pub pattern: Option<String>,
+}
+
+/// `paste <target> [text]`: pastes `text`, or the current clipboard when omitted, into the target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasteNode {
+    pub target: Target,
+    pub text: Option<Text>,
+}
+
+/// `press <chord> [xN]`: presses a key chord such as `ctrl+shift+F5`, `N` times in a row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PressNode {
+    pub chord: String,
+    pub repeat: Option<u32>,
+}
+
+/// The option a `select` statement chooses, either by image or by its label.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectOption {
+    Template(Target),
+    Text(Text),
+}
+
+/// `select <target> (<template> | text) [popup=rect(...)] [max=N]` -> `verb::select::Select`:
+/// opens the dropdown at `target` and chooses the option, scrolling the popup at most `N` times.
+/// The `zone` option is where the dropdown shows its value, which must change after choosing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelectNode {
+    pub target: Target,
+    pub option: SelectOption,
+    pub popup: Option<Region>,
+    pub max_scrolls: Option<u32>,
+}
+
+/// `checkbox <checked-template> <unchecked-template> (checked | unchecked) [confidence=N]`
+/// -> `verb::checkbox::SetChecked`: clicks the control only if it is not already in that state.
+/// The `zone` option is the control's region.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckboxNode {
+    pub checked: Target,
+    pub unchecked: Target,
+    pub desired: bool,
+    /// Minimum match confidence between 0 and 1.
+    pub confidence: Option<f64>,
+}
+
+/// What a `wait` statement waits for.
+/// * `Visible`: `wait for <template> [into NAME]` -> `verb::wait::WaitFor`
+/// * `Gone`: `wait until <template> gone` -> `verb::wait::WaitUntilGone`
+/// * `Stable`: `wait until rect(...) stable [quiet=MS]` -> `verb::wait::WaitStable`
+#[derive(Debug, Clone, PartialEq)]
+pub enum WaitUntil {
+    Visible(Target),
+    Gone(Target),
+    Stable { region: Region, quiet: Option<u64> },
+}
+
+/// `wait ... [poll=MS]`: blocks until the condition holds or the step times out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WaitNode {
+    pub until: WaitUntil,
+    /// Variable bound to the centre of the template once it appears, as `NAME.x` and `NAME.y`.
+    pub variable: Option<String>,
+    /// Time in ms between polls of the screen.
+    pub poll: Option<u64>,
+}
+
+/// `drag <target> to <target> [speed=N]`: drags with the left button from one target to the other.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DragNode {
+    pub from: Target,
+    pub to: Target,
+    /// Cursor speed in pixels per second.
+    pub speed: Option<u64>,
+}
+
+/// `check <probe> [<expectation>]`: asserts that a value read from the screen meets the
+/// expectation, e.g. `check text rect(...) >= 7.0`. Without an expectation the probe must be a
+/// template, which must be visible.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckNode {
+    pub probe: Probe,
+    pub expect: Option<Expectation>,
+}
+
+/// Value read from the screen by `check` and by `if` conditions.
+/// * `Template`: `template<name>`, `true` when the template is found.
+/// * `Text`: `text rect(...)`, the OCR'd text of the region.
+/// * `Pixel`: `pixel x y`, the pixel color as `#rrggbb`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Probe {
+    Template(Target),
+    Text(Region),
+    Pixel(Target),
+}
+
+impl Probe {
+    pub fn targets(&self) -> Vec<&Target> {
+        match self {
+            Probe::Template(target) | Probe::Pixel(target) => vec![target],
+            Probe::Text(_) => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Test applied to a probed value.
+/// * `Compare`: `== != < <= > >=` against a number, a date or, for `==` and `!=`, any text.
+/// * `Contains`: `contains "value"`, ignoring case.
+/// * `Is`: `is "value"`, whole-value equality ignoring case.
+/// * `Matches`: `matches /pattern/`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expectation {
+    Compare(Comparison, Text),
+    Contains(Text),
+    Is(Text),
+    Matches(String),
+}
+
+impl Expectation {
+    /// The text the expectation compares against, if any.
+    pub fn text(&self) -> Option<&Text> {
+        match self {
+            Expectation::Compare(_, text) | Expectation::Contains(text) | Expectation::Is(text) => {
+                Some(text)
+            }
+            Expectation::Matches(_) => None,
+        }
+    }
+}
+
+/// `set <name> = <text>`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SetNode {
+    pub name: String,
+    pub value: Text,
+}
+
+/// A column of a `table` statement, written as its name and optional width, e.g. `Result=80` or
+/// `"Ref Range"`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableColumn {
+    pub name: String,
+    pub width: Option<f64>,
+}
+
+/// `table NAME rect(x, y, width, height) columns(COLUMN, ...) [header=N] [pitch=N]`
+/// -> `nav::table::Table`: defines a table that later statements address by name. Columns without
+/// a width share the width the others leave. `header` is the height of the header row inside the
+/// rect; `pitch` the height of every data row, which are otherwise split at ruling lines.
+///
+/// `table NAME rect(x, y, width, height) detect [columns(NAME, ...)]` -> `Table::detected`:
+/// detects the columns, header and rows on screen instead. Columns are named after their header
+/// cells unless named here, left to right.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableNode {
+    pub name: String,
+    pub region: Region,
+    pub columns: Vec<TableColumn>,
+    pub header: Option<u64>,
+    pub pitch: Option<u64>,
+    pub detect: bool,
+}
+
+/// `export NAME to "file.csv"`: reads every row of table `NAME` and writes them to a CSV file with
+/// the column names as header.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportNode {
+    pub table: String,
+    pub path: String,
+}
+
+/// Rows a `for` loop iterates over.
+/// * `Csv`: `csv("mrns.csv")`, one iteration per data row. Columns are available as
+///   `${row.<header>}` where `row` is the loop variable.
+/// * `Matches`: `template<name>`, one iteration per match on screen in reading order. The match
+///   number is available as `${hit.index}` where `hit` is the loop variable, e.g. to act on it
+///   with `template<name>[${hit.index}]`, along with its centre as `${hit.x}` and `${hit.y}`.
+/// * `Table`: the name of a table, one iteration per data row read with OCR. Cells are available as
+///   `${row.<column>}` and the row number as `${row.index}`, e.g. to act on a cell with
+///   `labs[${row.index}].col("Result")`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoopSource {
+    Csv(String),
+    Matches(Target),
+    Table(String),
+}
+
+/// `for <variable> in <source>` followed by the body and a closing `end`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForNode {
+    pub variable: String,
+    pub source: LoopSource,
+    pub body: Vec<Statement>,
+}
+
+/// Screen state tested by an `if` statement.
+/// * `Visible`: `visible template<name>`, the template is currently found by its `LocationStrategy`.
+/// * `Absent`: `absent template<name>`, the opposite of `Visible`.
+/// * `Check`: A probe and expectation as in `check`, e.g. `text rect(...) contains "value"` or
+///   `pixel 10 20 == "#ffffff"`.
+/// * `Changed`: `changed rect(...)`, the region changes before the statement's timeout.
+/// * `Not`: `not <condition>`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Visible(Target),
+    Absent(Target),
+    Check(Probe, Expectation),
+    Changed(Region),
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    pub fn targets(&self) -> Vec<&Target> {
+        match self {
+            Condition::Visible(target) | Condition::Absent(target) => vec![target],
+            Condition::Not(condition) => condition.targets(),
+            Condition::Check(probe, _) => probe.targets(),
+            Condition::Changed(_) => vec![],
+        }
+    }
+}
+
+/// `if <condition>`, the statements to run when it holds, an optional `else` block and `end`.
+/// `else if <condition>` chains are stored as a nested `IfNode` in `else_body`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IfNode {
+    pub condition: Condition,
+    pub then_body: Vec<Statement>,
+    pub else_body: Vec<Statement>,
+}
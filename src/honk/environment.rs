@@ -0,0 +1,51 @@
+//! Variables visible to a running Honk script.
+use crate::errors::HonkRuntimeError;
+use crate::honk::ast::{Text, TextPart};
+use std::collections::HashMap;
+
+/// Flat map of variable names to string values.
+/// Records such as CSV rows are stored as dotted names, so column `mrn` of loop variable `row` is
+/// the variable `row.mrn`.
+#[derive(Debug, Default)]
+pub struct Environment {
+    variables: HashMap<String, String>,
+}
+
+impl Environment {
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.variables.get(name).map(|value| value.as_str())
+    }
+
+    pub fn set(&mut self, name: &str, value: String) {
+        self.variables.insert(name.to_string(), value);
+    }
+
+    /// Binds every `(field, value)` pair as `name.field`, replacing any previous record `name`.
+    pub fn set_record(&mut self, name: &str, fields: &[(String, String)]) {
+        self.remove_record(name);
+        for (field, value) in fields {
+            self.set(&format!("{}.{}", name, field), value.clone());
+        }
+    }
+
+    pub fn remove_record(&mut self, name: &str) {
+        let prefix = format!("{}.", name);
+        self.variables.retain(|key, _| !key.starts_with(&prefix));
+    }
+
+    /// Resolves every `${variable}` in `text`. Errors if a variable is not defined.
+    pub fn interpolate(&self, text: &Text) -> Result<String, HonkRuntimeError> {
+        let mut output = String::new();
+        for part in &text.parts {
+            match part {
+                TextPart::Literal(literal) => output.push_str(literal),
+                TextPart::Variable(name) => {
+                    output.push_str(self.get(name).ok_or_else(|| HonkRuntimeError {
+                        message: format!("Variable '{}' is not defined", name),
+                    })?)
+                }
+            }
+        }
+        Ok(output)
+    }
+}
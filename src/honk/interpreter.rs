@@ -0,0 +1,1404 @@
+//! Executes parsed Honk scripts by firing the matching GUI verbs in order.
+use crate::clipboard::read_clipboard;
+use crate::errors::{
+    HonkRuntimeError, OutOfBoundsError, TableError, TemplateNotFoundError, UIActionTimeOutError,
+};
+use crate::honk::ast::{
+    CellRef, CheckNode, Comparison, Condition, ElementKind, ElementRef, Expectation, ForNode,
+    HoverUntil, IfNode, LoopSource, MouseButton, PasteNode, Placement, Probe, ReadArea, ReadNode,
+    RectAnchor, Region, Script, ScrollDirection, ScrollMode, ScrollNode, SelectNode, SelectOption,
+    Side, Statement, StatementKind, TableNode, Target, Text, ValueType, WaitNode, WaitUntil,
+};
+use crate::honk::environment::Environment;
+use crate::honk::registry::TemplateRegistry;
+use crate::nav::coordinate::{Coordinate, PointAsRectAnchor, ScreenRect};
+use crate::nav::location::{
+    self, AbsoluteLocation, GetLocation, ImageTemplate, RelativeRegion, TargetFactory,
+};
+use crate::nav::parse::{self, ElementQuery};
+use crate::nav::strategy::{LocationStrategyType, TextLocationStrategy};
+use crate::nav::table::{Column, RowLayout, Table};
+use crate::verb::action::{CheckUIState, GuiVerb};
+use crate::verb::check::{self, Check};
+use crate::verb::checkbox::SetChecked;
+use crate::verb::click::Click;
+use crate::verb::copy::CopyText;
+use crate::verb::drag::Drag;
+use crate::verb::hover::{Hover, HoverExpectation};
+use crate::verb::input::Input;
+use crate::verb::menu::ContextMenu;
+use crate::verb::paste::PasteText;
+use crate::verb::press::{parse_modifiers, Press};
+use crate::verb::read::{self, ReadRegion, ReadText, Reading};
+use crate::verb::scroll::{self, IterativeScroll, SeekScroll};
+use crate::verb::select::{OptionLocator, Select};
+use crate::verb::wait::{WaitFor, WaitStable, WaitUntilGone};
+use autopilot::mouse::Button;
+use regex::Regex;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepStatus {
+    Passed,
+    Failed,
+    /// Not executed, either because it precedes the start label or follows a failed step.
+    Skipped,
+}
+
+/// Outcome of a single statement.
+#[derive(Debug)]
+pub struct StepResult {
+    pub line: usize,
+    pub label: Option<String>,
+    pub verb: &'static str,
+    pub status: StepStatus,
+    pub duration: Duration,
+    /// The error raised by the verb, e.g. `UIActionTimeOutError` or `OutOfBoundsError`.
+    /// Callers can `downcast_ref` to inspect the concrete type.
+    pub error: Option<Box<dyn Error>>,
+    /// One entry per row for `for` loops, or per pass of a `scroll ... do` block; empty for every
+    /// other statement.
+    pub iterations: Vec<IterationResult>,
+    /// For `if` statements, whether the condition held. `None` when it was not evaluated.
+    pub condition: Option<bool>,
+    /// For `if` statements, the steps of the branch that ran.
+    pub body: Vec<StepResult>,
+    /// For `read` statements, the value stored along with the text and confidence OCR reported.
+    pub reading: Option<Reading>,
+}
+
+impl StepResult {
+    fn skipped(statement: &Statement) -> Self {
+        StepResult {
+            line: statement.span.line,
+            label: statement.label.clone(),
+            verb: statement.kind.verb_name(),
+            status: StepStatus::Skipped,
+            duration: Duration::ZERO,
+            error: None,
+            iterations: Vec::new(),
+            condition: None,
+            body: Vec::new(),
+            reading: None,
+        }
+    }
+
+    /// The innermost failed step, following failures into `if` branches.
+    pub fn root_cause(&self) -> &StepResult {
+        self.body
+            .iter()
+            .find(|step| step.status == StepStatus::Failed)
+            .map_or(self, |step| step.root_cause())
+    }
+}
+
+impl Display for StepResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {} {:?}", self.line, self.verb, self.status)?;
+        if let Some(label) = &self.label {
+            write!(f, " [{}]", label)?;
+        }
+        if let Some(holds) = self.condition {
+            write!(f, " (condition {})", holds)?;
+        }
+        if let Some(reading) = &self.reading {
+            write!(
+                f,
+                " (read {:?} from {:?}, confidence {:.2})",
+                reading.value, reading.raw, reading.confidence
+            )?;
+        }
+        write!(f, " ({}ms)", self.duration.as_millis())?;
+        if let Some(error) = &self.error {
+            write!(f, " - {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of one pass through a loop body, recorded against the row that drove it.
+#[derive(Debug)]
+pub struct IterationResult {
+    /// 1-based index of the data row in the loop source, or of the pass for `scroll ... do`.
+    pub row: usize,
+    /// Column name and value pairs of the row.
+    pub values: Vec<(String, String)>,
+    pub steps: Vec<StepResult>,
+}
+
+impl IterationResult {
+    pub fn is_success(&self) -> bool {
+        self.failure().is_none()
+    }
+
+    pub fn failure(&self) -> Option<&StepResult> {
+        self.steps
+            .iter()
+            .find(|step| step.status == StepStatus::Failed)
+    }
+}
+
+/// Outcome of a whole script run, one `StepResult` per top-level statement.
+#[derive(Debug, Default)]
+pub struct RunResult {
+    pub steps: Vec<StepResult>,
+    pub duration: Duration,
+}
+
+impl RunResult {
+    pub fn is_success(&self) -> bool {
+        self.steps
+            .iter()
+            .all(|step| step.status != StepStatus::Failed)
+    }
+
+    /// The step that stopped the run, if any.
+    pub fn failure(&self) -> Option<&StepResult> {
+        self.steps
+            .iter()
+            .find(|step| step.status == StepStatus::Failed)
+    }
+}
+
+/// Walks a `Script` and fires each verb through `GuiVerb::fire`.
+/// Execution stops at the first failing step; the remaining steps are reported as skipped.
+/// A failing step inside a `for` loop only ends that iteration: the loop moves on to the next row
+/// and fails once every row has been processed.
+/// Parameters:
+/// * `templates`: Registry used to resolve `template<name>` references.
+/// * `default_timeout`: Optional. Timeout in ms passed to each verb unless the statement sets
+///   `timeout=`. When not provided, each verb falls back to its own default.
+/// * `dry_run`: Optional. When true, templates are resolved but no verb is fired. Default false.
+pub struct Interpreter {
+    templates: TemplateRegistry,
+    env: Environment,
+    /// Tables defined by `table` statements so far, by name.
+    tables: HashMap<String, TableNode>,
+    /// Tables built for the current statement, by name, so that a detected table is detected once
+    /// however many cells the statement refers to. Tables being looped over stay for the loop.
+    built_tables: RefCell<HashMap<String, Rc<Table>>>,
+    /// Names of the tables whose loops are running, innermost last.
+    looped_tables: Vec<String>,
+    default_timeout: Option<u64>,
+    dry_run: bool,
+}
+
+impl Interpreter {
+    pub fn new(
+        templates: TemplateRegistry,
+        default_timeout: Option<u64>,
+        dry_run: Option<bool>,
+    ) -> Self {
+        Interpreter {
+            templates,
+            env: Environment::default(),
+            tables: HashMap::new(),
+            built_tables: RefCell::new(HashMap::new()),
+            looped_tables: Vec::new(),
+            default_timeout,
+            dry_run: dry_run.unwrap_or(false),
+        }
+    }
+
+    pub fn env(&self) -> &Environment {
+        &self.env
+    }
+
+    pub fn env_mut(&mut self) -> &mut Environment {
+        &mut self.env
+    }
+
+    /// Runs every statement of `script` from the top.
+    pub fn run(&mut self, script: &Script) -> RunResult {
+        self.run_script(script, 0)
+    }
+
+    /// Runs `script` starting at the statement labelled `label` (e.g. `begin`).
+    /// Statements before the label are reported as skipped.
+    pub fn run_from(
+        &mut self,
+        script: &Script,
+        label: &str,
+    ) -> Result<RunResult, HonkRuntimeError> {
+        let start = script.find_label(label).ok_or_else(|| HonkRuntimeError {
+            message: format!("Label '{}' does not exist in script", label),
+        })?;
+        Ok(self.run_script(script, start))
+    }
+
+    fn run_script(&mut self, script: &Script, start: usize) -> RunResult {
+        let run_start = Instant::now();
+        let steps = self.run_block(&script.statements, start);
+        RunResult {
+            steps,
+            duration: run_start.elapsed(),
+        }
+    }
+
+    fn run_block(&mut self, statements: &[Statement], start: usize) -> Vec<StepResult> {
+        let mut steps = Vec::new();
+        let mut failed = false;
+
+        for (index, statement) in statements.iter().enumerate() {
+            let mut step = StepResult::skipped(statement);
+
+            if index >= start && !failed {
+                // The screen may have changed since tables were built for the last statement.
+                let looped = &self.looped_tables;
+                self.built_tables
+                    .get_mut()
+                    .retain(|name, _| looped.contains(name));
+                let step_start = Instant::now();
+                match self.execute(statement, &mut step) {
+                    Ok(()) => step.status = StepStatus::Passed,
+                    Err(error) => {
+                        step.status = StepStatus::Failed;
+                        step.error = Some(error);
+                        failed = true;
+                    }
+                }
+                step.duration = step_start.elapsed();
+            }
+            steps.push(step);
+        }
+        steps
+    }
+
+    fn execute(
+        &mut self,
+        statement: &Statement,
+        step: &mut StepResult,
+    ) -> Result<(), Box<dyn Error>> {
+        let timeout = statement.options.timeout.or(self.default_timeout);
+        match &statement.kind {
+            StatementKind::Set(node) => {
+                let value = self.env.interpolate(&node.value)?;
+                self.env.set(&node.name, value);
+                Ok(())
+            }
+            StatementKind::Table(node) => {
+                self.tables.insert(node.name.clone(), node.clone());
+                self.built_tables.get_mut().remove(&node.name);
+                Ok(())
+            }
+            StatementKind::For(node) => self.run_loop(node, step),
+            StatementKind::If(node) => self.run_if(statement, node, step),
+            StatementKind::Scroll(node) => self.run_scroll(statement, node, step),
+            // Verbs never touch the screen in a dry run.
+            _ if self.dry_run => self.dry_run_verb(statement),
+            StatementKind::Click(node) => match &node.menu_item {
+                Some(item) => {
+                    let menu = ContextMenu::new(
+                        self.target_factory(&node.target)?,
+                        self.template(item)?,
+                        check_zone(statement)?,
+                    )?;
+                    menu.fire(timeout)
+                }
+                None => {
+                    let modifiers = match &node.modifiers {
+                        Some(modifiers) => Some(parse_modifiers(modifiers)?),
+                        None => None,
+                    };
+                    let click = Click::new(
+                        self.target_factory(&node.target)?,
+                        mouse_button(node.button),
+                        check_zone(statement)?,
+                        Some(node.clicks),
+                        modifiers,
+                    )?;
+                    click.fire(timeout)
+                }
+            },
+            StatementKind::Input(node) => {
+                let input = Input::new(
+                    self.target_factory(&node.target)?,
+                    self.env.interpolate(&node.text)?,
+                    Some(node.submit),
+                    check_zone(statement)?,
+                )?;
+                input.fire(timeout)
+            }
+            StatementKind::Hover(node) => {
+                let expect = match &node.until {
+                    HoverUntil::Visible(template) => {
+                        HoverExpectation::Template(self.template(template)?)
+                    }
+                    HoverUntil::Changed(region) => {
+                        HoverExpectation::RegionChange(region_to_rect(*region)?)
+                    }
+                };
+                let hover = Hover::new(self.target_factory(&node.target)?, expect, node.dwell)?;
+                hover.fire(timeout)
+            }
+            StatementKind::Copy(node) => {
+                let copy = CopyText::new(
+                    self.target_factory(&node.from)?,
+                    self.target_factory(&node.to)?,
+                    node.hold,
+                    check_zone(statement)?,
+                )?;
+                copy.fire(timeout)?;
+                self.env.set(&node.variable, read_clipboard()?);
+                Ok(())
+            }
+            StatementKind::Read(node) => {
+                let read = ReadText::new(
+                    self.read_region(&node.area)?,
+                    node.pattern.as_deref().map(Regex::new).transpose()?,
+                    Some(read_value_type(node.value_type)),
+                    None,
+                );
+                let reading = read.read_within(timeout)?;
+                self.env.set(&node.variable, reading.value.clone());
+                step.reading = Some(reading);
+                Ok(())
+            }
+            StatementKind::Paste(node) => {
+                let text = match &node.text {
+                    Some(text) => Some(self.env.interpolate(text)?),
+                    None => None,
+                };
+                let paste = PasteText::new(
+                    self.target_factory(&node.target)?,
+                    text,
+                    check_zone(statement)?,
+                )?;
+                paste.fire(timeout)
+            }
+            StatementKind::Drag(node) => {
+                let drag = Drag::new(
+                    self.target_factory(&node.from)?,
+                    self.target_factory(&node.to)?,
+                    node.speed.map(|speed| speed as f64),
+                    check_zone(statement)?,
+                )?;
+                drag.fire(timeout)
+            }
+            StatementKind::Select(node) => {
+                let option = match &node.option {
+                    SelectOption::Template(option) => {
+                        OptionLocator::Template(self.template(option)?)
+                    }
+                    SelectOption::Text(text) => OptionLocator::Text(self.env.interpolate(text)?),
+                };
+                let select = Select::new(
+                    self.target_factory(&node.target)?,
+                    option,
+                    node.popup.map(region_to_rect).transpose()?,
+                    check_zone(statement)?,
+                    node.max_scrolls,
+                )?;
+                select.fire(timeout)
+            }
+            StatementKind::Checkbox(node) => {
+                let checkbox = SetChecked::new(
+                    self.template(&node.checked)?,
+                    self.template(&node.unchecked)?,
+                    node.desired,
+                    check_zone(statement)?,
+                    node.confidence,
+                );
+                checkbox.fire(timeout)
+            }
+            StatementKind::Wait(node) => match &node.until {
+                WaitUntil::Visible(template) => {
+                    let location =
+                        WaitFor::new(self.template(template)?, node.poll).wait(timeout)?;
+                    if let Some(variable) = &node.variable {
+                        self.env
+                            .set_record(variable, &location_fields(location.x, location.y));
+                    }
+                    Ok(())
+                }
+                WaitUntil::Gone(template) => {
+                    WaitUntilGone::new(self.template(template)?, node.poll).fire(timeout)
+                }
+                WaitUntil::Stable { region, quiet } => {
+                    WaitStable::new(region_to_rect(*region)?, *quiet, node.poll).fire(timeout)
+                }
+            },
+            StatementKind::Press(node) => {
+                Press::new(&node.chord, node.repeat, check_zone(statement)?)?.fire(timeout)
+            }
+            StatementKind::Check(node) => {
+                self.check(&node.probe, node.expect.as_ref())?.fire(timeout)
+            }
+            StatementKind::Export(node) => {
+                self.table(&node.table)?.export_csv(Path::new(&node.path))?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Validates a verb instead of firing it.
+    fn dry_run_verb(&mut self, statement: &Statement) -> Result<(), Box<dyn Error>> {
+        self.validate(statement)?;
+        match &statement.kind {
+            // Bind the variable so later statements that use it still validate.
+            StatementKind::Copy(node) => self.env.set(&node.variable, String::new()),
+            StatementKind::Read(node) => self.env.set(&node.variable, String::new()),
+            StatementKind::Wait(WaitNode {
+                variable: Some(variable),
+                ..
+            }) => self.env.set_record(variable, &location_fields(0.0, 0.0)),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Runs the loop body once per row, recording each iteration against its row.
+    fn run_loop(&mut self, node: &ForNode, step: &mut StepResult) -> Result<(), Box<dyn Error>> {
+        let rows = match &node.source {
+            LoopSource::Csv(path) => read_csv(Path::new(path))?,
+            LoopSource::Matches(target) => self.match_rows(target)?,
+            LoopSource::Table(name) => self.table_rows(name)?,
+        };
+
+        let looped = match &node.source {
+            LoopSource::Table(name) => Some(name.clone()),
+            _ => None,
+        };
+        self.looped_tables.extend(looped.clone());
+        for (index, values) in rows.into_iter().enumerate() {
+            self.env.set_record(&node.variable, &values);
+            let steps = self.run_block(&node.body, 0);
+            step.iterations.push(IterationResult {
+                row: index + 1,
+                values,
+                steps,
+            });
+        }
+        if looped.is_some() {
+            self.looped_tables.pop();
+        }
+        self.env.remove_record(&node.variable);
+
+        let failed = step
+            .iterations
+            .iter()
+            .filter(|iteration| !iteration.is_success())
+            .count();
+        if failed > 0 {
+            return Err(Box::new(HonkRuntimeError {
+                message: format!("{} of {} rows failed", failed, step.iterations.len()),
+            }));
+        }
+        Ok(())
+    }
+
+    /// Fires a seek or iterative scroll. An iterative scroll with a `do` block runs the block on
+    /// the initial view and after every scroll that moved the region, stopping at the first pass
+    /// that fails. In a dry run the block is validated once without scrolling.
+    fn run_scroll(
+        &mut self,
+        statement: &Statement,
+        node: &ScrollNode,
+        step: &mut StepResult,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.dry_run {
+            self.validate(statement)?;
+            if !node.body.is_empty() {
+                self.run_scroll_pass(&node.body, 0, step);
+            }
+            return scroll_outcome(step);
+        }
+
+        let timeout = statement.options.timeout.or(self.default_timeout);
+        let direction = scroll_direction(node.direction);
+        match &node.mode {
+            ScrollMode::Seek(target) => {
+                let seek = SeekScroll::new(
+                    self.template(target)?,
+                    check_zone(statement)?,
+                    direction,
+                    node.step,
+                    node.max_scrolls,
+                    None,
+                );
+                seek.fire(timeout)
+            }
+            ScrollMode::Iterative(region) => {
+                let scroll = IterativeScroll::new(
+                    region_to_rect(*region)?,
+                    direction,
+                    node.step,
+                    node.max_scrolls,
+                    None,
+                );
+                if node.body.is_empty() {
+                    return scroll.fire(timeout);
+                }
+
+                if self.run_scroll_pass(&node.body, 0, step) {
+                    for count in 1..=scroll.max_scrolls() as usize {
+                        if !scroll.scroll_once(timeout)?
+                            || !self.run_scroll_pass(&node.body, count, step)
+                        {
+                            break;
+                        }
+                    }
+                }
+                scroll_outcome(step)
+            }
+        }
+    }
+
+    /// Runs a `scroll ... do` block after `count` scrolls. Returns whether every step passed.
+    fn run_scroll_pass(&mut self, body: &[Statement], count: usize, step: &mut StepResult) -> bool {
+        let iteration = IterationResult {
+            row: count + 1,
+            values: vec![("scroll".to_string(), count.to_string())],
+            steps: self.run_block(body, 0),
+        };
+        let passed = iteration.is_success();
+        step.iterations.push(iteration);
+        passed
+    }
+
+    /// Evaluates the condition and runs the matching branch. In a dry run both branches are
+    /// validated since the screen is never inspected.
+    fn run_if(
+        &mut self,
+        statement: &Statement,
+        node: &IfNode,
+        step: &mut StepResult,
+    ) -> Result<(), Box<dyn Error>> {
+        if self.dry_run {
+            self.validate(statement)?;
+            step.body = self.run_block(&node.then_body, 0);
+            step.body.extend(self.run_block(&node.else_body, 0));
+        } else {
+            let timeout = statement
+                .options
+                .timeout
+                .or(self.default_timeout)
+                .unwrap_or(DEFAULT_CONDITION_TIMEOUT);
+            let holds = self.evaluate(&node.condition, timeout)?;
+            step.condition = Some(holds);
+            let branch = if holds {
+                &node.then_body
+            } else {
+                &node.else_body
+            };
+            step.body = self.run_block(branch, 0);
+        }
+
+        match step.body.iter().find(|s| s.status == StepStatus::Failed) {
+            Some(failed) => Err(Box::new(HonkRuntimeError {
+                message: format!("Step on line {} failed", failed.root_cause().line),
+            })),
+            None => Ok(()),
+        }
+    }
+
+    /// Tests `condition` against the current screen.
+    /// * `timeout`: How long in ms a `changed` condition waits for the region to change.
+    fn evaluate(&self, condition: &Condition, timeout: u64) -> Result<bool, Box<dyn Error>> {
+        match condition {
+            Condition::Visible(target) => self.is_visible(target),
+            Condition::Absent(target) => Ok(!self.is_visible(target)?),
+            Condition::Not(condition) => Ok(!self.evaluate(condition, timeout)?),
+            Condition::Changed(region) => {
+                match RegionWatcher.check_ui_state(
+                    timeout,
+                    false,
+                    None,
+                    Some(region_to_rect(*region)?),
+                ) {
+                    Ok(()) => Ok(true),
+                    Err(e) if e.is::<UIActionTimeOutError>() => Ok(false),
+                    Err(e) => Err(e),
+                }
+            }
+            Condition::Check(probe, expect) => self.check(probe, Some(expect))?.evaluate(),
+        }
+    }
+
+    /// Builds the `Check` verb for a probe, interpolating variables in the expectation.
+    fn check(&self, probe: &Probe, expect: Option<&Expectation>) -> Result<Check, Box<dyn Error>> {
+        let probe = match probe {
+            Probe::Template(target) => check::Probe::Template(self.template(target)?),
+            Probe::Text(region) => check::Probe::Text(region_to_rect(*region)?),
+            Probe::Pixel(target) => {
+                check::Probe::Pixel(self.target_factory(target)?.get_location()?)
+            }
+        };
+        let expect = match expect {
+            None => None,
+            Some(Expectation::Compare(comparison, text)) => Some(check::Expectation::Compare(
+                check_comparison(*comparison),
+                self.env.interpolate(text)?,
+            )),
+            Some(Expectation::Contains(text)) => {
+                Some(check::Expectation::Contains(self.env.interpolate(text)?))
+            }
+            Some(Expectation::Is(text)) => {
+                Some(check::Expectation::Is(self.env.interpolate(text)?))
+            }
+            Some(Expectation::Matches(pattern)) => {
+                Some(check::Expectation::Matches(Regex::new(pattern)?))
+            }
+        };
+        Ok(Check::new(probe, expect))
+    }
+
+    /// Builds the region a `read` statement reads.
+    fn read_region(&self, area: &ReadArea) -> Result<ReadRegion, Box<dyn Error>> {
+        match area {
+            ReadArea::Region(region) => Ok(ReadRegion::Fixed(region_to_rect(*region)?)),
+            ReadArea::Cell(cell) => {
+                let (table, row, column) = self.cell(cell)?;
+                Ok(ReadRegion::Fixed(table.cell(row, &column)?))
+            }
+            ReadArea::Relative {
+                placement,
+                anchor,
+                width,
+                height,
+            } => Ok(ReadRegion::Relative(RelativeRegion::new(
+                self.target_factory(anchor)?,
+                match placement {
+                    Placement::Beside(side) => location::Placement::Beside(location_side(*side)),
+                    Placement::At(corner) => location::Placement::At(rect_anchor(*corner)),
+                },
+                width.map(|width| width as f64),
+                height.map(|height| height as f64),
+            ))),
+        }
+    }
+
+    /// Whether the target's location strategy finds it on screen. Only `TemplateNotFoundError`
+    /// counts as not visible; other errors, e.g. a failed screen capture, are returned.
+    fn is_visible(&self, target: &Target) -> Result<bool, Box<dyn Error>> {
+        match target {
+            Target::Template(_) | Target::Edges { .. } => {
+                Ok(self.template(target)?.find(None, None)?.is_some())
+            }
+            Target::Indexed { .. } | Target::Text(_) | Target::Element(_) | Target::Cell(_) => {
+                // Detecting a table to find a cell in fails when the table is not on screen.
+                match self
+                    .target_factory(target)
+                    .and_then(|factory| factory.get_location())
+                {
+                    Ok(_) => Ok(true),
+                    Err(e) if e.is::<TemplateNotFoundError>() || e.is::<TableError>() => Ok(false),
+                    Err(e) => Err(e),
+                }
+            }
+            Target::Absolute { .. } => Ok(self.target_factory(target)?.get_location().is_ok()),
+            Target::Relative { anchor, .. } => self.is_visible(anchor),
+        }
+    }
+
+    /// Checks that every template and variable the statement refers to exists, without touching
+    /// the screen.
+    fn validate(&self, statement: &Statement) -> Result<(), Box<dyn Error>> {
+        match &statement.kind {
+            StatementKind::Input(node) => {
+                self.env.interpolate(&node.text)?;
+            }
+            StatementKind::Check(CheckNode {
+                expect: Some(expect),
+                ..
+            }) => {
+                if let Some(text) = expect.text() {
+                    self.env.interpolate(text)?;
+                }
+            }
+            StatementKind::Paste(PasteNode {
+                text: Some(text), ..
+            })
+            | StatementKind::Select(SelectNode {
+                option: SelectOption::Text(text),
+                ..
+            }) => {
+                self.env.interpolate(text)?;
+            }
+            StatementKind::Read(ReadNode {
+                area: ReadArea::Cell(cell),
+                ..
+            }) => {
+                self.validate_cell(cell)?;
+            }
+            StatementKind::Export(node) => {
+                self.table_node(&node.table)?;
+            }
+            _ => {}
+        }
+        for target in statement.kind.targets() {
+            self.validate_target(target)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the target's template has an image and its match index, if any, resolves, or
+    /// that the variables in its text are set.
+    fn validate_target(&self, target: &Target) -> Result<(), Box<dyn Error>> {
+        match target {
+            Target::Indexed { index, .. } => {
+                self.match_index(index)?;
+            }
+            Target::Text(text)
+            | Target::Edges {
+                label: Some(text), ..
+            } => {
+                self.env.interpolate(text)?;
+            }
+            Target::Relative { anchor, .. } => {
+                self.validate_target(anchor)?;
+            }
+            Target::Cell(cell) => {
+                self.validate_cell(cell)?;
+            }
+            Target::Element(element) => {
+                self.element_query(element)?;
+            }
+            _ => {}
+        }
+        if let Some(name) = target.template_name() {
+            if self.templates.path_for(name).is_none() {
+                return Err(Box::new(HonkRuntimeError {
+                    message: format!(
+                        "No image for template<{}> in {}",
+                        name,
+                        self.templates.directory.display()
+                    ),
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolves a target that must be a template, such as the one a seek scroll looks for.
+    fn template(&self, target: &Target) -> Result<ImageTemplate, Box<dyn Error>> {
+        match target {
+            Target::Template(name) => self.templates.resolve(name),
+            Target::Edges { name, label } => self.templates.resolve_with(
+                name,
+                LocationStrategyType::EdgeParsing,
+                label
+                    .as_ref()
+                    .map(|label| self.env.interpolate(label))
+                    .transpose()?,
+            ),
+            Target::Indexed { name, index } => Err(Box::new(HonkRuntimeError {
+                message: format!(
+                    "Expected a template without an index, found template<{}>[{}]",
+                    name, index
+                ),
+            })),
+            Target::Absolute { x, y } => Err(Box::new(HonkRuntimeError {
+                message: format!("Expected a template, found coordinates {} {}", x, y),
+            })),
+            Target::Text(text) => Err(Box::new(HonkRuntimeError {
+                message: format!("Expected a template, found text<\"{}\">", text),
+            })),
+            Target::Relative { .. } => Err(Box::new(HonkRuntimeError {
+                message: "Expected a template, found a relative target".to_string(),
+            })),
+            Target::Cell(cell) => Err(Box::new(HonkRuntimeError {
+                message: format!(
+                    "Expected a template, found a cell of table '{}'",
+                    cell.table
+                ),
+            })),
+            Target::Element(element) => Err(Box::new(HonkRuntimeError {
+                message: format!(
+                    "Expected a template, found an element of panel \"{}\"",
+                    element.panel
+                ),
+            })),
+        }
+    }
+
+    fn target_factory(&self, target: &Target) -> Result<TargetFactory, Box<dyn Error>> {
+        match target {
+            Target::Template(_) | Target::Edges { .. } => {
+                Ok(TargetFactory::TemplateTarget(self.template(target)?))
+            }
+            Target::Indexed { name, index } => Ok(TargetFactory::IndexedTemplateTarget(
+                self.templates.resolve(name)?,
+                self.match_index(index)?,
+            )),
+            Target::Absolute { x, y } => Ok(TargetFactory::AbsoluteTarget(AbsoluteLocation {
+                x: Coordinate::new(*x),
+                y: Coordinate::new(*y),
+            })),
+            Target::Text(text) => Ok(TargetFactory::TextTarget(TextLocationStrategy::new(
+                self.env.interpolate(text)?,
+                None,
+                None,
+            ))),
+            Target::Cell(cell) => {
+                let (table, row, column) = self.cell(cell)?;
+                Ok(TargetFactory::CellTarget(table, row, column))
+            }
+            Target::Element(element) => {
+                Ok(TargetFactory::ElementTarget(self.element_query(element)?))
+            }
+            Target::Relative { anchor, offsets } => Ok(TargetFactory::RelativeTarget(
+                Box::new(self.target_factory(anchor)?),
+                offsets
+                    .iter()
+                    .map(|offset| (location_side(offset.side), offset.distance))
+                    .collect(),
+            )),
+        }
+    }
+
+    /// The definition of table `name` by an earlier `table` statement.
+    fn table_node(&self, name: &str) -> Result<&TableNode, HonkRuntimeError> {
+        self.tables.get(name).ok_or_else(|| HonkRuntimeError {
+            message: format!("Table '{}' is not defined", name),
+        })
+    }
+
+    /// The table defined as `name`, built at most once per statement or loop over it.
+    fn table(&self, name: &str) -> Result<Rc<Table>, Box<dyn Error>> {
+        if let Some(table) = self.built_tables.borrow().get(name) {
+            return Ok(table.clone());
+        }
+        let table = Rc::new(self.build_table(name)?);
+        self.built_tables
+            .borrow_mut()
+            .insert(name.to_string(), table.clone());
+        Ok(table)
+    }
+
+    /// Builds the table defined as `name`.
+    fn build_table(&self, name: &str) -> Result<Table, Box<dyn Error>> {
+        let node = self.table_node(name)?;
+        if node.detect {
+            let names = Some(
+                node.columns
+                    .iter()
+                    .map(|column| column.name.clone())
+                    .collect(),
+            )
+            .filter(|names: &Vec<String>| !names.is_empty());
+            return Table::detected(region_to_rect(node.region)?, names, None);
+        }
+        let columns: Vec<(String, Option<f64>)> = node
+            .columns
+            .iter()
+            .map(|column| (column.name.clone(), column.width))
+            .collect();
+        Ok(Table::new(
+            region_to_rect(node.region)?,
+            Column::side_by_side(&columns, node.region.width),
+            node.header.map(|header| header as f64),
+            node.pitch.map(|pitch| RowLayout::Fixed(pitch as f64)),
+            None,
+        ))
+    }
+
+    /// Checks that a cell reference names a defined table and one of its columns, and that its row
+    /// index resolves, without looking at the screen. Columns of a detected table without named
+    /// columns are only known on screen, so any column is accepted.
+    fn validate_cell(&self, cell: &CellRef) -> Result<(), Box<dyn Error>> {
+        let node = self.table_node(&cell.table)?;
+        self.match_index(&cell.row)?;
+        let column = self.env.interpolate(&cell.column)?;
+        let known = (node.detect && node.columns.is_empty())
+            || node
+                .columns
+                .iter()
+                .any(|c| c.name.eq_ignore_ascii_case(column.trim()));
+        if !known {
+            return Err(Box::new(HonkRuntimeError {
+                message: format!("Table '{}' has no column '{}'", cell.table, column),
+            }));
+        }
+        Ok(())
+    }
+
+    /// Resolves a cell reference to its table, 0-based row and column name.
+    fn cell(&self, cell: &CellRef) -> Result<(Rc<Table>, usize, String), Box<dyn Error>> {
+        let table = self.table(&cell.table)?;
+        let row = self.match_index(&cell.row)?;
+        let column = table
+            .column(&self.env.interpolate(&cell.column)?)?
+            .name
+            .clone();
+        Ok((table, row, column))
+    }
+
+    /// The `ElementQuery` an element target resolves to, with its title and index interpolated.
+    fn element_query(&self, element: &ElementRef) -> Result<ElementQuery, Box<dyn Error>> {
+        Ok(ElementQuery::new(
+            self.env.interpolate(&element.panel)?,
+            element_kind(element.kind),
+            self.match_index(&element.index)?,
+        ))
+    }
+
+    /// Resolves the `N` of `template<name>[N]`, counting from 1, to a 0-based match index.
+    fn match_index(&self, index: &Text) -> Result<usize, Box<dyn Error>> {
+        let value = self.env.interpolate(index)?;
+        match value.trim().parse::<usize>() {
+            Ok(number) if number > 0 => Ok(number - 1),
+            _ => Err(Box::new(HonkRuntimeError {
+                message: format!("Match index must be a number from 1, found '{}'", value),
+            })),
+        }
+    }
+
+    /// One row per data row of the table, with field `index` and one field per column. A dry run
+    /// yields a single row of empty cells so the loop body is validated once.
+    fn table_rows(&self, name: &str) -> Result<Vec<Row>, Box<dyn Error>> {
+        let (columns, rows) = if self.dry_run {
+            let columns: Vec<String> = self
+                .table_node(name)?
+                .columns
+                .iter()
+                .map(|column| column.name.clone())
+                .collect();
+            let rows = vec![vec![String::new(); columns.len()]];
+            (columns, rows)
+        } else {
+            let table = self.table(name)?;
+            let columns = table.columns().iter().map(|column| column.name.clone());
+            (columns.collect(), table.read_rows()?)
+        };
+        Ok(rows
+            .into_iter()
+            .enumerate()
+            .map(|(index, cells)| {
+                let mut row = vec![("index".to_string(), (index + 1).to_string())];
+                row.extend(columns.iter().cloned().zip(cells));
+                row
+            })
+            .collect())
+    }
+
+    /// One row per match of the template in reading order, with fields `index`, `x` and `y`.
+    /// A dry run yields a single placeholder row so the loop body is validated once.
+    fn match_rows(&self, target: &Target) -> Result<Vec<Row>, Box<dyn Error>> {
+        if self.dry_run {
+            self.validate_target(target)?;
+            return Ok(vec![match_row(1, 0.0, 0.0)]);
+        }
+        Ok(self
+            .template(target)?
+            .find_all(None, None)?
+            .into_iter()
+            .enumerate()
+            .map(|(index, location)| match_row(index + 1, location.x, location.y))
+            .collect())
+    }
+}
+
+/// Timeout in ms for conditions that wait on the screen when neither the statement nor the
+/// interpreter sets one.
+const DEFAULT_CONDITION_TIMEOUT: u64 = 1000;
+
+/// Watches a region of the screen for `changed` conditions.
+struct RegionWatcher;
+
+impl CheckUIState for RegionWatcher {}
+
+/// Fails a `scroll ... do` statement whose block failed on any pass.
+fn scroll_outcome(step: &StepResult) -> Result<(), Box<dyn Error>> {
+    match step
+        .iterations
+        .iter()
+        .find_map(|iteration| iteration.failure())
+    {
+        Some(failed) => Err(Box::new(HonkRuntimeError {
+            message: format!("Step on line {} failed", failed.root_cause().line),
+        })),
+        None => Ok(()),
+    }
+}
+
+/// Column name and value pairs of one data row.
+type Row = Vec<(String, String)>;
+
+fn match_row(index: usize, x: f64, y: f64) -> Row {
+    let mut row = vec![("index".to_string(), index.to_string())];
+    row.extend(location_fields(x, y));
+    row
+}
+
+/// Fields `x` and `y` of a location on screen, in whole pixels.
+fn location_fields(x: f64, y: f64) -> Row {
+    vec![
+        ("x".to_string(), (x.round() as i64).to_string()),
+        ("y".to_string(), (y.round() as i64).to_string()),
+    ]
+}
+
+/// Reads a CSV file with a header row into one `Row` per data row.
+fn read_csv(path: &Path) -> Result<Vec<Row>, Box<dyn Error>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let headers: Vec<String> = reader
+        .headers()?
+        .iter()
+        .map(|header| header.trim().to_string())
+        .collect();
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        rows.push(
+            headers
+                .iter()
+                .cloned()
+                .zip(record.iter().map(|value| value.to_string()))
+                .collect(),
+        );
+    }
+    Ok(rows)
+}
+
+/// The `ScreenRect` of a `rect(...)` written in a script. Errors with `OutOfBoundsError` if its top
+/// left corner is off the screen.
+pub fn region_to_rect(region: Region) -> Result<ScreenRect, OutOfBoundsError> {
+    ScreenRect::on_screen(region.x, region.y, region.width, region.height)
+}
+
+/// The rect given by a statement's `check_zone` option, if any.
+fn check_zone(statement: &Statement) -> Result<Option<ScreenRect>, OutOfBoundsError> {
+    statement.options.check_zone.map(region_to_rect).transpose()
+}
+
+fn scroll_direction(direction: ScrollDirection) -> scroll::ScrollDirection {
+    match direction {
+        ScrollDirection::Up => scroll::ScrollDirection::Up,
+        ScrollDirection::Down => scroll::ScrollDirection::Down,
+        ScrollDirection::Left => scroll::ScrollDirection::Left,
+        ScrollDirection::Right => scroll::ScrollDirection::Right,
+    }
+}
+
+fn location_side(side: Side) -> location::Side {
+    match side {
+        Side::Left => location::Side::Left,
+        Side::Right => location::Side::Right,
+        Side::Above => location::Side::Above,
+        Side::Below => location::Side::Below,
+    }
+}
+
+fn element_kind(kind: ElementKind) -> parse::ElementKind {
+    match kind {
+        ElementKind::Button => parse::ElementKind::Button,
+        ElementKind::TextField => parse::ElementKind::TextField,
+        ElementKind::Checkbox => parse::ElementKind::Checkbox,
+        ElementKind::Text => parse::ElementKind::Text,
+    }
+}
+
+fn rect_anchor(anchor: RectAnchor) -> PointAsRectAnchor {
+    match anchor {
+        RectAnchor::TopLeft => PointAsRectAnchor::TopLeft,
+        RectAnchor::TopRight => PointAsRectAnchor::TopRight,
+        RectAnchor::BottomLeft => PointAsRectAnchor::BottomLeft,
+        RectAnchor::BottomRight => PointAsRectAnchor::BottomRight,
+        RectAnchor::Center => PointAsRectAnchor::Center,
+    }
+}
+
+fn read_value_type(value_type: ValueType) -> read::ValueType {
+    match value_type {
+        ValueType::Text => read::ValueType::Text,
+        ValueType::Number => read::ValueType::Number,
+        ValueType::Date => read::ValueType::Date,
+    }
+}
+
+fn check_comparison(comparison: Comparison) -> check::Comparison {
+    match comparison {
+        Comparison::Eq => check::Comparison::Eq,
+        Comparison::Ne => check::Comparison::Ne,
+        Comparison::Lt => check::Comparison::Lt,
+        Comparison::Le => check::Comparison::Le,
+        Comparison::Gt => check::Comparison::Gt,
+        Comparison::Ge => check::Comparison::Ge,
+    }
+}
+
+fn mouse_button(button: MouseButton) -> Button {
+    match button {
+        MouseButton::Left => Button::Left,
+        MouseButton::Right => Button::Right,
+        MouseButton::Middle => Button::Middle,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::honk::parser::parse;
+    use std::path::Path;
+
+    fn interpreter() -> Interpreter {
+        Interpreter::new(
+            TemplateRegistry::new(Path::new("fixtures/unit"), None, None),
+            Some(1000),
+            None,
+        )
+    }
+
+    fn dry_run_interpreter() -> Interpreter {
+        Interpreter::new(
+            TemplateRegistry::new(Path::new("fixtures/unit"), None, None),
+            None,
+            Some(true),
+        )
+    }
+
+    #[test]
+    fn failing_step_reports_line_and_skips_rest() {
+        let script = parse("\n\ncheck template<does-not-exist>\nclick 10 10").unwrap();
+        let result = interpreter().run(&script);
+
+        assert!(!result.is_success());
+        let failure = result.failure().expect("Expected a failed step");
+        assert_eq!(failure.line, 3);
+        assert!(failure
+            .error
+            .as_ref()
+            .unwrap()
+            .downcast_ref::<HonkRuntimeError>()
+            .is_some());
+        assert_eq!(result.steps[1].status, StepStatus::Skipped);
+    }
+
+    #[test]
+    fn dry_run_resolves_templates_without_firing() {
+        let script = parse("begin: check template<notepad_close_button>\nclick 10 10").unwrap();
+        let mut interpreter = dry_run_interpreter();
+        let result = interpreter.run(&script);
+
+        assert!(result.is_success());
+        assert!(result
+            .steps
+            .iter()
+            .all(|step| step.status == StepStatus::Passed));
+    }
+
+    #[test]
+    fn csv_loop_records_each_row() {
+        let path = std::env::temp_dir().join("goose_interpreter_loop.csv");
+        std::fs::write(&path, "mrn,name\n000289401,A\n000289402,B\n").unwrap();
+        let source = format!(
+            "set prefix = MRN\nfor row in csv(\"{}\")\n  input 10 10 \"${{prefix}} ${{row.mrn}}\"\nend",
+            path.display().to_string().replace('\\', "/")
+        );
+        let script = parse(&source).unwrap();
+        let mut interpreter = dry_run_interpreter();
+        let result = interpreter.run(&script);
+
+        assert!(result.is_success());
+        let iterations = &result.steps[1].iterations;
+        assert_eq!(iterations.len(), 2);
+        assert_eq!(iterations[1].row, 2);
+        assert_eq!(
+            iterations[1].values,
+            vec![
+                ("mrn".to_string(), "000289402".to_string()),
+                ("name".to_string(), "B".to_string())
+            ]
+        );
+        assert!(interpreter.env().get("row.mrn").is_none());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn undefined_variable_fails_step() {
+        let script = parse("input 10 10 ${missing}").unwrap();
+        let mut interpreter = dry_run_interpreter();
+        let result = interpreter.run(&script);
+        let error = result.failure().unwrap().error.as_ref().unwrap();
+        assert!(error.to_string().contains("'missing'"));
+    }
+
+    #[test]
+    fn dry_run_validates_both_branches() {
+        let script = parse(
+            "if visible template<notepad_close_button>\n\
+             \tclick 10 10\n\
+             else\n\
+             \tinput 10 10 ${undefined}\n\
+             end",
+        )
+        .unwrap();
+        let mut interpreter = dry_run_interpreter();
+        let result = interpreter.run(&script);
+
+        let step = &result.steps[0];
+        assert_eq!(step.status, StepStatus::Failed);
+        assert_eq!(step.body.len(), 2);
+        assert_eq!(step.root_cause().line, 4);
+    }
+
+    #[test]
+    fn dry_run_scroll_validates_block_once() {
+        let script = parse(
+            "scroll down in rect(0, 0, 400, 600) do\n\
+             \tclick template<notepad_close_button>\n\
+             end\n\
+             scroll down until template<missing-row>",
+        )
+        .unwrap();
+        let mut interpreter = dry_run_interpreter();
+        let result = interpreter.run(&script);
+
+        let step = &result.steps[0];
+        assert_eq!(step.status, StepStatus::Passed);
+        assert_eq!(step.iterations.len(), 1);
+        assert_eq!(step.iterations[0].steps[0].status, StepStatus::Passed);
+        assert_eq!(result.failure().unwrap().line, 4);
+    }
+
+    #[test]
+    fn dry_run_copy_binds_variable() {
+        let script = parse("copy 10 10 to 200 200 into note\npaste 300 300 \"${note}\"").unwrap();
+        let mut interpreter = dry_run_interpreter();
+        let result = interpreter.run(&script);
+
+        assert!(result.is_success());
+        assert_eq!(interpreter.env().get("note"), Some(""));
+    }
+
+    #[test]
+    fn dry_run_read_binds_variable() {
+        let script = parse(
+            "read below of template<notepad_close_button> into note as date height=20\n\
+             input 10 10 \"${note}\"",
+        )
+        .unwrap();
+        let mut interpreter = dry_run_interpreter();
+        let result = interpreter.run(&script);
+
+        assert!(result.is_success());
+        assert_eq!(interpreter.env().get("note"), Some(""));
+    }
+
+    #[test]
+    fn dry_run_validates_tables() {
+        let script = parse(
+            "table labs rect(0, 0, 300, 200) columns(Test, Result) pitch=20\n\
+             for row in labs\n\
+             \tinput labs[${row.index}].col(result) \"${row.Test}\"\n\
+             end\n\
+             read labs[1].col(Result) into value\n\
+             export labs to \"labs.csv\"",
+        )
+        .unwrap();
+        let mut interpreter = dry_run_interpreter();
+        let result = interpreter.run(&script);
+
+        assert!(result.is_success(), "{:?}", result.failure());
+        assert_eq!(result.steps[1].iterations.len(), 1);
+        assert_eq!(
+            result.steps[1].iterations[0].values,
+            vec![
+                ("index".to_string(), "1".to_string()),
+                ("Test".to_string(), String::new()),
+                ("Result".to_string(), String::new()),
+            ]
+        );
+
+        // Columns of a detected table are only known on screen.
+        let detected = parse(
+            "table meds rect(0, 0, 300, 200) detect\n\
+             click meds[1].col(Dose)\n\
+             export meds to \"meds.csv\"",
+        )
+        .unwrap();
+        let mut interpreter = dry_run_interpreter();
+        assert!(interpreter.run(&detected).is_success());
+
+        for source in [
+            "click labs[1].col(Result)",
+            "table labs rect(0, 0, 300, 200) columns(Test)\nclick labs[1].col(Result)",
+            "table labs rect(0, 0, 300, 200) columns(Test)\nclick labs[0].col(Test)",
+            "table labs rect(0, 0, 300, 200) detect columns(Test)\nclick labs[1].col(Result)",
+        ] {
+            let mut interpreter = dry_run_interpreter();
+            assert!(!interpreter.run(&parse(source).unwrap()).is_success());
+        }
+    }
+
+    #[test]
+    fn dry_run_validates_match_loops_and_indices() {
+        let script = parse(
+            "for hit in template<notepad_close_button>\n\
+             \tclick template<notepad_close_button>[${hit.index}]\n\
+             end\n\
+             click template<notepad_close_button>[${hit.index}]",
+        )
+        .unwrap();
+        let mut interpreter = dry_run_interpreter();
+        let result = interpreter.run(&script);
+
+        let iterations = &result.steps[0].iterations;
+        assert_eq!(result.steps[0].status, StepStatus::Passed);
+        assert_eq!(iterations.len(), 1);
+        assert_eq!(
+            iterations[0].values[0],
+            ("index".to_string(), "1".to_string())
+        );
+        // The loop variable is gone once the loop ends.
+        assert_eq!(result.failure().unwrap().line, 4);
+    }
+
+    #[test]
+    fn dry_run_binds_waited_for_location() {
+        let script = parse(
+            "wait for template<notepad_close_button> into close\n\
+             set at = \"${close.x},${close.y}\"",
+        )
+        .unwrap();
+        let mut interpreter = dry_run_interpreter();
+        let result = interpreter.run(&script);
+
+        assert!(result.is_success(), "{:?}", result.failure());
+        assert_eq!(interpreter.env().get("at"), Some("0,0"));
+    }
+
+    #[test]
+    fn dry_run_validates_edge_labels() {
+        let script = parse(
+            "set action = Close\n\
+             click template<notepad_close_button>.edges(\"${action}\")\n\
+             click template<notepad_close_button>.edges(\"${missing}\")",
+        )
+        .unwrap();
+        let mut interpreter = dry_run_interpreter();
+        let result = interpreter.run(&script);
+
+        assert_eq!(result.steps[1].status, StepStatus::Passed);
+        assert_eq!(result.failure().unwrap().line, 3);
+    }
+
+    #[test]
+    fn dry_run_validates_element_targets() {
+        let script = parse(
+            "set n = 2\n\
+             click panel(\"Demographics\").text_field[${n}]\n\
+             click panel(\"Demographics\").text_field[0]",
+        )
+        .unwrap();
+        let mut interpreter = dry_run_interpreter();
+        let result = interpreter.run(&script);
+
+        assert_eq!(result.steps[1].status, StepStatus::Passed);
+        assert_eq!(result.failure().unwrap().line, 3);
+    }
+
+    #[test]
+    fn run_from_unknown_label_errors() {
+        let script = parse("begin: click 10 10").unwrap();
+        assert!(interpreter().run_from(&script, "missing").is_err());
+    }
+}
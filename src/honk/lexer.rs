@@ -0,0 +1,505 @@
+//! Tokenizer for Honk scripts.
+use crate::errors::HonkSyntaxError;
+use crate::honk::ast::{Span, Text};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// Verb names, keywords, option names and unquoted text.
+    Word(String),
+    /// Numeric literal. The raw lexeme is kept so values like `000289401` survive as input text.
+    Number(String),
+    /// Double-quoted string, or a bare word containing `${variable}` references.
+    Str(Text),
+    /// `template<name>` reference. The name may contain spaces, e.g. `template<Epic EHR>`.
+    Template(String),
+    /// `text<"...">` reference to text on screen, read with OCR, e.g. `text<"Chart Review">`.
+    TextTarget(Text),
+    /// `[index]` following a template reference, e.g. `template<open-result>[3]`. The index is a
+    /// number or a `${variable}` reference.
+    Index(Text),
+    /// Comparison operator: `==`, `!=`, `<`, `<=`, `>` or `>=`.
+    Operator(String),
+    /// `/pattern/` regular expression literal. `\/` inside the pattern is an escaped slash.
+    Regex(String),
+    Colon,
+    Comma,
+    /// `.` starting an offset after a target, e.g. `template<mrn-label>.right(40)`. Dots inside
+    /// words and numbers belong to them.
+    Dot,
+    Equals,
+    LParen,
+    RParen,
+    Newline,
+    Eof,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// Splits `source` into tokens. Comments start with `#` and run to the end of the line.
+pub fn tokenize(source: &str) -> Result<Vec<Token>, HonkSyntaxError> {
+    Lexer::new(source).tokenize()
+}
+
+struct Lexer {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Lexer {
+    fn new(source: &str) -> Self {
+        Lexer {
+            chars: source.chars().collect(),
+            pos: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn error(&self, message: String, line: usize, column: usize) -> HonkSyntaxError {
+        HonkSyntaxError {
+            message,
+            line,
+            column,
+        }
+    }
+
+    fn tokenize(mut self) -> Result<Vec<Token>, HonkSyntaxError> {
+        let mut tokens = Vec::new();
+
+        while let Some(c) = self.peek() {
+            let (start, line, column) = (self.pos, self.line, self.column);
+            let kind = match c {
+                ' ' | '\t' | '\r' => {
+                    self.bump();
+                    continue;
+                }
+                '#' => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                    continue;
+                }
+                '=' | '!' | '<' | '>' if self.chars.get(self.pos + 1) == Some(&'=') => {
+                    self.bump();
+                    self.bump();
+                    TokenKind::Operator(format!("{}=", c))
+                }
+                '<' | '>' => {
+                    self.bump();
+                    TokenKind::Operator(c.to_string())
+                }
+                '/' => self.regex(line, column)?,
+                '[' => self.index(line, column)?,
+                '\n' | ':' | ',' | '.' | '=' | '(' | ')' => {
+                    self.bump();
+                    match c {
+                        '\n' => TokenKind::Newline,
+                        ':' => TokenKind::Colon,
+                        ',' => TokenKind::Comma,
+                        '.' => TokenKind::Dot,
+                        '=' => TokenKind::Equals,
+                        '(' => TokenKind::LParen,
+                        _ => TokenKind::RParen,
+                    }
+                }
+                '"' => TokenKind::Str(self.string(line, column)?),
+                c if is_word_start(c) => self.word(line, column)?,
+                '$' if self.chars.get(self.pos + 1) == Some(&'{') => self.word(line, column)?,
+                other => {
+                    return Err(self.error(
+                        format!("Unexpected character '{}'", other),
+                        line,
+                        column,
+                    ))
+                }
+            };
+            tokens.push(Token {
+                kind,
+                span: Span::new(line, column, self.pos - start),
+            });
+        }
+
+        tokens.push(Token {
+            kind: TokenKind::Eof,
+            span: Span::new(self.line, self.column, 0),
+        });
+        Ok(tokens)
+    }
+
+    fn string(&mut self, line: usize, column: usize) -> Result<Text, HonkSyntaxError> {
+        self.bump(); // opening quote
+        let mut value = Text::default();
+        loop {
+            match self.bump() {
+                Some('"') => return Ok(value),
+                Some('$') if self.peek() == Some('{') => {
+                    let name = self.variable(self.line, self.column - 1)?;
+                    value.push_variable(name);
+                }
+                Some('\\') => match self.bump() {
+                    Some('n') => value.push_char('\n'),
+                    Some('t') => value.push_char('\t'),
+                    Some(c @ ('"' | '\\' | '$')) => value.push_char(c),
+                    Some(c) => {
+                        return Err(self.error(
+                            format!("Unknown escape sequence '\\{}'", c),
+                            self.line,
+                            self.column - 2,
+                        ))
+                    }
+                    None => break,
+                },
+                Some('\n') | None => break,
+                Some(c) => value.push_char(c),
+            }
+        }
+        Err(self.error("Unterminated string".to_string(), line, column))
+    }
+
+    fn regex(&mut self, line: usize, column: usize) -> Result<TokenKind, HonkSyntaxError> {
+        self.bump(); // opening slash
+        let mut pattern = String::new();
+        loop {
+            match self.bump() {
+                Some('/') => return Ok(TokenKind::Regex(pattern)),
+                Some('\\') if self.peek() == Some('/') => {
+                    self.bump();
+                    pattern.push('/');
+                }
+                Some('\n') | None => break,
+                Some(c) => pattern.push(c),
+            }
+        }
+        Err(self.error("Unterminated regex; expected '/'".to_string(), line, column))
+    }
+
+    fn index(&mut self, line: usize, column: usize) -> Result<TokenKind, HonkSyntaxError> {
+        self.bump(); // opening bracket
+        let mut index = Text::default();
+        loop {
+            match self.bump() {
+                Some(']') => break,
+                Some('$') if self.peek() == Some('{') => {
+                    index.push_variable(self.variable(line, column)?);
+                }
+                Some(c) if c.is_ascii_digit() => index.push_char(c),
+                Some(' ' | '\t') => {}
+                _ => {
+                    return Err(self.error(
+                        "Index must be a number or ${variable} followed by ']'".to_string(),
+                        line,
+                        column,
+                    ))
+                }
+            }
+        }
+        if index.parts.is_empty() {
+            return Err(self.error("Index is empty".to_string(), line, column));
+        }
+        Ok(TokenKind::Index(index))
+    }
+
+    /// Reads the `{name}` part of a `${name}` reference; the `$` has already been consumed.
+    fn variable(&mut self, line: usize, column: usize) -> Result<String, HonkSyntaxError> {
+        self.bump(); // opening brace
+        let mut name = String::new();
+        loop {
+            match self.peek() {
+                Some('}') => {
+                    self.bump();
+                    break;
+                }
+                Some('\n' | '"') | None => {
+                    return Err(self.error(
+                        "Unterminated variable reference; expected '}'".to_string(),
+                        line,
+                        column,
+                    ))
+                }
+                Some(c) => {
+                    name.push(c);
+                    self.bump();
+                }
+            }
+        }
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(self.error("Variable name is empty".to_string(), line, column));
+        }
+        Ok(name.to_string())
+    }
+
+    /// Reads a bare word or number. `template` immediately followed by `<` starts a template
+    /// reference that runs to the closing `>`. Words containing `${variable}` references become
+    /// `Str` tokens so they are interpolated like quoted strings.
+    fn word(&mut self, line: usize, column: usize) -> Result<TokenKind, HonkSyntaxError> {
+        let mut word = String::new();
+        let mut text = Text::default();
+        loop {
+            match self.peek() {
+                Some('$') if self.chars.get(self.pos + 1) == Some(&'{') => {
+                    let (line, column) = (self.line, self.column);
+                    self.bump();
+                    text.push_str(&word);
+                    word.clear();
+                    text.push_variable(self.variable(line, column)?);
+                }
+                Some(c) if is_word_char(c) => {
+                    word.push(c);
+                    self.bump();
+                }
+                _ => break,
+            }
+        }
+        if !text.parts.is_empty() {
+            text.push_str(&word);
+            return Ok(TokenKind::Str(text));
+        }
+
+        if word == "template" && self.peek() == Some('<') {
+            self.bump();
+            let mut name = String::new();
+            loop {
+                match self.peek() {
+                    Some('>') => {
+                        self.bump();
+                        break;
+                    }
+                    Some('\n') | None => {
+                        return Err(self.error(
+                            "Unterminated template reference; expected '>'".to_string(),
+                            line,
+                            column,
+                        ))
+                    }
+                    Some(c) => {
+                        name.push(c);
+                        self.bump();
+                    }
+                }
+            }
+            let name = name.trim();
+            if name.is_empty() {
+                return Err(self.error("Template name is empty".to_string(), line, column));
+            }
+            return Ok(TokenKind::Template(name.to_string()));
+        }
+
+        if word == "text" && self.peek() == Some('<') {
+            self.bump();
+            if self.peek() != Some('"') {
+                return Err(self.error(
+                    "Expected a quoted string after 'text<', e.g. text<\"Chart Review\">"
+                        .to_string(),
+                    line,
+                    column,
+                ));
+            }
+            let text = self.string(self.line, self.column)?;
+            if self.peek() != Some('>') {
+                return Err(self.error(
+                    "Unterminated text reference; expected '>'".to_string(),
+                    line,
+                    column,
+                ));
+            }
+            self.bump();
+            if text.parts.is_empty() {
+                return Err(self.error("Text to find is empty".to_string(), line, column));
+            }
+            return Ok(TokenKind::TextTarget(text));
+        }
+
+        if is_number(&word) {
+            Ok(TokenKind::Number(word))
+        } else {
+            Ok(TokenKind::Word(word))
+        }
+    }
+}
+
+fn is_word_start(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | '+')
+}
+
+fn is_number(word: &str) -> bool {
+    word.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && word.chars().filter(|c| *c == '.').count() <= 1
+        && word.chars().any(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::honk::ast::TextPart;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        tokenize(source)
+            .expect("Failed to tokenize")
+            .into_iter()
+            .map(|token| token.kind)
+            .collect()
+    }
+
+    #[test]
+    fn tokenizes_pitch_example() {
+        assert_eq!(
+            kinds("begin: check template<Epic EHR>\ninput template<mrn-text-box> 000289401"),
+            vec![
+                TokenKind::Word("begin".to_string()),
+                TokenKind::Colon,
+                TokenKind::Word("check".to_string()),
+                TokenKind::Template("Epic EHR".to_string()),
+                TokenKind::Newline,
+                TokenKind::Word("input".to_string()),
+                TokenKind::Template("mrn-text-box".to_string()),
+                TokenKind::Number("000289401".to_string()),
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_comments_and_tracks_columns() {
+        let tokens = tokenize("# comment\n  click 10 20").unwrap();
+        assert_eq!(tokens[0].kind, TokenKind::Newline);
+        assert_eq!(tokens[1].span, Span::new(2, 3, 5));
+        assert_eq!(tokens[2].span, Span::new(2, 9, 2));
+    }
+
+    #[test]
+    fn unterminated_template_reports_position() {
+        let err = tokenize("click\nclick template<oops").unwrap_err();
+        assert_eq!((err.line, err.column), (2, 7));
+    }
+
+    #[test]
+    fn string_escapes() {
+        assert_eq!(
+            kinds(r#""say \"hi\" \${x}""#),
+            vec![
+                TokenKind::Str(Text::literal("say \"hi\" ${x}")),
+                TokenKind::Eof
+            ]
+        );
+    }
+
+    #[test]
+    fn interpolated_strings_and_words() {
+        let mut expected = Text::literal("MRN ");
+        expected.push_variable("row.mrn".to_string());
+        assert_eq!(
+            kinds(r#""MRN ${row.mrn}" ${row.mrn}"#),
+            vec![
+                TokenKind::Str(expected),
+                TokenKind::Str(Text {
+                    parts: vec![TextPart::Variable("row.mrn".to_string())]
+                }),
+                TokenKind::Eof,
+            ]
+        );
+        assert!(tokenize("input 1 1 ${oops").is_err());
+    }
+
+    #[test]
+    fn operators_and_regex() {
+        assert_eq!(
+            kinds(r"== >= < /\d{2}\/\d{4}/ x=1"),
+            vec![
+                TokenKind::Operator("==".to_string()),
+                TokenKind::Operator(">=".to_string()),
+                TokenKind::Operator("<".to_string()),
+                TokenKind::Regex(r"\d{2}/\d{4}".to_string()),
+                TokenKind::Word("x".to_string()),
+                TokenKind::Equals,
+                TokenKind::Number("1".to_string()),
+                TokenKind::Eof,
+            ]
+        );
+        assert!(tokenize("check text rect(0, 0, 1, 1) matches /oops").is_err());
+    }
+
+    #[test]
+    fn template_indices() {
+        let mut variable = Text::default();
+        variable.push_variable("hit.index".to_string());
+        assert_eq!(
+            kinds("template<open-result>[3] template<row>[${hit.index}]"),
+            vec![
+                TokenKind::Template("open-result".to_string()),
+                TokenKind::Index(Text::literal("3")),
+                TokenKind::Template("row".to_string()),
+                TokenKind::Index(variable),
+                TokenKind::Eof,
+            ]
+        );
+        assert!(tokenize("click template<row>[]").is_err());
+        assert!(tokenize("click template<row>[first]").is_err());
+    }
+
+    #[test]
+    fn text_references() {
+        let mut expected = Text::literal("Patient ");
+        expected.push_variable("row.name".to_string());
+        assert_eq!(
+            kinds(r#"text<"Chart Review"> text<"Patient ${row.name}">"#),
+            vec![
+                TokenKind::TextTarget(Text::literal("Chart Review")),
+                TokenKind::TextTarget(expected),
+                TokenKind::Eof,
+            ]
+        );
+        assert!(tokenize("click text<Chart Review>").is_err());
+        assert!(tokenize(r#"click text<"Chart Review""#).is_err());
+        assert!(tokenize(r#"click text<"">"#).is_err());
+    }
+
+    #[test]
+    fn target_offsets() {
+        assert_eq!(
+            kinds("template<mrn-label>.right(40) template<row>[2].below(1.5)"),
+            vec![
+                TokenKind::Template("mrn-label".to_string()),
+                TokenKind::Dot,
+                TokenKind::Word("right".to_string()),
+                TokenKind::LParen,
+                TokenKind::Number("40".to_string()),
+                TokenKind::RParen,
+                TokenKind::Template("row".to_string()),
+                TokenKind::Index(Text::literal("2")),
+                TokenKind::Dot,
+                TokenKind::Word("below".to_string()),
+                TokenKind::LParen,
+                TokenKind::Number("1.5".to_string()),
+                TokenKind::RParen,
+                TokenKind::Eof,
+            ]
+        );
+    }
+}
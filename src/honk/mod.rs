@@ -0,0 +1,11 @@
+//! Honk, the scripting language for Goose automations.
+//! Scripts are tokenized by `lexer`, parsed into an `ast::Script` by `parser`, and executed verb by
+//! verb against the screen by `interpreter`, which resolves `template<name>` references through a
+//! `registry::TemplateRegistry` and interpolates `${variable}` references from an
+//! `environment::Environment`.
+pub mod ast;
+pub mod environment;
+pub mod interpreter;
+pub mod lexer;
+pub mod parser;
+pub mod registry;
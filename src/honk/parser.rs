@@ -0,0 +1,2237 @@
+//! Recursive-descent parser turning Honk tokens into a `Script`.
+//!
+//! Grammar, one statement per line:
+//! ```text
+//! script    := block
+//! block     := { [label ':'] [statement] NEWLINE }
+//! statement := verb { option }
+//!            | 'set' NAME '=' text
+//!            | 'table' NAME region ['detect'] 'columns' '(' column { ',' column } ')'
+//!              (columns are optional after 'detect', and take no widths)
+//!            | 'for' NAME 'in' ('csv' '(' STRING ')' | TEMPLATE | NAME) NEWLINE block 'end'
+//!            | 'if' condition { option } NEWLINE block ['else' (if | NEWLINE block)] 'end'
+//! verb      := 'click' target ['double' | 'triple'] ['with' MODIFIERS] ['menu' TEMPLATE]
+//!            | 'input' target text ['submit']
+//!            | 'scroll' direction ('until' target | 'in' region)
+//!            | 'scroll' direction 'in' region { option } 'do' NEWLINE block 'end'
+//!            | 'hover' target 'until' (TEMPLATE | 'changed' region)
+//!            | 'copy' target 'to' target 'into' NAME
+//!            | 'read' (region | cell | SIDE 'of' target | 'at' target) 'into' NAME ['as' TYPE]
+//!              ['matching' REGEX]                 (TYPE is text, number or date)
+//!            | 'paste' target [text]
+//!            | 'drag' target 'to' target
+//!            | 'press' CHORD ['x' COUNT]         (e.g. `press ctrl+shift+F5`, `press tab x3`)
+//!            | 'check' probe [expectation]      (a bare template probe checks that it is visible)
+//!            | 'select' target (TEMPLATE | text)
+//!            | 'checkbox' TEMPLATE TEMPLATE ('checked' | 'unchecked')
+//!            | 'wait' ('for' TEMPLATE ['into' NAME] | 'until' TEMPLATE 'gone' | 'until' region 'stable')
+//!            | 'export' NAME 'to' STRING
+//! text      := STRING | NUMBER | WORD      (strings and words may contain ${variable})
+//! target    := (TEMPLATE ['[' (NUMBER | ${variable}) ']' | edges] | TEXT | cell | element)
+//!              { '.' SIDE '(' NUMBER ')' }
+//!            | NUMBER [','] NUMBER
+//!              (TEXT is `text<"...">`, text read on screen with OCR; SIDE is right, left, above
+//!              or below, at most one of each axis)
+//! edges     := '.' 'edges' '(' [text] ')'    (locates TEMPLATE by its outline, containing text)
+//! cell      := NAME '[' (NUMBER | ${variable}) ']' '.' 'col' '(' text ')'
+//! element   := 'panel' '(' text ')' '.' KIND ['[' (NUMBER | ${variable}) ']']
+//!              (KIND is button, text_field, checkbox or text)
+//! column    := (WORD | STRING) ['=' NUMBER]
+//! region    := 'rect' '(' NUMBER ',' NUMBER ',' NUMBER ',' NUMBER ')'
+//! probe     := TEMPLATE | 'text' region | 'pixel' target
+//! expectation := OPERATOR text | 'contains' text | 'is' text | 'matches' REGEX
+//! condition := 'not' condition | 'visible' (TEMPLATE | TEXT) | 'absent' (TEMPLATE | TEXT)
+//!            | probe expectation | 'changed' region
+//! option    := WORD '=' (NUMBER | WORD | STRING | region)
+//! ```
+use crate::errors::HonkSyntaxError;
+use crate::honk::ast::{
+    CellRef, CheckNode, CheckboxNode, ClickNode, Comparison, Condition, CopyNode, DragNode,
+    ElementKind, ElementRef, Expectation, ExportNode, ForNode, HoverNode, HoverUntil, IfNode,
+    InputNode, LoopSource, MouseButton, Offset, PasteNode, Placement, PressNode, Probe, ReadArea,
+    ReadNode, RectAnchor, Region, Script, ScrollDirection, ScrollMode, ScrollNode, SelectNode,
+    SelectOption, SetNode, Side, Span, Statement, StatementKind, StepOptions, TableColumn,
+    TableNode, Target, Text, ValueType, WaitNode, WaitUntil,
+};
+use crate::honk::lexer::{tokenize, Token, TokenKind};
+use crate::verb::press::{parse_modifiers, Chord};
+use std::collections::HashSet;
+
+/// Keywords that can start a statement.
+const VERBS: &[&str] = &[
+    "click", "input", "scroll", "hover", "copy", "read", "paste", "press", "drag", "check", "wait",
+    "select", "checkbox", "set", "table", "export", "for", "if",
+];
+
+/// Parses the source of a `.honk` file.
+pub fn parse(source: &str) -> Result<Script, HonkSyntaxError> {
+    Parser::new(tokenize(source)?).parse_script()
+}
+
+/// Value on the right-hand side of a `name=value` option.
+enum OptionValue {
+    Number(f64),
+    Word(String),
+    Str(String),
+    Region(Region),
+}
+
+struct VerbOption {
+    name: String,
+    value: OptionValue,
+    span: Span,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Labels seen so far; labels are unique across the whole script, including inside blocks.
+    labels: HashSet<String>,
+}
+
+fn error_at(message: String, span: Span) -> HonkSyntaxError {
+    HonkSyntaxError {
+        message,
+        line: span.line,
+        column: span.column,
+    }
+}
+
+/// `x3` after a chord repeats it three times.
+fn is_repeat_count(word: &str) -> bool {
+    word.len() > 1 && word.starts_with('x') && word[1..].chars().all(|c| c.is_ascii_digit())
+}
+
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Word(word) => format!("'{}'", word),
+        TokenKind::Number(number) => format!("number {}", number),
+        TokenKind::Str(value) => format!("string \"{}\"", value),
+        TokenKind::Template(name) => format!("template<{}>", name),
+        TokenKind::TextTarget(text) => format!("text<\"{}\">", text),
+        TokenKind::Index(index) => format!("index [{}]", index),
+        TokenKind::Operator(operator) => format!("'{}'", operator),
+        TokenKind::Regex(pattern) => format!("regex /{}/", pattern),
+        TokenKind::Colon => "':'".to_string(),
+        TokenKind::Comma => "','".to_string(),
+        TokenKind::Dot => "'.'".to_string(),
+        TokenKind::Equals => "'='".to_string(),
+        TokenKind::LParen => "'('".to_string(),
+        TokenKind::RParen => "')'".to_string(),
+        TokenKind::Newline => "end of line".to_string(),
+        TokenKind::Eof => "end of file".to_string(),
+    }
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>) -> Self {
+        Parser {
+            tokens,
+            pos: 0,
+            labels: HashSet::new(),
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        // The lexer always terminates the stream with `Eof`, which is never consumed.
+        &self.tokens[self.pos.min(self.tokens.len() - 1)]
+    }
+
+    fn peek_nth(&self, n: usize) -> &Token {
+        &self.tokens[(self.pos + n).min(self.tokens.len() - 1)]
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn unexpected(&self, expected: &str) -> HonkSyntaxError {
+        let token = self.peek();
+        error_at(
+            format!("Expected {}, found {}", expected, describe(&token.kind)),
+            token.span,
+        )
+    }
+
+    fn expect(&mut self, kind: TokenKind, expected: &str) -> Result<Token, HonkSyntaxError> {
+        if self.peek().kind == kind {
+            Ok(self.advance())
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+
+    fn at_line_end(&self) -> bool {
+        matches!(self.peek().kind, TokenKind::Newline | TokenKind::Eof)
+    }
+
+    /// Whether the next token is a trailing `do` that opens a block on the following lines.
+    fn at_block_start(&self) -> bool {
+        matches!(&self.peek().kind, TokenKind::Word(word) if word == "do")
+            && matches!(self.peek_nth(1).kind, TokenKind::Newline | TokenKind::Eof)
+    }
+
+    fn parse_script(&mut self) -> Result<Script, HonkSyntaxError> {
+        let (statements, _) = self.parse_block(&[])?;
+        Ok(Script { statements })
+    }
+
+    /// Parses statements until one of the `terminators` keywords starts a line, or until the end
+    /// of the file when `terminators` is empty. The terminator itself is consumed and returned.
+    fn parse_block(
+        &mut self,
+        terminators: &[&str],
+    ) -> Result<(Vec<Statement>, Option<Token>), HonkSyntaxError> {
+        let mut statements = Vec::new();
+        let mut pending_label: Option<(String, Span)> = None;
+
+        let terminator = loop {
+            match &self.peek().kind {
+                TokenKind::Eof if terminators.is_empty() => break None,
+                TokenKind::Eof => {
+                    return Err(self.unexpected(&format!("'{}'", terminators.join("' or '"))))
+                }
+                TokenKind::Newline => {
+                    self.advance();
+                    continue;
+                }
+                TokenKind::Word(word)
+                    if terminators.contains(&word.as_str())
+                        && self.peek_nth(1).kind != TokenKind::Colon =>
+                {
+                    break Some(self.advance());
+                }
+                TokenKind::Word(name) if self.peek_nth(1).kind == TokenKind::Colon => {
+                    let (name, span) = (name.clone(), self.peek().span);
+                    if let Some((previous, _)) = &pending_label {
+                        return Err(error_at(
+                            format!("Label '{}' must be followed by a statement", previous),
+                            span,
+                        ));
+                    }
+                    if !self.labels.insert(name.clone()) {
+                        return Err(error_at(format!("Duplicate label '{}'", name), span));
+                    }
+                    self.advance();
+                    self.advance();
+                    pending_label = Some((name, span));
+                    continue;
+                }
+                _ => {}
+            }
+
+            let label = pending_label.take().map(|(name, _)| name);
+            statements.push(self.parse_statement(label)?);
+
+            if !self.at_line_end() {
+                return Err(self.unexpected("end of line"));
+            }
+        };
+
+        if let Some((name, span)) = pending_label {
+            return Err(error_at(
+                format!("Label '{}' must be followed by a statement", name),
+                span,
+            ));
+        }
+        Ok((statements, terminator))
+    }
+
+    fn parse_statement(&mut self, label: Option<String>) -> Result<Statement, HonkSyntaxError> {
+        let verb_token = self.peek().clone();
+        let verb = match &verb_token.kind {
+            TokenKind::Word(word) => word.clone(),
+            _ => return Err(self.unexpected("a verb")),
+        };
+        self.advance();
+
+        let mut options = StepOptions::default();
+        let kind = match verb.as_str() {
+            "click" => {
+                let target = self.parse_target()?;
+                let clicks = if self.accept_keyword("double") {
+                    2
+                } else if self.accept_keyword("triple") {
+                    3
+                } else {
+                    1
+                };
+                let modifiers = if self.accept_keyword("with") {
+                    let token = self.peek().clone();
+                    let modifiers = match &token.kind {
+                        TokenKind::Word(modifiers) => modifiers.clone(),
+                        _ => return Err(self.unexpected("modifiers such as ctrl+shift")),
+                    };
+                    if let Err(error) = parse_modifiers(&modifiers) {
+                        return Err(error_at(error.message, token.span));
+                    }
+                    self.advance();
+                    Some(modifiers)
+                } else {
+                    None
+                };
+                let menu_span = self.peek().span;
+                let menu_item = if self.accept_keyword("menu") {
+                    if clicks > 1 || modifiers.is_some() {
+                        return Err(error_at(
+                            "A context menu click cannot be repeated or modified".to_string(),
+                            menu_span,
+                        ));
+                    }
+                    Some(self.parse_template()?)
+                } else {
+                    None
+                };
+                let mut button = MouseButton::Left;
+                for option in self.parse_options()? {
+                    match option.name.as_str() {
+                        "button" => button = self.button_option(option)?,
+                        _ => self.step_option(&mut options, option)?,
+                    }
+                }
+                StatementKind::Click(ClickNode {
+                    target,
+                    button,
+                    clicks,
+                    modifiers,
+                    menu_item,
+                })
+            }
+            "input" => {
+                let target = self.parse_target()?;
+                let text = self.parse_text()?;
+                let submit = self.accept_keyword("submit");
+                for option in self.parse_options()? {
+                    self.step_option(&mut options, option)?;
+                }
+                StatementKind::Input(InputNode {
+                    target,
+                    text,
+                    submit,
+                })
+            }
+            "scroll" => {
+                let direction = self.parse_direction()?;
+                let mode = match &self.peek().kind {
+                    TokenKind::Word(word) if word == "until" => {
+                        self.advance();
+                        ScrollMode::Seek(self.parse_template()?)
+                    }
+                    TokenKind::Word(word) if word == "in" => {
+                        self.advance();
+                        ScrollMode::Iterative(self.parse_region()?)
+                    }
+                    _ => return Err(self.unexpected("'until' or 'in'")),
+                };
+                let (mut max_scrolls, mut step) = (None, None);
+                for option in self.parse_options()? {
+                    match option.name.as_str() {
+                        "max" => max_scrolls = Some(self.count_option(option)?),
+                        "step" => {
+                            let span = option.span;
+                            match self.count_option(option)? {
+                                0 => {
+                                    return Err(error_at(
+                                        "Option 'step' must be greater than 0".to_string(),
+                                        span,
+                                    ))
+                                }
+                                value => step = Some(value),
+                            }
+                        }
+                        _ => self.step_option(&mut options, option)?,
+                    }
+                }
+                let mut body = Vec::new();
+                if self.at_block_start() {
+                    if let ScrollMode::Seek(_) = mode {
+                        return Err(error_at(
+                            "Only 'scroll ... in rect(...)' can run a 'do' block".to_string(),
+                            self.peek().span,
+                        ));
+                    }
+                    self.advance();
+                    body = self.parse_block(&["end"])?.0;
+                }
+                StatementKind::Scroll(ScrollNode {
+                    direction,
+                    mode,
+                    max_scrolls,
+                    step,
+                    body,
+                })
+            }
+            "hover" => {
+                let target = self.parse_target()?;
+                if !self.accept_keyword("until") {
+                    return Err(self.unexpected("'until'"));
+                }
+                let until = if self.accept_keyword("changed") {
+                    HoverUntil::Changed(self.parse_region()?)
+                } else {
+                    HoverUntil::Visible(self.parse_template()?)
+                };
+                let mut dwell = None;
+                for option in self.parse_options()? {
+                    match option.name.as_str() {
+                        "dwell" => dwell = Some(self.integer_option(option)?),
+                        _ => self.step_option(&mut options, option)?,
+                    }
+                }
+                StatementKind::Hover(HoverNode {
+                    target,
+                    until,
+                    dwell,
+                })
+            }
+            "copy" => {
+                let from = self.parse_target()?;
+                if !self.accept_keyword("to") {
+                    return Err(self.unexpected("'to'"));
+                }
+                let to = self.parse_target()?;
+                if !self.accept_keyword("into") {
+                    return Err(self.unexpected("'into'"));
+                }
+                let variable = self.parse_name("a variable name")?;
+                let mut hold = None;
+                for option in self.parse_options()? {
+                    match option.name.as_str() {
+                        "hold" => hold = Some(self.integer_option(option)?),
+                        _ => self.step_option(&mut options, option)?,
+                    }
+                }
+                StatementKind::Copy(CopyNode {
+                    from,
+                    to,
+                    variable,
+                    hold,
+                })
+            }
+            "read" => {
+                let mut area = match &self.peek().kind {
+                    TokenKind::Word(word) if word == "rect" => {
+                        ReadArea::Region(self.parse_region()?)
+                    }
+                    TokenKind::Word(_) if matches!(self.peek_nth(1).kind, TokenKind::Index(_)) => {
+                        ReadArea::Cell(self.parse_cell()?)
+                    }
+                    TokenKind::Word(word) if word == "at" => {
+                        self.advance();
+                        ReadArea::Relative {
+                            placement: Placement::At(RectAnchor::TopLeft),
+                            anchor: self.parse_target()?,
+                            width: None,
+                            height: None,
+                        }
+                    }
+                    _ => {
+                        let side = self.parse_side(
+                            "rect(x, y, width, height), 'at' or a side (right, left, above, below)",
+                        )?;
+                        if !self.accept_keyword("of") {
+                            return Err(self.unexpected("'of'"));
+                        }
+                        ReadArea::Relative {
+                            placement: Placement::Beside(side),
+                            anchor: self.parse_target()?,
+                            width: None,
+                            height: None,
+                        }
+                    }
+                };
+                if !self.accept_keyword("into") {
+                    return Err(self.unexpected("'into'"));
+                }
+                let variable = self.parse_name("a variable name")?;
+                let value_type = if self.accept_keyword("as") {
+                    self.parse_value_type()?
+                } else {
+                    ValueType::Text
+                };
+                let pattern = if self.accept_keyword("matching") {
+                    Some(self.parse_regex()?)
+                } else {
+                    None
+                };
+                for option in self.parse_options()? {
+                    match (option.name.as_str(), &mut area) {
+                        ("width", ReadArea::Relative { width, .. }) => {
+                            *width = Some(self.integer_option(option)?)
+                        }
+                        ("height", ReadArea::Relative { height, .. }) => {
+                            *height = Some(self.integer_option(option)?)
+                        }
+                        (
+                            "anchor",
+                            ReadArea::Relative {
+                                placement: Placement::At(corner),
+                                ..
+                            },
+                        ) => *corner = self.anchor_option(option)?,
+                        _ => self.step_option(&mut options, option)?,
+                    }
+                }
+                StatementKind::Read(ReadNode {
+                    area,
+                    variable,
+                    value_type,
+                    pattern,
+                })
+            }
+            "paste" => {
+                let target = self.parse_target()?;
+                let text = match &self.peek().kind {
+                    TokenKind::Word(_) if self.peek_nth(1).kind == TokenKind::Equals => None,
+                    TokenKind::Str(_) | TokenKind::Number(_) | TokenKind::Word(_) => {
+                        Some(self.parse_text()?)
+                    }
+                    _ => None,
+                };
+                for option in self.parse_options()? {
+                    self.step_option(&mut options, option)?;
+                }
+                StatementKind::Paste(PasteNode { target, text })
+            }
+            "drag" => {
+                let from = self.parse_target()?;
+                if !self.accept_keyword("to") {
+                    return Err(self.unexpected("'to'"));
+                }
+                let to = self.parse_target()?;
+                let mut speed = None;
+                for option in self.parse_options()? {
+                    match option.name.as_str() {
+                        "speed" => speed = Some(self.integer_option(option)?),
+                        _ => self.step_option(&mut options, option)?,
+                    }
+                }
+                StatementKind::Drag(DragNode { from, to, speed })
+            }
+            "press" => {
+                let token = self.peek().clone();
+                let chord = match &token.kind {
+                    TokenKind::Word(chord) | TokenKind::Number(chord) => chord.clone(),
+                    _ => return Err(self.unexpected("a key chord such as ctrl+shift+F5")),
+                };
+                if let Err(error) = chord.parse::<Chord>() {
+                    return Err(error_at(error.message, token.span));
+                }
+                self.advance();
+
+                let repeat = match &self.peek().kind {
+                    TokenKind::Word(word) if is_repeat_count(word) => {
+                        let span = self.peek().span;
+                        let count = word[1..].parse::<u32>().ok().filter(|count| *count > 0);
+                        self.advance();
+                        Some(count.ok_or_else(|| {
+                            error_at("Repeat count must be at least 1".to_string(), span)
+                        })?)
+                    }
+                    _ => None,
+                };
+                for option in self.parse_options()? {
+                    self.step_option(&mut options, option)?;
+                }
+                StatementKind::Press(PressNode { chord, repeat })
+            }
+            "check" => {
+                let probe = self.parse_probe()?;
+                let expect = self.parse_expectation()?;
+                if expect.is_none() && !matches!(probe, Probe::Template(_)) {
+                    return Err(self.unexpected("a comparison, 'contains', 'is' or 'matches'"));
+                }
+                for option in self.parse_options()? {
+                    self.step_option(&mut options, option)?;
+                }
+                StatementKind::Check(CheckNode { probe, expect })
+            }
+            "wait" => {
+                let mut variable = None;
+                let until = if self.accept_keyword("for") {
+                    let template = self.parse_template()?;
+                    if self.accept_keyword("into") {
+                        variable = Some(self.parse_name("a variable name")?);
+                    }
+                    WaitUntil::Visible(template)
+                } else if self.accept_keyword("until") {
+                    if matches!(&self.peek().kind, TokenKind::Template(_)) {
+                        let template = self.parse_template()?;
+                        if !self.accept_keyword("gone") {
+                            return Err(self.unexpected("'gone'"));
+                        }
+                        WaitUntil::Gone(template)
+                    } else {
+                        let region = self.parse_region()?;
+                        if !self.accept_keyword("stable") {
+                            return Err(self.unexpected("'stable'"));
+                        }
+                        WaitUntil::Stable {
+                            region,
+                            quiet: None,
+                        }
+                    }
+                } else {
+                    return Err(self.unexpected("'for' or 'until'"));
+                };
+                let mut wait = WaitNode {
+                    until,
+                    variable,
+                    poll: None,
+                };
+                for option in self.parse_options()? {
+                    match (option.name.as_str(), &mut wait.until) {
+                        ("poll", _) => wait.poll = Some(self.integer_option(option)?),
+                        ("quiet", WaitUntil::Stable { quiet, .. }) => {
+                            *quiet = Some(self.integer_option(option)?)
+                        }
+                        _ => self.step_option(&mut options, option)?,
+                    }
+                }
+                StatementKind::Wait(wait)
+            }
+            "select" => {
+                let target = self.parse_target()?;
+                let option = match &self.peek().kind {
+                    TokenKind::Template(_) => SelectOption::Template(self.parse_template()?),
+                    _ => SelectOption::Text(self.parse_text()?),
+                };
+                let (mut popup, mut max_scrolls) = (None, None);
+                for option in self.parse_options()? {
+                    match option.name.as_str() {
+                        "popup" => match option.value {
+                            OptionValue::Region(region) => popup = Some(region),
+                            _ => {
+                                return Err(error_at(
+                                    "Option 'popup' expects rect(x, y, width, height)".to_string(),
+                                    option.span,
+                                ))
+                            }
+                        },
+                        "max" => max_scrolls = Some(self.count_option(option)?),
+                        _ => self.step_option(&mut options, option)?,
+                    }
+                }
+                StatementKind::Select(SelectNode {
+                    target,
+                    option,
+                    popup,
+                    max_scrolls,
+                })
+            }
+            "checkbox" => {
+                let checked = self.parse_template()?;
+                let unchecked = self.parse_template()?;
+                let desired = if self.accept_keyword("checked") {
+                    true
+                } else if self.accept_keyword("unchecked") {
+                    false
+                } else {
+                    return Err(self.unexpected("'checked' or 'unchecked'"));
+                };
+                let mut confidence = None;
+                for option in self.parse_options()? {
+                    match option.name.as_str() {
+                        "confidence" => confidence = Some(self.fraction_option(option)?),
+                        _ => self.step_option(&mut options, option)?,
+                    }
+                }
+                StatementKind::Checkbox(CheckboxNode {
+                    checked,
+                    unchecked,
+                    desired,
+                    confidence,
+                })
+            }
+            "set" => {
+                let name = self.parse_name("a variable name")?;
+                self.expect(TokenKind::Equals, "'='")?;
+                let value = self.parse_text()?;
+                StatementKind::Set(SetNode { name, value })
+            }
+            "table" => {
+                let name = self.parse_name("a table name")?;
+                let region = self.parse_region()?;
+                let detect = self.accept_keyword("detect");
+                let columns_span = self.peek().span;
+                let columns = if self.accept_keyword("columns") {
+                    self.parse_columns()?
+                } else if detect {
+                    Vec::new()
+                } else {
+                    return Err(self.unexpected("'detect' or 'columns'"));
+                };
+                if detect && columns.iter().any(|column| column.width.is_some()) {
+                    return Err(error_at(
+                        "Column widths are detected with 'detect'".to_string(),
+                        columns_span,
+                    ));
+                }
+                let (mut header, mut pitch) = (None, None);
+                for option in self.parse_options()? {
+                    match option.name.as_str() {
+                        "header" | "pitch" if detect => {
+                            return Err(error_at(
+                                format!("Option '{}' is detected with 'detect'", option.name),
+                                option.span,
+                            ))
+                        }
+                        "header" => header = Some(self.integer_option(option)?),
+                        "pitch" => {
+                            let span = option.span;
+                            match self.integer_option(option)? {
+                                0 => {
+                                    return Err(error_at(
+                                        "Option 'pitch' must be greater than 0".to_string(),
+                                        span,
+                                    ))
+                                }
+                                value => pitch = Some(value),
+                            }
+                        }
+                        _ => self.step_option(&mut options, option)?,
+                    }
+                }
+                StatementKind::Table(TableNode {
+                    name,
+                    region,
+                    columns,
+                    header,
+                    pitch,
+                    detect,
+                })
+            }
+            "export" => {
+                let table = self.parse_name("a table name")?;
+                if !self.accept_keyword("to") {
+                    return Err(self.unexpected("'to'"));
+                }
+                let path = self.parse_literal_string()?;
+                for option in self.parse_options()? {
+                    self.step_option(&mut options, option)?;
+                }
+                StatementKind::Export(ExportNode { table, path })
+            }
+            "for" => {
+                let variable = self.parse_name("a loop variable name")?;
+                if !self.accept_keyword("in") {
+                    return Err(self.unexpected("'in'"));
+                }
+                let source = self.parse_loop_source()?;
+                if !self.at_line_end() {
+                    return Err(self.unexpected("end of line"));
+                }
+                let (body, _) = self.parse_block(&["end"])?;
+                StatementKind::For(ForNode {
+                    variable,
+                    source,
+                    body,
+                })
+            }
+            "if" => {
+                let condition = self.parse_condition()?;
+                for option in self.parse_options()? {
+                    self.step_option(&mut options, option)?;
+                }
+                StatementKind::If(self.parse_if_body(condition)?)
+            }
+            other => {
+                return Err(error_at(
+                    format!(
+                        "Unknown verb '{}'; expected one of {}",
+                        other,
+                        VERBS.join(", ")
+                    ),
+                    verb_token.span,
+                ))
+            }
+        };
+
+        Ok(Statement {
+            label,
+            kind,
+            options,
+            span: verb_token.span,
+        })
+    }
+
+    /// Parses the blocks following an `if` line up to and including the closing `end`.
+    fn parse_if_body(&mut self, condition: Condition) -> Result<IfNode, HonkSyntaxError> {
+        if !self.at_line_end() {
+            return Err(self.unexpected("end of line"));
+        }
+        let (then_body, terminator) = self.parse_block(&["else", "end"])?;
+
+        let else_body = match terminator.map(|token| token.kind) {
+            Some(TokenKind::Word(word)) if word == "else" => {
+                if matches!(&self.peek().kind, TokenKind::Word(word) if word == "if") {
+                    // `else if` shares the closing `end` of the nested if statement.
+                    vec![self.parse_statement(None)?]
+                } else {
+                    if !self.at_line_end() {
+                        return Err(self.unexpected("end of line"));
+                    }
+                    self.parse_block(&["end"])?.0
+                }
+            }
+            _ => Vec::new(),
+        };
+
+        Ok(IfNode {
+            condition,
+            then_body,
+            else_body,
+        })
+    }
+
+    fn parse_condition(&mut self) -> Result<Condition, HonkSyntaxError> {
+        let expected = "a condition (visible, absent, text, pixel, changed or not)";
+        let keyword = match &self.peek().kind {
+            TokenKind::Word(word) => word.clone(),
+            TokenKind::Template(_) => String::new(),
+            _ => return Err(self.unexpected(expected)),
+        };
+        let condition = match keyword.as_str() {
+            "not" => {
+                self.advance();
+                Condition::Not(Box::new(self.parse_condition()?))
+            }
+            "visible" => {
+                self.advance();
+                Condition::Visible(self.parse_template_or_text()?)
+            }
+            "absent" => {
+                self.advance();
+                Condition::Absent(self.parse_template_or_text()?)
+            }
+            "changed" => {
+                self.advance();
+                Condition::Changed(self.parse_region()?)
+            }
+            "text" | "pixel" | "" => {
+                let probe = self.parse_probe()?;
+                match self.parse_expectation()? {
+                    Some(expect) => Condition::Check(probe, expect),
+                    None => {
+                        return Err(self.unexpected("a comparison, 'contains', 'is' or 'matches'"))
+                    }
+                }
+            }
+            _ => return Err(self.unexpected(expected)),
+        };
+        Ok(condition)
+    }
+
+    /// `template<name>`, `text rect(...)` or `pixel x y`.
+    fn parse_probe(&mut self) -> Result<Probe, HonkSyntaxError> {
+        if let TokenKind::Template(_) = &self.peek().kind {
+            Ok(Probe::Template(self.parse_template()?))
+        } else if self.accept_keyword("text") {
+            Ok(Probe::Text(self.parse_region()?))
+        } else if self.accept_keyword("pixel") {
+            Ok(Probe::Pixel(self.parse_target()?))
+        } else {
+            Err(self.unexpected("template<name>, 'text' or 'pixel'"))
+        }
+    }
+
+    /// Parses a comparison, `contains`, `is` or `matches` test, or returns `None` if the next
+    /// token starts none of them.
+    fn parse_expectation(&mut self) -> Result<Option<Expectation>, HonkSyntaxError> {
+        if let TokenKind::Operator(operator) = &self.peek().kind {
+            let comparison = match operator.as_str() {
+                "==" => Comparison::Eq,
+                "!=" => Comparison::Ne,
+                "<" => Comparison::Lt,
+                "<=" => Comparison::Le,
+                ">" => Comparison::Gt,
+                _ => Comparison::Ge,
+            };
+            self.advance();
+            return Ok(Some(Expectation::Compare(comparison, self.parse_text()?)));
+        }
+        if self.accept_keyword("contains") {
+            return Ok(Some(Expectation::Contains(self.parse_text()?)));
+        }
+        if self.accept_keyword("is") {
+            return Ok(Some(Expectation::Is(self.parse_text()?)));
+        }
+        if self.accept_keyword("matches") {
+            return Ok(Some(Expectation::Matches(self.parse_regex()?)));
+        }
+        Ok(None)
+    }
+
+    /// A `/pattern/` literal, which must be a valid regex.
+    fn parse_regex(&mut self) -> Result<String, HonkSyntaxError> {
+        let token = self.peek().clone();
+        let TokenKind::Regex(pattern) = token.kind else {
+            return Err(self.unexpected("a regex (/pattern/)"));
+        };
+        if let Err(error) = regex::Regex::new(&pattern) {
+            return Err(error_at(format!("Invalid regex: {}", error), token.span));
+        }
+        self.advance();
+        Ok(pattern)
+    }
+
+    fn accept_keyword(&mut self, keyword: &str) -> bool {
+        let is_keyword = matches!(&self.peek().kind, TokenKind::Word(word) if word == keyword)
+            && self.peek_nth(1).kind != TokenKind::Equals;
+        if is_keyword {
+            self.advance();
+        }
+        is_keyword
+    }
+
+    fn parse_number(&mut self) -> Result<f64, HonkSyntaxError> {
+        match &self.peek().kind {
+            TokenKind::Number(number) => {
+                let value = number.parse::<f64>().map_err(|_| {
+                    error_at(format!("Invalid number {}", number), self.peek().span)
+                })?;
+                self.advance();
+                Ok(value)
+            }
+            _ => Err(self.unexpected("a number")),
+        }
+    }
+
+    fn parse_target(&mut self) -> Result<Target, HonkSyntaxError> {
+        match &self.peek().kind {
+            TokenKind::Template(name) => {
+                let name = name.clone();
+                self.advance();
+                let target = if let TokenKind::Index(index) = &self.peek().kind {
+                    let index = index.clone();
+                    self.advance();
+                    Target::Indexed { name, index }
+                } else {
+                    self.parse_edges(name)?
+                };
+                self.parse_offsets(target)
+            }
+            TokenKind::TextTarget(text) => {
+                let text = text.clone();
+                self.advance();
+                self.parse_offsets(Target::Text(text))
+            }
+            TokenKind::Word(word)
+                if word == "panel" && self.peek_nth(1).kind == TokenKind::LParen =>
+            {
+                let element = self.parse_element()?;
+                self.parse_offsets(Target::Element(element))
+            }
+            TokenKind::Word(_) if matches!(self.peek_nth(1).kind, TokenKind::Index(_)) => {
+                let cell = self.parse_cell()?;
+                self.parse_offsets(Target::Cell(cell))
+            }
+            TokenKind::Number(_) => {
+                let x = self.parse_number()?;
+                if self.peek().kind == TokenKind::Comma {
+                    self.advance();
+                }
+                let y = self.parse_number()?;
+                Ok(Target::Absolute { x, y })
+            }
+            _ => {
+                Err(self.unexpected("a target (template<name>, text<\"...\"> or x y coordinates)"))
+            }
+        }
+    }
+
+    /// `NAME[N].col(COLUMN)`, a cell of a table.
+    fn parse_cell(&mut self) -> Result<CellRef, HonkSyntaxError> {
+        let table = self.parse_name("a table name")?;
+        let row = match &self.peek().kind {
+            TokenKind::Index(index) => index.clone(),
+            _ => return Err(self.unexpected("a row index [N]")),
+        };
+        self.advance();
+        self.expect(TokenKind::Dot, "'.col(column)'")?;
+        if !self.accept_keyword("col") {
+            return Err(self.unexpected("'col'"));
+        }
+        self.expect(TokenKind::LParen, "'('")?;
+        let column = self.parse_text()?;
+        self.expect(TokenKind::RParen, "')'")?;
+        Ok(CellRef { table, row, column })
+    }
+
+    /// `panel("TITLE").KIND[N]`, an element found by parsing the screen.
+    fn parse_element(&mut self) -> Result<ElementRef, HonkSyntaxError> {
+        self.advance();
+        self.expect(TokenKind::LParen, "'('")?;
+        let panel = self.parse_text()?;
+        self.expect(TokenKind::RParen, "')'")?;
+        self.expect(TokenKind::Dot, "'.' and an element kind")?;
+        let expected = "an element kind (button, text_field, checkbox, text)";
+        let kind = match &self.peek().kind {
+            TokenKind::Word(word) => match word.as_str() {
+                "button" => ElementKind::Button,
+                "text_field" => ElementKind::TextField,
+                "checkbox" => ElementKind::Checkbox,
+                "text" => ElementKind::Text,
+                _ => return Err(self.unexpected(expected)),
+            },
+            _ => return Err(self.unexpected(expected)),
+        };
+        self.advance();
+        let index = match &self.peek().kind {
+            TokenKind::Index(index) => {
+                let index = index.clone();
+                self.advance();
+                index
+            }
+            _ => Text::literal("1"),
+        };
+        Ok(ElementRef { panel, kind, index })
+    }
+
+    /// `(COLUMN, ...)` after `table ... columns`, each column a name and an optional `=WIDTH`.
+    fn parse_columns(&mut self) -> Result<Vec<TableColumn>, HonkSyntaxError> {
+        self.expect(TokenKind::LParen, "'('")?;
+        let mut columns: Vec<TableColumn> = Vec::new();
+        loop {
+            let span = self.peek().span;
+            let name = match &self.peek().kind {
+                TokenKind::Word(word) => {
+                    let word = word.clone();
+                    self.advance();
+                    word
+                }
+                TokenKind::Str(_) => self.parse_literal_string()?,
+                _ => return Err(self.unexpected("a column name")),
+            };
+            if columns
+                .iter()
+                .any(|column| column.name.eq_ignore_ascii_case(&name))
+            {
+                return Err(error_at(format!("Duplicate column '{}'", name), span));
+            }
+            let width = if self.peek().kind == TokenKind::Equals {
+                self.advance();
+                Some(self.parse_number()?)
+            } else {
+                None
+            };
+            columns.push(TableColumn { name, width });
+
+            match self.peek().kind {
+                TokenKind::Comma => {
+                    self.advance();
+                }
+                TokenKind::RParen => {
+                    self.advance();
+                    return Ok(columns);
+                }
+                _ => return Err(self.unexpected("',' or ')'")),
+            }
+        }
+    }
+
+    /// Any `.side(distance)` offsets after `anchor`, making it a relative target.
+    fn parse_offsets(&mut self, anchor: Target) -> Result<Target, HonkSyntaxError> {
+        let mut offsets: Vec<Offset> = Vec::new();
+        while self.peek().kind == TokenKind::Dot {
+            self.advance();
+            let span = self.peek().span;
+            let side = self.parse_side("a side (right, left, above, below)")?;
+            self.expect(TokenKind::LParen, "'('")?;
+            let distance = self.parse_number()?;
+            self.expect(TokenKind::RParen, "')'")?;
+
+            let horizontal = |side: Side| matches!(side, Side::Left | Side::Right);
+            if offsets
+                .iter()
+                .any(|offset| horizontal(offset.side) == horizontal(side))
+            {
+                return Err(error_at(
+                    format!(
+                        "Target already has a {} offset",
+                        if horizontal(side) {
+                            "horizontal"
+                        } else {
+                            "vertical"
+                        }
+                    ),
+                    span,
+                ));
+            }
+            offsets.push(Offset { side, distance });
+        }
+        if offsets.is_empty() {
+            return Ok(anchor);
+        }
+        Ok(Target::Relative {
+            anchor: Box::new(anchor),
+            offsets,
+        })
+    }
+
+    fn parse_template(&mut self) -> Result<Target, HonkSyntaxError> {
+        match &self.peek().kind {
+            TokenKind::Template(name) => {
+                let name = name.clone();
+                self.advance();
+                self.parse_edges(name)
+            }
+            _ => Err(self.unexpected("template<name>")),
+        }
+    }
+
+    /// `.edges()` or `.edges(LABEL)` after `template<name>`, selecting edge parsing. Without it
+    /// the target is a plain template.
+    fn parse_edges(&mut self, name: String) -> Result<Target, HonkSyntaxError> {
+        let is_edges = self.peek().kind == TokenKind::Dot
+            && matches!(&self.peek_nth(1).kind, TokenKind::Word(word) if word == "edges");
+        if !is_edges {
+            return Ok(Target::Template(name));
+        }
+        self.advance();
+        self.advance();
+        self.expect(TokenKind::LParen, "'('")?;
+        let label = if self.peek().kind == TokenKind::RParen {
+            None
+        } else {
+            Some(self.parse_text()?)
+        };
+        self.expect(TokenKind::RParen, "')'")?;
+        Ok(Target::Edges { name, label })
+    }
+
+    /// `template<name>` or `text<"...">`, for conditions on whether something is on screen.
+    fn parse_template_or_text(&mut self) -> Result<Target, HonkSyntaxError> {
+        if let TokenKind::TextTarget(text) = &self.peek().kind {
+            let text = text.clone();
+            self.advance();
+            return Ok(Target::Text(text));
+        }
+        self.parse_template()
+    }
+
+    fn parse_text(&mut self) -> Result<Text, HonkSyntaxError> {
+        let text = match &self.peek().kind {
+            TokenKind::Str(text) => text.clone(),
+            TokenKind::Number(text) | TokenKind::Word(text) => Text::literal(text),
+            _ => return Err(self.unexpected("text")),
+        };
+        self.advance();
+        Ok(text)
+    }
+
+    /// A bare word used as a variable name.
+    fn parse_name(&mut self, expected: &str) -> Result<String, HonkSyntaxError> {
+        match &self.peek().kind {
+            TokenKind::Word(name)
+                if name.chars().all(|c| c.is_alphanumeric() || c == '_')
+                    && !name.starts_with(|c: char| c.is_ascii_digit()) =>
+            {
+                let name = name.clone();
+                self.advance();
+                Ok(name)
+            }
+            _ => Err(self.unexpected(expected)),
+        }
+    }
+
+    /// A string literal without variable references, e.g. a file path.
+    fn parse_literal_string(&mut self) -> Result<String, HonkSyntaxError> {
+        match &self.peek().kind {
+            TokenKind::Str(text) => {
+                let value = text.as_literal().ok_or_else(|| {
+                    error_at(
+                        "Variables are not allowed here".to_string(),
+                        self.peek().span,
+                    )
+                })?;
+                self.advance();
+                Ok(value)
+            }
+            _ => Err(self.unexpected("a quoted string")),
+        }
+    }
+
+    fn parse_loop_source(&mut self) -> Result<LoopSource, HonkSyntaxError> {
+        if let TokenKind::Template(_) = &self.peek().kind {
+            return Ok(LoopSource::Matches(self.parse_template()?));
+        }
+        let is_csv = matches!(&self.peek().kind, TokenKind::Word(word) if word == "csv")
+            && self.peek_nth(1).kind == TokenKind::LParen;
+        if !is_csv {
+            return self
+                .parse_name("csv(\"file.csv\"), template<name> or a table name")
+                .map(LoopSource::Table);
+        }
+        self.advance();
+        self.expect(TokenKind::LParen, "'('")?;
+        let path = self.parse_literal_string()?;
+        self.expect(TokenKind::RParen, "')'")?;
+        Ok(LoopSource::Csv(path))
+    }
+
+    fn parse_direction(&mut self) -> Result<ScrollDirection, HonkSyntaxError> {
+        let direction = match &self.peek().kind {
+            TokenKind::Word(word) => match word.as_str() {
+                "up" => ScrollDirection::Up,
+                "down" => ScrollDirection::Down,
+                "left" => ScrollDirection::Left,
+                "right" => ScrollDirection::Right,
+                _ => return Err(self.unexpected("a scroll direction (up, down, left, right)")),
+            },
+            _ => return Err(self.unexpected("a scroll direction (up, down, left, right)")),
+        };
+        self.advance();
+        Ok(direction)
+    }
+
+    fn parse_side(&mut self, expected: &str) -> Result<Side, HonkSyntaxError> {
+        let side = match &self.peek().kind {
+            TokenKind::Word(word) => match word.as_str() {
+                "left" => Side::Left,
+                "right" => Side::Right,
+                "above" => Side::Above,
+                "below" => Side::Below,
+                _ => return Err(self.unexpected(expected)),
+            },
+            _ => return Err(self.unexpected(expected)),
+        };
+        self.advance();
+        Ok(side)
+    }
+
+    fn parse_value_type(&mut self) -> Result<ValueType, HonkSyntaxError> {
+        let value_type = match &self.peek().kind {
+            TokenKind::Word(word) => match word.as_str() {
+                "text" => ValueType::Text,
+                "number" => ValueType::Number,
+                "date" => ValueType::Date,
+                _ => return Err(self.unexpected("a type (text, number, date)")),
+            },
+            _ => return Err(self.unexpected("a type (text, number, date)")),
+        };
+        self.advance();
+        Ok(value_type)
+    }
+
+    fn parse_region(&mut self) -> Result<Region, HonkSyntaxError> {
+        if !matches!(&self.peek().kind, TokenKind::Word(word) if word == "rect") {
+            return Err(self.unexpected("rect(x, y, width, height)"));
+        }
+        self.advance();
+        self.expect(TokenKind::LParen, "'('")?;
+        let x = self.parse_number()?;
+        self.expect(TokenKind::Comma, "','")?;
+        let y = self.parse_number()?;
+        self.expect(TokenKind::Comma, "','")?;
+        let width = self.parse_number()?;
+        self.expect(TokenKind::Comma, "','")?;
+        let height = self.parse_number()?;
+        self.expect(TokenKind::RParen, "')'")?;
+        Ok(Region {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    fn parse_options(&mut self) -> Result<Vec<VerbOption>, HonkSyntaxError> {
+        let mut options = Vec::new();
+        while !self.at_line_end() && !self.at_block_start() {
+            let (name, span) = match &self.peek().kind {
+                TokenKind::Word(name) if self.peek_nth(1).kind == TokenKind::Equals => {
+                    (name.clone(), self.peek().span)
+                }
+                _ => return Err(self.unexpected("an option (name=value) or end of line")),
+            };
+            self.advance();
+            self.advance();
+
+            let value = match &self.peek().kind {
+                TokenKind::Number(_) => OptionValue::Number(self.parse_number()?),
+                TokenKind::Word(word) if word == "rect" => {
+                    OptionValue::Region(self.parse_region()?)
+                }
+                TokenKind::Word(word) => {
+                    let word = word.clone();
+                    self.advance();
+                    OptionValue::Word(word)
+                }
+                TokenKind::Str(_) => OptionValue::Str(self.parse_literal_string()?),
+                _ => return Err(self.unexpected(&format!("a value for option '{}'", name))),
+            };
+            options.push(VerbOption { name, value, span });
+        }
+        Ok(options)
+    }
+
+    /// Applies options shared by every verb, rejecting anything else.
+    fn step_option(
+        &self,
+        options: &mut StepOptions,
+        option: VerbOption,
+    ) -> Result<(), HonkSyntaxError> {
+        match option.name.as_str() {
+            "timeout" => options.timeout = Some(self.integer_option(option)?),
+            "zone" => match option.value {
+                OptionValue::Region(region) => options.check_zone = Some(region),
+                _ => {
+                    return Err(error_at(
+                        "Option 'zone' expects rect(x, y, width, height)".to_string(),
+                        option.span,
+                    ))
+                }
+            },
+            other => return Err(error_at(format!("Unknown option '{}'", other), option.span)),
+        }
+        Ok(())
+    }
+
+    fn integer_option(&self, option: VerbOption) -> Result<u64, HonkSyntaxError> {
+        match option.value {
+            OptionValue::Number(value) if value >= 0.0 && value.fract() == 0.0 => Ok(value as u64),
+            _ => Err(error_at(
+                format!("Option '{}' expects a whole number", option.name),
+                option.span,
+            )),
+        }
+    }
+
+    /// `integer_option` for counts, which must fit a `u32`.
+    fn count_option(&self, option: VerbOption) -> Result<u32, HonkSyntaxError> {
+        let (name, span) = (option.name.clone(), option.span);
+        u32::try_from(self.integer_option(option)?).map_err(|_| {
+            error_at(
+                format!("Option '{}' must be at most {}", name, u32::MAX),
+                span,
+            )
+        })
+    }
+
+    fn fraction_option(&self, option: VerbOption) -> Result<f64, HonkSyntaxError> {
+        match option.value {
+            OptionValue::Number(value) if (0.0..=1.0).contains(&value) => Ok(value),
+            _ => Err(error_at(
+                format!("Option '{}' expects a number between 0 and 1", option.name),
+                option.span,
+            )),
+        }
+    }
+
+    fn anchor_option(&self, option: VerbOption) -> Result<RectAnchor, HonkSyntaxError> {
+        let anchor = match &option.value {
+            OptionValue::Word(word) | OptionValue::Str(word) => match word.as_str() {
+                "top-left" => Some(RectAnchor::TopLeft),
+                "top-right" => Some(RectAnchor::TopRight),
+                "bottom-left" => Some(RectAnchor::BottomLeft),
+                "bottom-right" => Some(RectAnchor::BottomRight),
+                "center" => Some(RectAnchor::Center),
+                _ => None,
+            },
+            _ => None,
+        };
+        anchor.ok_or_else(|| {
+            error_at(
+                "Option 'anchor' expects top-left, top-right, bottom-left, bottom-right or center"
+                    .to_string(),
+                option.span,
+            )
+        })
+    }
+
+    fn button_option(&self, option: VerbOption) -> Result<MouseButton, HonkSyntaxError> {
+        let button = match &option.value {
+            OptionValue::Word(word) | OptionValue::Str(word) => match word.as_str() {
+                "left" => Some(MouseButton::Left),
+                "right" => Some(MouseButton::Right),
+                "middle" => Some(MouseButton::Middle),
+                _ => None,
+            },
+            _ => None,
+        };
+        button.ok_or_else(|| {
+            error_at(
+                "Option 'button' expects left, right or middle".to_string(),
+                option.span,
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pitch_example() {
+        let script = parse(
+            "begin: check template<Epic EHR>\n\
+             click template<chart-review-button>\n\
+             input template<mrn-text-box> 000289401\n",
+        )
+        .expect("Failed to parse");
+
+        assert_eq!(script.statements.len(), 3);
+        assert_eq!(script.statements[0].label.as_deref(), Some("begin"));
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Check(CheckNode {
+                probe: Probe::Template(Target::Template("Epic EHR".to_string())),
+                expect: None,
+            })
+        );
+        assert_eq!(
+            script.statements[2].kind,
+            StatementKind::Input(InputNode {
+                target: Target::Template("mrn-text-box".to_string()),
+                text: Text::literal("000289401"),
+                submit: false,
+            })
+        );
+        assert_eq!(script.statements[2].span.line, 3);
+        assert_eq!(script.find_label("begin"), Some(0));
+    }
+
+    #[test]
+    fn parses_absolute_targets_and_options() {
+        let script =
+            parse("click 120, 40 button=right timeout=2000 zone=rect(0, 0, 300, 200)").unwrap();
+        let statement = &script.statements[0];
+
+        assert_eq!(
+            statement.kind,
+            StatementKind::Click(ClickNode {
+                target: Target::Absolute { x: 120.0, y: 40.0 },
+                button: MouseButton::Right,
+                clicks: 1,
+                modifiers: None,
+                menu_item: None,
+            })
+        );
+        assert_eq!(statement.options.timeout, Some(2000));
+        assert_eq!(
+            statement.options.check_zone,
+            Some(Region {
+                x: 0.0,
+                y: 0.0,
+                width: 300.0,
+                height: 200.0
+            })
+        );
+    }
+
+    #[test]
+    fn parses_scroll_modes() {
+        let script = parse(
+            "scroll down until template<last-row> max=20\nscroll up in rect(10, 10, 400, 600) step=3",
+        )
+        .unwrap();
+
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Scroll(ScrollNode {
+                direction: ScrollDirection::Down,
+                mode: ScrollMode::Seek(Target::Template("last-row".to_string())),
+                max_scrolls: Some(20),
+                step: None,
+                body: vec![],
+            })
+        );
+        assert!(matches!(
+            &script.statements[1].kind,
+            StatementKind::Scroll(ScrollNode {
+                mode: ScrollMode::Iterative(_),
+                step: Some(3),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_scroll_counts_out_of_range() {
+        let err = parse("scroll up in rect(10, 10, 400, 600) step=0").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 37));
+        assert!(err.message.contains("greater than 0"));
+
+        let err = parse("scroll down until template<last-row> max=5000000000").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 38));
+        let err = parse("select template<a> \"b\" max=5000000000").unwrap_err();
+        assert!(err.message.contains("at most"));
+    }
+
+    #[test]
+    fn scroll_do_block() {
+        let script =
+            parse("scroll down in rect(0, 0, 400, 600) max=5 do\n  click 10 10\nend\nclick 20 20")
+                .unwrap();
+        assert_eq!(script.statements.len(), 2);
+        match &script.statements[0].kind {
+            StatementKind::Scroll(node) => {
+                assert_eq!(node.max_scrolls, Some(5));
+                assert_eq!(node.body.len(), 1);
+            }
+            other => panic!("Expected scroll, found {:?}", other),
+        }
+
+        let err = parse("scroll down until template<row> do\n  click 10 10\nend").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 33));
+    }
+
+    #[test]
+    fn parses_hover() {
+        let script = parse(
+            "hover template<lab-row> until template<lab-flyout> dwell=800\n\
+             hover 40 60 until changed rect(200, 0, 300, 400)",
+        )
+        .unwrap();
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Hover(HoverNode {
+                target: Target::Template("lab-row".to_string()),
+                until: HoverUntil::Visible(Target::Template("lab-flyout".to_string())),
+                dwell: Some(800),
+            })
+        );
+        assert!(matches!(
+            &script.statements[1].kind,
+            StatementKind::Hover(HoverNode {
+                until: HoverUntil::Changed(_),
+                dwell: None,
+                ..
+            })
+        ));
+        assert!(parse("hover template<lab-row> template<lab-flyout>").is_err());
+    }
+
+    #[test]
+    fn parses_copy_and_paste() {
+        let script = parse(
+            "copy template<note-start> to 400 900 into note hold=1500\n\
+             paste template<reply> \"${note}\"\n\
+             paste template<reply> timeout=200",
+        )
+        .unwrap();
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Copy(CopyNode {
+                from: Target::Template("note-start".to_string()),
+                to: Target::Absolute { x: 400.0, y: 900.0 },
+                variable: "note".to_string(),
+                hold: Some(1500),
+            })
+        );
+        assert!(matches!(
+            &script.statements[1].kind,
+            StatementKind::Paste(PasteNode { text: Some(_), .. })
+        ));
+        assert!(matches!(
+            &script.statements[2].kind,
+            StatementKind::Paste(PasteNode { text: None, .. })
+        ));
+        assert_eq!(script.statements[2].options.timeout, Some(200));
+    }
+
+    #[test]
+    fn parses_check_expectations() {
+        let script = parse(
+            "check text rect(0, 0, 80, 20) >= 7.0\n\
+             check pixel 10 20 == \"#ff0000\"\n\
+             check text rect(0, 0, 80, 20) matches /\\d{2}\\/\\d{4}/\n\
+             if template<banner> == false\n\
+             end",
+        )
+        .unwrap();
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Check(CheckNode {
+                probe: Probe::Text(Region {
+                    x: 0.0,
+                    y: 0.0,
+                    width: 80.0,
+                    height: 20.0
+                }),
+                expect: Some(Expectation::Compare(Comparison::Ge, Text::literal("7.0"))),
+            })
+        );
+        assert!(matches!(
+            &script.statements[1].kind,
+            StatementKind::Check(CheckNode {
+                probe: Probe::Pixel(Target::Absolute { .. }),
+                expect: Some(Expectation::Compare(Comparison::Eq, _)),
+            })
+        ));
+        assert!(matches!(
+            &script.statements[2].kind,
+            StatementKind::Check(CheckNode {
+                expect: Some(Expectation::Matches(pattern)),
+                ..
+            }) if pattern == r"\d{2}/\d{4}"
+        ));
+        assert!(matches!(
+            &script.statements[3].kind,
+            StatementKind::If(IfNode {
+                condition: Condition::Check(Probe::Template(_), _),
+                ..
+            })
+        ));
+
+        let err = parse("check text rect(0, 0, 1, 1)").unwrap_err();
+        assert!(err.message.contains("'matches'"));
+        let err = parse("check text rect(0, 0, 1, 1) matches /(/").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 37));
+    }
+
+    #[test]
+    fn parses_press() {
+        let script = parse("press ctrl+shift+F5\npress tab x3 zone=rect(0, 0, 50, 50)").unwrap();
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Press(PressNode {
+                chord: "ctrl+shift+F5".to_string(),
+                repeat: None,
+            })
+        );
+        assert_eq!(
+            script.statements[1].kind,
+            StatementKind::Press(PressNode {
+                chord: "tab".to_string(),
+                repeat: Some(3),
+            })
+        );
+        assert!(script.statements[1].options.check_zone.is_some());
+
+        let err = parse("press ctrl+hyper+a").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 7));
+        assert!(parse("press tab x0").is_err());
+    }
+
+    #[test]
+    fn parses_drag() {
+        let script = parse("drag template<series-3> to 640 200 speed=300 timeout=1500").unwrap();
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Drag(DragNode {
+                from: Target::Template("series-3".to_string()),
+                to: Target::Absolute { x: 640.0, y: 200.0 },
+                speed: Some(300),
+            })
+        );
+        assert_eq!(script.statements[0].options.timeout, Some(1500));
+        assert!(parse("drag 1 1 2 2").is_err());
+    }
+
+    #[test]
+    fn parses_click_variants() {
+        let script = parse(
+            "click template<row> double with ctrl+shift\nclick template<row> menu template<Open chart>",
+        )
+        .unwrap();
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Click(ClickNode {
+                target: Target::Template("row".to_string()),
+                button: MouseButton::Left,
+                clicks: 2,
+                modifiers: Some("ctrl+shift".to_string()),
+                menu_item: None,
+            })
+        );
+        assert_eq!(
+            script.statements[1].kind,
+            StatementKind::Click(ClickNode {
+                target: Target::Template("row".to_string()),
+                button: MouseButton::Left,
+                clicks: 1,
+                modifiers: None,
+                menu_item: Some(Target::Template("Open chart".to_string())),
+            })
+        );
+
+        let err = parse("click template<row> with ctrl+q").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 26));
+        assert!(parse("click template<row> double menu template<Open>").is_err());
+        assert!(parse("click template<row> menu 10 10").is_err());
+    }
+
+    #[test]
+    fn parses_waits() {
+        let script = parse(
+            "wait for template<chart> into chart poll=100\n\
+             wait until template<spinner> gone timeout=30000\n\
+             wait until rect(0, 0, 800, 600) stable quiet=500",
+        )
+        .unwrap();
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Wait(WaitNode {
+                until: WaitUntil::Visible(Target::Template("chart".to_string())),
+                variable: Some("chart".to_string()),
+                poll: Some(100),
+            })
+        );
+        assert_eq!(
+            script.statements[1].kind,
+            StatementKind::Wait(WaitNode {
+                until: WaitUntil::Gone(Target::Template("spinner".to_string())),
+                variable: None,
+                poll: None,
+            })
+        );
+        assert_eq!(script.statements[1].options.timeout, Some(30000));
+        assert!(matches!(
+            script.statements[2].kind,
+            StatementKind::Wait(WaitNode {
+                until: WaitUntil::Stable {
+                    quiet: Some(500),
+                    ..
+                },
+                ..
+            })
+        ));
+        assert!(parse("wait until template<spinner> quiet=500").is_err());
+        assert!(parse("wait for template<chart> quiet=500").is_err());
+        assert!(parse("wait until template<spinner> gone into spinner").is_err());
+    }
+
+    #[test]
+    fn parses_select() {
+        let script = parse(
+            "select template<visit-type> \"Office Visit\" popup=rect(100, 200, 300, 400)\n\
+             select template<provider> template<dr-smith> max=5",
+        )
+        .unwrap();
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Select(SelectNode {
+                target: Target::Template("visit-type".to_string()),
+                option: SelectOption::Text(Text::literal("Office Visit")),
+                popup: Some(Region {
+                    x: 100.0,
+                    y: 200.0,
+                    width: 300.0,
+                    height: 400.0
+                }),
+                max_scrolls: None,
+            })
+        );
+        assert_eq!(
+            script.statements[1].kind,
+            StatementKind::Select(SelectNode {
+                target: Target::Template("provider".to_string()),
+                option: SelectOption::Template(Target::Template("dr-smith".to_string())),
+                popup: None,
+                max_scrolls: Some(5),
+            })
+        );
+        assert!(parse("select template<provider> popup=3").is_err());
+    }
+
+    #[test]
+    fn parses_checkbox() {
+        let script =
+            parse("checkbox template<consent-on> template<consent-off> unchecked confidence=0.9")
+                .unwrap();
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Checkbox(CheckboxNode {
+                checked: Target::Template("consent-on".to_string()),
+                unchecked: Target::Template("consent-off".to_string()),
+                desired: false,
+                confidence: Some(0.9),
+            })
+        );
+        assert!(parse("checkbox template<on> template<off>").is_err());
+        assert!(parse("checkbox template<on> template<off> checked confidence=90").is_err());
+    }
+
+    #[test]
+    fn input_submit_and_quoted_text() {
+        let script = parse("input template<search> \"radical nephrectomy\" submit").unwrap();
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Input(InputNode {
+                target: Target::Template("search".to_string()),
+                text: Text::literal("radical nephrectomy"),
+                submit: true,
+            })
+        );
+    }
+
+    #[test]
+    fn errors_carry_line_and_column() {
+        let err = parse("click template<a>\n\nclik template<b>").unwrap_err();
+        assert_eq!((err.line, err.column), (3, 1));
+        assert!(err.message.contains("Unknown verb 'clik'"));
+
+        let err = parse("click template<a> button=sideways").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 19));
+
+        let err = parse("input template<a>").unwrap_err();
+        assert_eq!((err.line, err.column), (1, 18));
+    }
+
+    #[test]
+    fn parses_set_and_csv_loop() {
+        let script = parse(
+            "set site = \"Main Campus\"\n\
+             for row in csv(\"mrns.csv\")\n\
+             \tinput template<mrn-text-box> ${row.mrn} submit\n\
+             \tcheck template<chart-open>\n\
+             end\n\
+             click template<done>",
+        )
+        .unwrap();
+
+        assert_eq!(script.statements.len(), 3);
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Set(SetNode {
+                name: "site".to_string(),
+                value: Text::literal("Main Campus"),
+            })
+        );
+        let StatementKind::For(node) = &script.statements[1].kind else {
+            panic!("Expected a for loop");
+        };
+        assert_eq!(node.variable, "row");
+        assert_eq!(node.source, LoopSource::Csv("mrns.csv".to_string()));
+        assert_eq!(node.body.len(), 2);
+        assert_eq!(node.body[1].span.line, 4);
+        assert_eq!(
+            script.template_names(),
+            vec!["mrn-text-box", "chart-open", "done"]
+        );
+    }
+
+    #[test]
+    fn parses_indexed_templates() {
+        let script = parse(
+            "click template<open-result>[3]\n\
+             for hit in template<open-result>\n\
+             \tclick template<open-result>[${hit.index}]\n\
+             end",
+        )
+        .unwrap();
+
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Click(ClickNode {
+                target: Target::Indexed {
+                    name: "open-result".to_string(),
+                    index: Text::literal("3"),
+                },
+                button: MouseButton::Left,
+                clicks: 1,
+                modifiers: None,
+                menu_item: None,
+            })
+        );
+        let StatementKind::For(node) = &script.statements[1].kind else {
+            panic!("Expected a for loop");
+        };
+        assert_eq!(
+            node.source,
+            LoopSource::Matches(Target::Template("open-result".to_string()))
+        );
+        assert_eq!(script.template_names(), vec!["open-result"]);
+        assert!(parse("wait for template<row>[2]").is_err());
+    }
+
+    #[test]
+    fn parses_read_statements() {
+        let script = parse(
+            "read right of text<\"HbA1c\"> into hba1c as number matching /([\\d.]+)\\s*%/ width=120\n\
+             read rect(10, 20, 300, 16) into attending",
+        )
+        .unwrap();
+
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Read(ReadNode {
+                area: ReadArea::Relative {
+                    placement: Placement::Beside(Side::Right),
+                    anchor: Target::Text(Text::literal("HbA1c")),
+                    width: Some(120),
+                    height: None,
+                },
+                variable: "hba1c".to_string(),
+                value_type: ValueType::Number,
+                pattern: Some(r"([\d.]+)\s*%".to_string()),
+            })
+        );
+        assert_eq!(
+            script.statements[1].kind,
+            StatementKind::Read(ReadNode {
+                area: ReadArea::Region(Region {
+                    x: 10.0,
+                    y: 20.0,
+                    width: 300.0,
+                    height: 16.0,
+                }),
+                variable: "attending".to_string(),
+                value_type: ValueType::Text,
+                pattern: None,
+            })
+        );
+        assert!(parse("read rect(0, 0, 10, 10) into x width=40").is_err());
+        assert!(parse("read rect(0, 0, 10, 10) into x as money").is_err());
+        assert!(parse("read behind of template<label> into x").is_err());
+    }
+
+    #[test]
+    fn parses_relative_targets() {
+        let script = parse(
+            "click template<mrn-label>.right(40)\n\
+             input text<\"Dose\">.below(12).right(5) 20\n\
+             read at template<row>[2].right(10) into dose anchor=center width=80",
+        )
+        .unwrap();
+
+        let StatementKind::Click(node) = &script.statements[0].kind else {
+            panic!("Expected a click");
+        };
+        assert_eq!(
+            node.target,
+            Target::Relative {
+                anchor: Box::new(Target::Template("mrn-label".to_string())),
+                offsets: vec![Offset {
+                    side: Side::Right,
+                    distance: 40.0,
+                }],
+            }
+        );
+        let StatementKind::Input(node) = &script.statements[1].kind else {
+            panic!("Expected an input");
+        };
+        assert_eq!(
+            node.target,
+            Target::Relative {
+                anchor: Box::new(Target::Text(Text::literal("Dose"))),
+                offsets: vec![
+                    Offset {
+                        side: Side::Below,
+                        distance: 12.0,
+                    },
+                    Offset {
+                        side: Side::Right,
+                        distance: 5.0,
+                    },
+                ],
+            }
+        );
+        let StatementKind::Read(node) = &script.statements[2].kind else {
+            panic!("Expected a read");
+        };
+        assert_eq!(
+            node.area,
+            ReadArea::Relative {
+                placement: Placement::At(RectAnchor::Center),
+                anchor: Target::Relative {
+                    anchor: Box::new(Target::Indexed {
+                        name: "row".to_string(),
+                        index: Text::literal("2"),
+                    }),
+                    offsets: vec![Offset {
+                        side: Side::Right,
+                        distance: 10.0,
+                    }],
+                },
+                width: Some(80),
+                height: None,
+            }
+        );
+        assert_eq!(script.template_names(), vec!["mrn-label", "row"]);
+
+        assert!(parse("click template<label>.right(4).left(2)").is_err());
+        assert!(parse("click template<label>.inside(4)").is_err());
+        assert!(parse("click template<label>.right").is_err());
+        assert!(parse("read right of template<label> into x anchor=center").is_err());
+    }
+
+    #[test]
+    fn parses_tables() {
+        let script = parse(
+            "table labs rect(100, 200, 400, 300) columns(Test=120, Result, \"Ref Range\"=90) header=24\n\
+             for row in labs\n\
+             \tclick labs[${row.index}].col(Result).right(10)\n\
+             end\n\
+             read labs[2].col(\"Ref Range\") into range\n\
+             export labs to \"labs.csv\"",
+        )
+        .unwrap();
+
+        assert_eq!(
+            script.statements[0].kind,
+            StatementKind::Table(TableNode {
+                name: "labs".to_string(),
+                region: Region {
+                    x: 100.0,
+                    y: 200.0,
+                    width: 400.0,
+                    height: 300.0,
+                },
+                columns: vec![
+                    TableColumn {
+                        name: "Test".to_string(),
+                        width: Some(120.0),
+                    },
+                    TableColumn {
+                        name: "Result".to_string(),
+                        width: None,
+                    },
+                    TableColumn {
+                        name: "Ref Range".to_string(),
+                        width: Some(90.0),
+                    },
+                ],
+                header: Some(24),
+                pitch: None,
+                detect: false,
+            })
+        );
+        let StatementKind::For(node) = &script.statements[1].kind else {
+            panic!("Expected a for loop");
+        };
+        assert_eq!(node.source, LoopSource::Table("labs".to_string()));
+        let mut row = Text::default();
+        row.push_variable("row.index".to_string());
+        assert_eq!(
+            node.body[0].kind.targets()[0],
+            &Target::Relative {
+                anchor: Box::new(Target::Cell(CellRef {
+                    table: "labs".to_string(),
+                    row,
+                    column: Text::literal("Result"),
+                })),
+                offsets: vec![Offset {
+                    side: Side::Right,
+                    distance: 10.0,
+                }],
+            }
+        );
+        let StatementKind::Read(node) = &script.statements[2].kind else {
+            panic!("Expected a read");
+        };
+        assert_eq!(
+            node.area,
+            ReadArea::Cell(CellRef {
+                table: "labs".to_string(),
+                row: Text::literal("2"),
+                column: Text::literal("Ref Range"),
+            })
+        );
+        assert_eq!(
+            script.statements[3].kind,
+            StatementKind::Export(ExportNode {
+                table: "labs".to_string(),
+                path: "labs.csv".to_string(),
+            })
+        );
+
+        assert!(parse("table t rect(0, 0, 10, 10) columns(A, a)").is_err());
+        assert!(parse("table t rect(0, 0, 10, 10) columns(A) pitch=0").is_err());
+        assert!(parse("table t rect(0, 0, 10, 10)").is_err());
+
+        let detected = parse("table t rect(0, 0, 10, 10) detect columns(A, \"B C\")").unwrap();
+        let StatementKind::Table(node) = &detected.statements[0].kind else {
+            panic!("Expected a table");
+        };
+        assert!(node.detect);
+        assert_eq!(node.columns[1].name, "B C");
+        let StatementKind::Table(node) = &parse("table t rect(0, 0, 10, 10) detect")
+            .unwrap()
+            .statements[0]
+            .kind
+        else {
+            panic!("Expected a table");
+        };
+        assert!(node.detect && node.columns.is_empty());
+        assert!(parse("table t rect(0, 0, 10, 10) detect columns(A=20)").is_err());
+        assert!(parse("table t rect(0, 0, 10, 10) detect pitch=20").is_err());
+        assert!(parse("click labs[1]").is_err());
+        assert!(parse("click labs[1].row(A)").is_err());
+    }
+
+    #[test]
+    fn parses_text_targets() {
+        let script = parse(
+            "if visible text<\"Chart Review\">\n\
+             \tclick text<\"Chart Review\"> double\n\
+             end",
+        )
+        .unwrap();
+
+        let StatementKind::If(node) = &script.statements[0].kind else {
+            panic!("Expected an if statement");
+        };
+        let target = Target::Text(Text::literal("Chart Review"));
+        assert_eq!(node.condition, Condition::Visible(target.clone()));
+        assert_eq!(
+            node.then_body[0].kind,
+            StatementKind::Click(ClickNode {
+                target,
+                button: MouseButton::Left,
+                clicks: 2,
+                modifiers: None,
+                menu_item: None,
+            })
+        );
+        assert!(script.template_names().is_empty());
+        assert!(parse("wait for text<\"Chart Review\">").is_err());
+    }
+
+    #[test]
+    fn parses_element_targets() {
+        let script = parse(
+            "input panel(\"Demographics\").text_field[2] Smith\n\
+             click panel(Orders).button[1].right(10)\n\
+             click panel(Orders).checkbox",
+        )
+        .unwrap();
+
+        let StatementKind::Input(node) = &script.statements[0].kind else {
+            panic!("Expected an input");
+        };
+        assert_eq!(
+            node.target,
+            Target::Element(ElementRef {
+                panel: Text::literal("Demographics"),
+                kind: ElementKind::TextField,
+                index: Text::literal("2"),
+            })
+        );
+        let StatementKind::Click(node) = &script.statements[1].kind else {
+            panic!("Expected a click");
+        };
+        assert_eq!(
+            node.target,
+            Target::Relative {
+                anchor: Box::new(Target::Element(ElementRef {
+                    panel: Text::literal("Orders"),
+                    kind: ElementKind::Button,
+                    index: Text::literal("1"),
+                })),
+                offsets: vec![Offset {
+                    side: Side::Right,
+                    distance: 10.0,
+                }],
+            }
+        );
+        let StatementKind::Click(node) = &script.statements[2].kind else {
+            panic!("Expected a click");
+        };
+        assert_eq!(
+            node.target,
+            Target::Element(ElementRef {
+                panel: Text::literal("Orders"),
+                kind: ElementKind::Checkbox,
+                index: Text::literal("1"),
+            })
+        );
+        assert!(script.template_names().is_empty());
+        assert!(parse("click panel(Orders).slider").is_err());
+        assert!(parse("click panel(Orders)").is_err());
+    }
+
+    #[test]
+    fn parses_edge_targets() {
+        let script = parse(
+            "click template<blue-button>.edges(\"Save\").below(4)\n\
+             wait for template<blue-button>.edges()",
+        )
+        .unwrap();
+
+        let StatementKind::Click(node) = &script.statements[0].kind else {
+            panic!("Expected a click");
+        };
+        assert_eq!(
+            node.target,
+            Target::Relative {
+                anchor: Box::new(Target::Edges {
+                    name: "blue-button".to_string(),
+                    label: Some(Text::literal("Save")),
+                }),
+                offsets: vec![Offset {
+                    side: Side::Below,
+                    distance: 4.0,
+                }],
+            }
+        );
+        let StatementKind::Wait(node) = &script.statements[1].kind else {
+            panic!("Expected a wait");
+        };
+        assert_eq!(
+            node.until,
+            WaitUntil::Visible(Target::Edges {
+                name: "blue-button".to_string(),
+                label: None,
+            })
+        );
+        assert_eq!(script.template_names(), vec!["blue-button".to_string()]);
+        assert!(parse("click template<blue-button>.edges").is_err());
+        assert!(parse("click template<blue-button>.edges(\"Save\"").is_err());
+        assert!(parse("click template<blue-button>[2].edges()").is_err());
+    }
+
+    #[test]
+    fn parses_if_else_chains() {
+        let script = parse(
+            "if visible template<patient already open>\n\
+             \tclick template<ok>\n\
+             else if not changed rect(0, 0, 100, 100) timeout=2000\n\
+             \tclick 5 5\n\
+             else\n\
+             \tinput template<mrn-text-box> ${mrn}\n\
+             end\n\
+             if text rect(10, 10, 200, 20) contains \"Discharged\"\n\
+             end",
+        )
+        .unwrap();
+
+        assert_eq!(script.statements.len(), 2);
+        let StatementKind::If(outer) = &script.statements[0].kind else {
+            panic!("Expected an if statement");
+        };
+        assert_eq!(
+            outer.condition,
+            Condition::Visible(Target::Template("patient already open".to_string()))
+        );
+        assert_eq!(outer.then_body.len(), 1);
+
+        let inner = &outer.else_body[0];
+        assert_eq!(inner.options.timeout, Some(2000));
+        let StatementKind::If(inner_node) = &inner.kind else {
+            panic!("Expected an else-if statement");
+        };
+        assert!(matches!(inner_node.condition, Condition::Not(_)));
+        assert_eq!(inner_node.else_body[0].span.line, 6);
+
+        assert!(matches!(
+            &script.statements[1].kind,
+            StatementKind::If(IfNode {
+                condition: Condition::Check(Probe::Text(_), Expectation::Contains(_)),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn unclosed_loop_reports_missing_end() {
+        let err = parse("for row in csv(\"mrns.csv\")\nclick 1 1\n").unwrap_err();
+        assert_eq!(err.line, 3);
+        assert!(err.message.contains("'end'"));
+    }
+
+    #[test]
+    fn rejects_duplicate_and_dangling_labels() {
+        assert!(parse("a: click 1 1\na: click 2 2").is_err());
+        assert!(parse("click 1 1\nend:").is_err());
+        let script = parse("begin:\nclick 1 1").unwrap();
+        assert_eq!(script.statements[0].label.as_deref(), Some("begin"));
+    }
+}
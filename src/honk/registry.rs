@@ -0,0 +1,174 @@
+//! Resolves `template<name>` references in scripts to image templates on disk.
+use crate::errors::HonkRuntimeError;
+use crate::nav::location::ImageTemplate;
+use crate::nav::strategy::{LocationStrategyType, ScaleCache, ScaleRange};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Image formats that can be used as templates.
+pub const TEMPLATE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "bmp"];
+
+/// An image in a template directory as its name, the index of its extension in
+/// `TEMPLATE_EXTENSIONS` and its path.
+type TemplateFile = (String, usize, PathBuf);
+
+/// A directory of template images. `template<chart-review-button>` resolves to
+/// `<directory>/chart-review-button.png` (or any other extension in `TEMPLATE_EXTENSIONS`).
+/// Every template resolved shares the registry's scale cache, so the scale a template was found at
+/// by one statement is tried first by the next.
+/// Parameters:
+/// * `directory`: Folder holding the template images.
+/// * `strategy`: Optional. Location strategy used for every resolved template. Default is
+///   `LocationStrategyType::TemplateMatching`.
+/// * `scale_range`: Optional. Scales at which templates are matched. Default is
+///   `ScaleRange::default()`.
+pub struct TemplateRegistry {
+    pub directory: PathBuf,
+    pub strategy: LocationStrategyType,
+    pub scale_range: Option<ScaleRange>,
+    scale_cache: ScaleCache,
+}
+
+impl TemplateRegistry {
+    pub fn new(
+        directory: &Path,
+        strategy: Option<LocationStrategyType>,
+        scale_range: Option<ScaleRange>,
+    ) -> Self {
+        TemplateRegistry {
+            directory: directory.to_path_buf(),
+            strategy: strategy.unwrap_or(LocationStrategyType::TemplateMatching),
+            scale_range,
+            scale_cache: ScaleCache::default(),
+        }
+    }
+
+    /// Path of the image backing the template `name`, if one exists in the directory. When several
+    /// images share the name, the first extension in `TEMPLATE_EXTENSIONS` wins.
+    pub fn path_for(&self, name: &str) -> Option<PathBuf> {
+        self.templates()
+            .ok()?
+            .into_iter()
+            .filter(|(stem, _, _)| stem == name)
+            .min_by_key(|(_, rank, _)| *rank)
+            .map(|(_, _, path)| path)
+    }
+
+    /// Names of all templates in the directory, sorted alphabetically.
+    pub fn names(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut names: Vec<String> = self
+            .templates()?
+            .into_iter()
+            .map(|(stem, _, _)| stem)
+            .collect();
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    /// Every image in the directory with an extension in `TEMPLATE_EXTENSIONS`, in any case.
+    fn templates(&self) -> Result<Vec<TemplateFile>, Box<dyn Error>> {
+        let mut templates = Vec::new();
+        for entry in fs::read_dir(&self.directory)? {
+            let path = entry?.path();
+            let rank = path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .and_then(|extension| {
+                    let extension = extension.to_lowercase();
+                    TEMPLATE_EXTENSIONS
+                        .iter()
+                        .position(|known| *known == extension)
+                });
+            let stem = path.file_stem().and_then(|s| s.to_str()).map(String::from);
+            if let (Some(rank), Some(stem), true) = (rank, stem, path.is_file()) {
+                templates.push((stem, rank, path));
+            }
+        }
+        Ok(templates)
+    }
+
+    /// Loads the template `name` as an `ImageTemplate` searching the whole screen.
+    pub fn resolve(&self, name: &str) -> Result<ImageTemplate, Box<dyn Error>> {
+        self.resolve_with(name, self.strategy, None)
+    }
+
+    /// Like `resolve`, but locates the template with `strategy` instead of the registry's.
+    /// Parameters:
+    /// * `name`: Name of the template.
+    /// * `strategy`: Location strategy for this template only.
+    /// * `label`: Optional. Text an `EdgeParsing` match must contain; see `EdgeParsingStrategy`.
+    pub fn resolve_with(
+        &self,
+        name: &str,
+        strategy: LocationStrategyType,
+        label: Option<String>,
+    ) -> Result<ImageTemplate, Box<dyn Error>> {
+        let path = self.path_for(name).ok_or_else(|| HonkRuntimeError {
+            message: format!(
+                "No image for template<{}> in {}",
+                name,
+                self.directory.display()
+            ),
+        })?;
+        ImageTemplate::new(
+            name.to_string(),
+            &path,
+            None,
+            strategy,
+            self.scale_range,
+            Some(self.scale_cache.clone()),
+            label,
+        )
+        .map_err(|e| -> Box<dyn Error> {
+            Box::new(HonkRuntimeError {
+                message: format!("Cannot load template<{}>: {}", name, e),
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp directory holding `files`.
+    fn directory(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let directory = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        for (file, contents) in files {
+            fs::write(directory.join(file), contents).unwrap();
+        }
+        directory
+    }
+
+    #[test]
+    fn resolves_extensions_in_any_case() {
+        let directory = directory(
+            "goose_registry_case",
+            &[("Button.PNG", b""), ("Button.jpg", b""), ("notes.txt", b"")],
+        );
+        let registry = TemplateRegistry::new(&directory, None, None);
+
+        assert_eq!(registry.names().unwrap(), vec!["Button".to_string()]);
+        assert_eq!(
+            registry.path_for("Button"),
+            Some(directory.join("Button.PNG"))
+        );
+        assert_eq!(registry.path_for("notes"), None);
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn corrupt_template_is_a_runtime_error() {
+        let directory = directory("goose_registry_corrupt", &[("broken.png", b"not a png")]);
+        let registry = TemplateRegistry::new(&directory, None, None);
+
+        let error = registry.resolve("broken").unwrap_err();
+        assert!(error.is::<HonkRuntimeError>());
+        assert!(error.to_string().contains("template<broken>"));
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -1,9 +1,15 @@
+mod cli;
+mod clipboard;
 mod errors;
 mod gui;
+mod honk;
 mod nav;
+mod ocr;
 mod utils;
 mod verb;
 
+use clap::Parser;
+use cli::Cli;
 use eframe;
 use eframe::egui;
 use gui::app::MyApp;
@@ -11,13 +17,29 @@ use gui::app::MyApp;
 use eframe::WindowBuilder;
 use egui_wgpu_backend::{RenderPass, ScreenDescriptor};
 use egui_winit_platform::{Platform, PlatformDescriptor};
+use std::process::ExitCode;
 use std::time::Instant;
 use wgpu::*;
 use winit::dpi::PhysicalSize;
 use winit::event::{Event, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
 
-fn main() -> eframe::Result {
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match cli.command {
+        Some(command) => cli::execute(command, &cli.templates, cli.scales),
+        None => match launch_overlay() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                ExitCode::FAILURE
+            }
+        },
+    }
+}
+
+/// Launches the transparent overlay used to draw zones and capture templates.
+fn launch_overlay() -> eframe::Result {
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_title("Test App")
@@ -1,6 +1,6 @@
 //! Details and behavior of coordinate, which is a core struct in the application.
 
-use crate::errors::ScreenCoordinateError;
+use crate::errors::{OutOfBoundsError, ScreenCoordinateError};
 use autopilot::geometry;
 use autopilot::screen;
 use opencv::core;
@@ -48,6 +48,7 @@ pub struct ScreenCoordinates {
     pub point: geometry::Point,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PointAsRectAnchor {
     TopLeft,
     TopRight,
@@ -206,6 +207,32 @@ impl ScreenRect {
             ),
         }
     }
+
+    /// Like `new`, but errors with `OutOfBoundsError` if the top left corner is off the screen,
+    /// where `new` would compute a nonsensical size. For rects given by users, e.g. in scripts.
+    pub fn on_screen<T>(x: T, y: T, width: f64, height: f64) -> Result<Self, OutOfBoundsError>
+    where
+        T: Into<Coordinate>,
+    {
+        let coord_x: Coordinate = x.into();
+        let coord_y: Coordinate = y.into();
+        check_on_screen(coord_x.val, coord_y.val, screen::size())?;
+        Ok(ScreenRect::new(coord_x, coord_y, width, height))
+    }
+}
+
+/// Errors with `OutOfBoundsError` unless the point lies on a screen of size `screen`, leaving room
+/// for a rect with its top left corner there.
+fn check_on_screen(x: f64, y: f64, screen: geometry::Size) -> Result<(), OutOfBoundsError> {
+    if x < screen.width && y < screen.height {
+        return Ok(());
+    }
+    Err(OutOfBoundsError {
+        message: format!(
+            "Rect corner ({}, {}) is outside the {}x{} screen",
+            x, y, screen.width, screen.height
+        ),
+    })
 }
 
 impl Default for ScreenRect {
@@ -259,3 +286,17 @@ impl Debug for ScreenRect {
         write!(f, "{:?}", self.rect)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rect_corners_must_be_on_screen() {
+        let screen = geometry::Size::new(1280.0, 720.0);
+        assert!(check_on_screen(0.0, 0.0, screen).is_ok());
+        assert!(check_on_screen(1279.0, 719.0, screen).is_ok());
+        assert!(check_on_screen(1280.0, 100.0, screen).is_err());
+        assert!(check_on_screen(100.0, 5000.0, screen).is_err());
+    }
+}
@@ -0,0 +1,463 @@
+//! Table structure detection: finds the rows, columns and header of a table on screen.
+//! Ruling lines are found by opening edges with long, thin kernels, which erases text and keeps
+//! only lines spanning much of the table. Tables drawn without lines are split at the whitespace
+//! between cells instead, using projection profiles of the ink in the table.
+use crate::nav::coordinate::ScreenRect;
+use crate::nav::strategy::{CANNY_HIGH, CANNY_LOW};
+use crate::nav::table::{merge_line_rows, rows_between};
+use crate::utils::{convert_bitmap_to_mat, generate_grid_overlay};
+use autopilot::{bitmap::capture_screen, geometry};
+use opencv::{
+    core::{self, Mat},
+    imgproc,
+    prelude::*,
+};
+use serde::Serialize;
+use std::error::Error;
+use std::ops::Range;
+use std::path::Path;
+
+/// Fraction of a table's width, or height, a line must span to count as a ruling line.
+const MIN_RULE_LENGTH: f64 = 0.5;
+/// Narrowest whitespace between columns. Wider than the space between words, so a cell holding
+/// several words stays one column.
+const MIN_COLUMN_GAP: usize = 12;
+/// Fewest bands of text between horizontal rules for each band to be taken as a row. With fewer,
+/// the rules only frame the table or set its header apart, and rows are split at whitespace.
+const MIN_RULED_ROWS: usize = 3;
+/// Difference in mean brightness, out of 255, that sets a header row apart from the rows below.
+const HEADER_CONTRAST: f64 = 16.0;
+
+/// Structure of a table on screen, in screen coordinates.
+/// * `rows`: Top and height of every row, header rows included, from top to bottom.
+/// * `columns`: Left and width of every column, from left to right.
+/// * `header_rows`: How many of the rows at the top are headers.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Grid {
+    pub rows: Vec<(f64, f64)>,
+    pub columns: Vec<(f64, f64)>,
+    pub header_rows: usize,
+}
+
+impl Grid {
+    /// Bottom edge of the header rows, if there are any.
+    pub fn header_bottom(&self) -> Option<f64> {
+        let (top, height) = self.rows.get(self.header_rows.checked_sub(1)?)?;
+        Some(top + height)
+    }
+
+    /// The header cells, one per column, spanning every header row. Empty without a header.
+    pub fn header_cells(&self) -> Vec<ScreenRect> {
+        let (Some(bottom), Some((top, _))) = (self.header_bottom(), self.rows.first()) else {
+            return Vec::new();
+        };
+        self.columns
+            .iter()
+            .map(|(left, width)| ScreenRect {
+                rect: geometry::Rect::new(
+                    geometry::Point::new(*left, *top),
+                    geometry::Size::new(*width, bottom - top),
+                ),
+            })
+            .collect()
+    }
+}
+
+/// Detects the structure of the table in `region` of the screen.
+/// Parameters:
+/// * `region`: Rect holding the table and little else.
+/// * `overlay`: Optional. Path of a PNG file to save the region to, with the detected grid drawn
+///   over it, to see what was detected.
+pub fn detect_grid(region: ScreenRect, overlay: Option<&Path>) -> Result<Grid, Box<dyn Error>> {
+    let bounds: core::Rect = region.into();
+    let screenshot = convert_bitmap_to_mat(&capture_screen()?);
+    let image = Mat::roi(&screenshot, bounds)?.try_clone()?;
+    let grid = detect_image(&image, (bounds.x as f64, bounds.y as f64))?;
+    if let Some(path) = overlay {
+        generate_grid_overlay(
+            &image,
+            &grid,
+            core::Point::new(bounds.x, bounds.y),
+            &path.to_string_lossy(),
+        )?;
+    }
+    Ok(grid)
+}
+
+/// Detects the structure of the table in a BGR image. `origin` is added to every row and
+/// column, so a table in a cropped screenshot keeps its screen coordinates.
+pub fn detect_image(image: &Mat, origin: (f64, f64)) -> Result<Grid, Box<dyn Error>> {
+    let lines = ruling_lines(image)?;
+    let ink = ink_map(image, &lines.mask)?;
+    let grid = detect_structure(&ink, &lines.horizontal, &lines.vertical);
+    Ok(Grid {
+        rows: grid
+            .rows
+            .into_iter()
+            .map(|(top, height)| (origin.1 + top, height))
+            .collect(),
+        columns: grid
+            .columns
+            .into_iter()
+            .map(|(left, width)| (origin.0 + left, width))
+            .collect(),
+        header_rows: grid.header_rows,
+    })
+}
+
+/// Ruling lines of a table image.
+/// * `horizontal`: Positions of horizontal lines, in pixels from the top of the image.
+/// * `vertical`: Positions of vertical lines, in pixels from the left of the image.
+/// * `mask`: The pixels on or next to a line.
+pub(crate) struct RulingLines {
+    pub horizontal: Vec<f64>,
+    pub vertical: Vec<f64>,
+    pub mask: Mat,
+}
+
+/// Finds the ruling lines of a BGR image. A ruling line is an edge spanning at least
+/// `MIN_RULE_LENGTH` of the image; opening the edges with a kernel that long erases text and
+/// short borders, leaving only the lines.
+pub(crate) fn ruling_lines(image: &Mat) -> Result<RulingLines, Box<dyn Error>> {
+    let mut gray = Mat::default();
+    imgproc::cvt_color(image, &mut gray, imgproc::COLOR_BGR2GRAY, 0)?;
+    let mut edges = Mat::default();
+    imgproc::canny(&gray, &mut edges, CANNY_LOW, CANNY_HIGH, 3, false)?;
+
+    let open = |size: core::Size| -> Result<Mat, Box<dyn Error>> {
+        let kernel =
+            imgproc::get_structuring_element(imgproc::MORPH_RECT, size, core::Point::new(-1, -1))?;
+        let mut lines = Mat::default();
+        imgproc::morphology_ex(
+            &edges,
+            &mut lines,
+            imgproc::MORPH_OPEN,
+            &kernel,
+            core::Point::new(-1, -1),
+            1,
+            core::BORDER_CONSTANT,
+            imgproc::morphology_default_border_value()?,
+        )?;
+        Ok(lines)
+    };
+    let rule_length = |length: i32| ((length as f64 * MIN_RULE_LENGTH) as i32).max(1);
+    let horizontal = open(core::Size::new(rule_length(image.cols()), 1))?;
+    let vertical = open(core::Size::new(1, rule_length(image.rows())))?;
+    let mut vertical_transposed = Mat::default();
+    core::transpose(&vertical, &mut vertical_transposed)?;
+
+    // Canny marks both sides of a line; widen the mask to cover the line between them.
+    let mut lines = Mat::default();
+    core::bitwise_or(&horizontal, &vertical, &mut lines, &core::no_array())?;
+    let mut mask = Mat::default();
+    imgproc::dilate(
+        &lines,
+        &mut mask,
+        &imgproc::get_structuring_element(
+            imgproc::MORPH_RECT,
+            core::Size::new(3, 3),
+            core::Point::new(-1, -1),
+        )?,
+        core::Point::new(-1, -1),
+        1,
+        core::BORDER_CONSTANT,
+        imgproc::morphology_default_border_value()?,
+    )?;
+
+    Ok(RulingLines {
+        horizontal: merge_line_rows(&lit_rows(&horizontal)?),
+        vertical: merge_line_rows(&lit_rows(&vertical_transposed)?),
+        mask,
+    })
+}
+
+/// Rows of a single channel image holding at least one set pixel.
+fn lit_rows(image: &Mat) -> Result<Vec<i32>, Box<dyn Error>> {
+    let mut rows = Vec::new();
+    for y in 0..image.rows() {
+        for x in 0..image.cols() {
+            if *image.at_2d::<u8>(y, x)? > 0 {
+                rows.push(y);
+                break;
+            }
+        }
+    }
+    Ok(rows)
+}
+
+/// Ink in a table image: pixels of text or graphics that stand out from their surroundings, with
+/// ruling lines left out, along with the mean brightness of every row of pixels.
+struct InkMap {
+    width: usize,
+    height: usize,
+    ink: Vec<bool>,
+    brightness: Vec<f64>,
+}
+
+impl InkMap {
+    /// Number of ink pixels in the given columns and rows.
+    fn count(&self, xs: Range<usize>, ys: Range<usize>) -> usize {
+        ys.map(|y| xs.clone().filter(|x| self.ink[y * self.width + x]).count())
+            .sum()
+    }
+
+    /// Ink pixels in each row of pixels in `ys`, across the whole width.
+    fn row_profile(&self, ys: Range<usize>) -> Vec<usize> {
+        ys.map(|y| self.count(0..self.width, y..y + 1)).collect()
+    }
+
+    /// Ink pixels in each column of pixels, across the whole height.
+    fn column_profile(&self) -> Vec<usize> {
+        (0..self.width)
+            .map(|x| self.count(x..x + 1, 0..self.height))
+            .collect()
+    }
+
+    /// Mean brightness of the rows of pixels in `ys`.
+    fn mean_brightness(&self, ys: Range<usize>) -> f64 {
+        let rows = &self.brightness[ys];
+        rows.iter().sum::<f64>() / rows.len().max(1) as f64
+    }
+}
+
+/// Builds the ink map of a BGR image. Ink is found with an adaptive threshold, so it is found on
+/// light and shaded backgrounds alike; pixels under `rules` are left out.
+fn ink_map(image: &Mat, rules: &Mat) -> Result<InkMap, Box<dyn Error>> {
+    let mut gray = Mat::default();
+    imgproc::cvt_color(image, &mut gray, imgproc::COLOR_BGR2GRAY, 0)?;
+    let mut binary = Mat::default();
+    imgproc::adaptive_threshold(
+        &gray,
+        &mut binary,
+        255.0,
+        imgproc::ADAPTIVE_THRESH_MEAN_C,
+        imgproc::THRESH_BINARY_INV,
+        15,
+        10.0,
+    )?;
+
+    let (width, height) = (gray.cols() as usize, gray.rows() as usize);
+    let mut ink = Vec::with_capacity(width * height);
+    let mut brightness = Vec::with_capacity(height);
+    for y in 0..height as i32 {
+        let mut sum = 0.0;
+        for x in 0..width as i32 {
+            sum += *gray.at_2d::<u8>(y, x)? as f64;
+            ink.push(*binary.at_2d::<u8>(y, x)? > 0 && *rules.at_2d::<u8>(y, x)? == 0);
+        }
+        brightness.push(sum / width.max(1) as f64);
+    }
+    Ok(InkMap {
+        width,
+        height,
+        ink,
+        brightness,
+    })
+}
+
+/// Pixel range covering the band starting at `start` and `size` long.
+fn pixels((start, size): (f64, f64)) -> Range<usize> {
+    start.round() as usize..(start + size).round() as usize
+}
+
+/// Runs of consecutive non-zero entries of a projection profile, as `(start, end)` with `end`
+/// exclusive, e.g. the lines of text in a row profile.
+fn ink_runs(profile: &[usize]) -> Vec<(usize, usize)> {
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for (i, count) in profile.iter().enumerate() {
+        if *count == 0 {
+            continue;
+        }
+        match runs.last_mut() {
+            Some((_, end)) if *end == i => *end = i + 1,
+            _ => runs.push((i, i + 1)),
+        }
+    }
+    runs
+}
+
+/// Splits a profile into bands at every gap of at least `min_gap` blank entries between ink,
+/// cutting each gap in the middle. Blank margins stay with the first and last bands.
+fn split_at_gaps(profile: &[usize], min_gap: usize) -> Vec<(f64, f64)> {
+    let mut cuts = vec![0.0];
+    for pair in ink_runs(profile).windows(2) {
+        let (end, start) = (pair[0].1, pair[1].0);
+        if start - end >= min_gap {
+            cuts.push((end + start) as f64 / 2.0);
+        }
+    }
+    cuts.push(profile.len() as f64);
+    cuts.windows(2)
+        .map(|pair| (pair[0], pair[1] - pair[0]))
+        .collect()
+}
+
+/// Splits the band of pixel rows `ys` into table rows at the whitespace between lines of text.
+/// A line without ink in `first_column` continues the row above, e.g. a comment wrapped inside a
+/// later column; without a first column, every line is a row. A band without text has no rows.
+fn split_rows(
+    ink: &InkMap,
+    ys: Range<usize>,
+    first_column: Option<Range<usize>>,
+) -> Vec<(f64, f64)> {
+    let lines = ink_runs(&ink.row_profile(ys.clone()));
+    if lines.is_empty() {
+        return Vec::new();
+    }
+    let mut cuts = vec![ys.start as f64];
+    for pair in lines.windows(2) {
+        let (end, (start, next_end)) = (pair[0].1, pair[1]);
+        let starts_row = first_column
+            .as_ref()
+            .is_none_or(|xs| ink.count(xs.clone(), ys.start + start..ys.start + next_end) > 0);
+        if starts_row {
+            cuts.push(ys.start as f64 + (end + start) as f64 / 2.0);
+        }
+    }
+    cuts.push(ys.end as f64);
+    cuts.windows(2)
+        .map(|pair| (pair[0], pair[1] - pair[0]))
+        .collect()
+}
+
+/// Infers the grid of a table from its ink and ruling lines, in pixels of the image.
+/// Columns lie between vertical rules when those separate text, and are otherwise split at wide
+/// whitespace. Rows lie between horizontal rules when at least `MIN_RULED_ROWS` bands of text
+/// are ruled off, and are otherwise split at the whitespace between lines of text. The first row
+/// is a header if a rule sets it apart from otherwise unruled rows, or if its background stands
+/// out from the rows below.
+fn detect_structure(ink: &InkMap, horizontal: &[f64], vertical: &[f64]) -> Grid {
+    let (width, height) = (ink.width, ink.height);
+
+    // Columns between vertical rules are split the same way as rows between horizontal ones.
+    let ruled_columns: Vec<(f64, f64)> = rows_between(vertical, width as f64)
+        .into_iter()
+        .filter(|band| ink.count(pixels(*band), 0..height) > 0)
+        .collect();
+    let columns = if ruled_columns.len() >= 2 {
+        ruled_columns
+    } else {
+        split_at_gaps(&ink.column_profile(), MIN_COLUMN_GAP)
+    };
+    let first_column = Some(&columns)
+        .filter(|columns| columns.len() >= 2)
+        .map(|columns| pixels(columns[0]));
+
+    let bands: Vec<(f64, f64)> = rows_between(horizontal, height as f64)
+        .into_iter()
+        .filter(|band| ink.count(0..width, pixels(*band)) > 0)
+        .collect();
+    let ruled = bands.len() >= MIN_RULED_ROWS;
+    let mut rows = Vec::new();
+    let mut first_band_rows = 0;
+    for (i, band) in bands.iter().enumerate() {
+        let band_rows = if ruled {
+            vec![*band]
+        } else {
+            split_rows(ink, pixels(*band), first_column.clone())
+        };
+        if i == 0 {
+            first_band_rows = band_rows.len();
+        }
+        rows.extend(band_rows);
+    }
+
+    let ruled_apart = !ruled && bands.len() == 2 && first_band_rows == 1;
+    let stands_out = rows.len() >= 2 && {
+        let mut body: Vec<f64> = rows[1..]
+            .iter()
+            .map(|row| ink.mean_brightness(pixels(*row)))
+            .collect();
+        body.sort_by(|a, b| a.total_cmp(b));
+        (ink.mean_brightness(pixels(rows[0])) - body[body.len() / 2]).abs() >= HEADER_CONTRAST
+    };
+    let header_rows = usize::from(rows.len() >= 2 && (ruled_apart || stands_out));
+
+    Grid {
+        rows,
+        columns,
+        header_rows,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A blank 200x100 table image on a white background.
+    fn blank() -> InkMap {
+        InkMap {
+            width: 200,
+            height: 100,
+            ink: vec![false; 200 * 100],
+            brightness: vec![250.0; 100],
+        }
+    }
+
+    /// Marks a block of ink, standing in for a word.
+    fn word(ink: &mut InkMap, x: usize, y: usize, width: usize) {
+        for row in y..y + 8 {
+            for column in x..x + width {
+                ink.ink[row * ink.width + column] = true;
+            }
+        }
+    }
+
+    #[test]
+    fn splits_unruled_tables_at_whitespace() {
+        let mut ink = blank();
+        // Header, then three rows; the second row's comment wraps onto a line of its own.
+        for x in [5, 70, 150] {
+            word(&mut ink, x, 2, 35);
+        }
+        ink.brightness[..15].fill(200.0);
+        word(&mut ink, 5, 20, 35);
+        word(&mut ink, 70, 20, 50);
+        word(&mut ink, 5, 38, 35);
+        word(&mut ink, 150, 38, 40);
+        word(&mut ink, 150, 50, 40);
+        word(&mut ink, 5, 70, 35);
+
+        let grid = detect_structure(&ink, &[], &[]);
+        assert_eq!(grid.columns, vec![(0.0, 55.0), (55.0, 80.0), (135.0, 65.0)]);
+        assert_eq!(
+            grid.rows,
+            vec![(0.0, 15.0), (15.0, 18.0), (33.0, 31.0), (64.0, 36.0)]
+        );
+        assert_eq!(grid.header_rows, 1);
+    }
+
+    #[test]
+    fn splits_ruled_tables_at_rules() {
+        let mut ink = blank();
+        for y in [2, 20, 38, 56] {
+            word(&mut ink, 5, y, 35);
+            word(&mut ink, 120, y, 35);
+        }
+        // A wrapped first column stays in its ruled row.
+        word(&mut ink, 5, 66, 35);
+
+        let ruled = detect_structure(&ink, &[14.0, 32.0, 50.0, 80.0], &[1.0, 100.0, 198.0]);
+        assert_eq!(ruled.columns, vec![(1.0, 99.0), (100.0, 98.0)]);
+        assert_eq!(
+            ruled.rows,
+            vec![(0.0, 14.0), (14.0, 18.0), (32.0, 18.0), (50.0, 30.0)]
+        );
+        assert_eq!(ruled.header_rows, 0);
+
+        // A single rule under the first row sets the header apart. Without rules between rows,
+        // the wrapped first column starts a row of its own.
+        let header = detect_structure(&ink, &[14.0], &[]);
+        assert_eq!(header.header_rows, 1);
+        assert_eq!(header.rows.len(), 5);
+        assert_eq!(header.header_bottom(), Some(14.0));
+    }
+
+    #[test]
+    fn finds_runs_and_gaps_in_profiles() {
+        let profile = [0, 3, 4, 0, 0, 0, 2, 0, 1, 1, 0];
+        assert_eq!(ink_runs(&profile), vec![(1, 3), (6, 7), (8, 10)]);
+        assert_eq!(split_at_gaps(&profile, 3), vec![(0.0, 4.5), (4.5, 6.5)]);
+        assert_eq!(split_at_gaps(&[0, 0], 1), vec![(0.0, 2.0)]);
+    }
+}
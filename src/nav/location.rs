@@ -1,18 +1,23 @@
 //! Traits and types for GUI navigation.
+use crate::errors::{TemplateLoadError, TemplateNotFoundError};
 use crate::nav::coordinate::Coordinate;
-use crate::nav::coordinate::{ScreenCoordinates, ScreenRect};
+use crate::nav::coordinate::{PointAsRectAnchor, ScreenCoordinates, ScreenRect};
+use crate::nav::parse::ElementQuery;
 use crate::nav::strategy::{
-    BitmapNeedleStrategy, EdgeParsingStrategy, LocationStrategy, LocationStrategyType,
-    TemplateMatchingStrategy,
+    BitmapNeedleStrategy, EdgeParsingStrategy, LocationStrategy, LocationStrategyType, MatchResult,
+    ScaleCache, ScaleRange, TemplateMatchingStrategy, TextLocationStrategy,
 };
-use autopilot::screen;
+use crate::nav::table::Table;
+use autopilot::{geometry, screen};
 use image::GenericImageView;
 use image::{io::Reader, DynamicImage};
+use std::error::Error;
 use std::fmt::Debug;
 use std::path::Path;
+use std::rc::Rc;
 
 pub trait GetLocation {
-    fn get_location(&self) -> ScreenCoordinates;
+    fn get_location(&self) -> Result<ScreenCoordinates, Box<dyn Error>>;
 }
 
 /// A simple struct to hold information about an image template, used as a basis for navigating the
@@ -22,6 +27,11 @@ pub trait GetLocation {
 /// Note: The search region is defined to provide specificity for multiple occurrence of the same
 /// GUI element on the screen. However, the algorithm performs best for matching when given the
 /// entire screen as the search region.
+/// `scale_range` and `scale_cache` only apply to `LocationStrategyType::TemplateMatching`; see
+/// `TemplateMatchingStrategy`. `label` only applies to `LocationStrategyType::EdgeParsing`; see
+/// `EdgeParsingStrategy`.
+/// `new` errors with `TemplateLoadError` if the image cannot be read or decoded, or its path is not
+/// valid unicode.
 pub struct ImageTemplate {
     pub name: String,
     pub image: DynamicImage,
@@ -35,7 +45,22 @@ impl ImageTemplate {
         path: &Path,
         search_region: Option<(Coordinate, Coordinate, Coordinate, Coordinate)>,
         strategy_type: LocationStrategyType,
-    ) -> ImageTemplate {
+        scale_range: Option<ScaleRange>,
+        scale_cache: Option<ScaleCache>,
+        label: Option<String>,
+    ) -> Result<ImageTemplate, TemplateLoadError> {
+        let load_error = |reason: String| TemplateLoadError {
+            message: format!("{}: {}", path.display(), reason),
+        };
+        let template_path = path
+            .to_str()
+            .ok_or_else(|| load_error("path is not valid unicode".to_string()))?
+            .to_string();
+        let image = Reader::open(path)
+            .map_err(|e| load_error(e.to_string()))?
+            .decode()
+            .map_err(|e| load_error(e.to_string()))?;
+
         let ssize = screen::size(); // Gets screen size in SCALED coordinates
         let output_sr = match search_region {
             Some(region) => (
@@ -46,59 +71,85 @@ impl ImageTemplate {
             ),
             None => (0, 0, ssize.width as i32, ssize.height as i32),
         };
-        let image = Reader::open(path)
-            .expect("Failed to read image file")
-            .decode()
-            .expect("Failed to read image file");
-
         let location_strategy: Box<dyn LocationStrategy> = match strategy_type {
-            LocationStrategyType::TemplateMatching => Box::new(TemplateMatchingStrategy {
-                template_path: String::from(
-                    path.to_str()
-                        .expect(&format!("Path {:?} is not valid unicode", path)),
-                ),
-            }),
-            LocationStrategyType::BitmapNeedle => Box::new(BitmapNeedleStrategy {
-                template_path: String::from(
-                    path.to_str()
-                        .expect(&format!("Path {:?} is not valid unicode", path)),
-                ),
-            }),
-            LocationStrategyType::EdgeParsing => Box::new(EdgeParsingStrategy {
-                template_path: String::from(
-                    path.to_str()
-                        .expect(&format!("Path {:?} is not valid unicode", path)),
-                ),
-            }),
+            LocationStrategyType::TemplateMatching => Box::new(TemplateMatchingStrategy::new(
+                template_path,
+                scale_range,
+                scale_cache,
+            )),
+            LocationStrategyType::BitmapNeedle => Box::new(BitmapNeedleStrategy { template_path }),
+            LocationStrategyType::EdgeParsing => {
+                Box::new(EdgeParsingStrategy::new(template_path, label))
+            }
         };
-        ImageTemplate {
+        Ok(ImageTemplate {
             name,
             image,
             search_region: output_sr,
             location_strategy,
+        })
+    }
+
+    /// Looks for the template on screen without treating a miss as an error.
+    /// Parameters:
+    /// * `search_region`: Optional. Where to search. Defaults to the template's own search region.
+    /// * `min_confidence`: Optional. Score the match must reach; see `LocationStrategy::find_match`.
+    ///
+    /// Returns:
+    /// * The *_CENTER_* of the match, or `None` if the template is not found. Any other error,
+    ///   e.g. failing to capture the screen, is returned as is.
+    pub fn find(
+        &self,
+        search_region: Option<ScreenRect>,
+        min_confidence: Option<f64>,
+    ) -> Result<Option<ScreenCoordinates>, Box<dyn Error>> {
+        let search_region = search_region.unwrap_or_else(|| self.default_search_region());
+        match self
+            .location_strategy
+            .find_match(Some(search_region), min_confidence)
+        {
+            Ok(found) => Ok(Some(found.center()?)),
+            Err(e) if e.is::<TemplateNotFoundError>() => Ok(None),
+            Err(e) => Err(e),
         }
     }
+
+    /// Finds every occurrence of the template; see `LocationStrategy::find_all`.
+    /// Takes the same parameters as `find`.
+    /// Returns:
+    /// * The *_CENTER_* of each match in reading order. Empty if there are none.
+    pub fn find_all(
+        &self,
+        search_region: Option<ScreenRect>,
+        min_confidence: Option<f64>,
+    ) -> Result<Vec<ScreenCoordinates>, Box<dyn Error>> {
+        let search_region = search_region.unwrap_or_else(|| self.default_search_region());
+        self.location_strategy
+            .find_all(Some(search_region), min_confidence)?
+            .into_iter()
+            .map(|found| Ok(found.center()?))
+            .collect()
+    }
+
+    fn default_search_region(&self) -> ScreenRect {
+        let (x, y, width, height) = self.search_region;
+        ScreenRect::new(x as f64, y as f64, width as f64, height as f64)
+    }
 }
 
 impl GetLocation for ImageTemplate {
     /// Gets target location based on image template and matching strategy
     /// Returns:
     /// * `ScreenCoordinates` - the *_CENTER_* of the image template on screen
-    fn get_location(&self) -> ScreenCoordinates {
+    fn get_location(&self) -> Result<ScreenCoordinates, Box<dyn Error>> {
         let (x, y, width, height) = self.search_region;
         let (x, y, width, height) = (x as f64, y as f64, width as f64, height as f64);
-        let screen_coords = self
+        let found = self
             .location_strategy
-            .get_location(Some(ScreenRect::new(x, y, width, height)))
-            .unwrap();
+            .find_match(Some(ScreenRect::new(x, y, width, height)), None)?;
 
         // Shift the coordinates to the center of the image
-        return screen_coords
-            .shift(
-                self.image.width() as f64 / 2.0,
-                self.image.height() as f64 / 2.0,
-            )
-            .expect("Image template dimensions out of screen bounds");
+        Ok(found.center()?)
     }
 }
 
@@ -117,22 +168,263 @@ pub struct AbsoluteLocation {
     pub y: Coordinate,
 }
 
+/// Where a verb should act.
+/// * `TemplateTarget`: The best match of a template.
+/// * `IndexedTemplateTarget`: The match at a 0-based index, in reading order, of a template that
+///   appears several times, e.g. the third "open result" button in a list.
+/// * `AbsoluteTarget`: Fixed screen coordinates.
+/// * `TextTarget`: Text read on screen with OCR.
+/// * `CellTarget`: The centre of a table cell, given by its 0-based data row and column name.
+/// * `ElementTarget`: The centre of an element found by parsing the screen; see `ElementQuery`.
+/// * `RelativeTarget`: A point offset from another target, for controls without a distinctive
+///   look next to a distinctive label. Each `(side, distance)` places the point `distance` pixels
+///   beyond that edge of the anchor's match; the point stays centred on the anchor along an axis
+///   without an offset.
 pub enum TargetFactory {
     TemplateTarget(ImageTemplate),
+    IndexedTemplateTarget(ImageTemplate, usize),
     AbsoluteTarget(AbsoluteLocation),
+    TextTarget(TextLocationStrategy),
+    RelativeTarget(Box<TargetFactory>, Vec<(Side, f64)>),
+    CellTarget(Rc<Table>, usize, String),
+    ElementTarget(ElementQuery),
 }
 
 impl GetLocation for AbsoluteLocation {
-    fn get_location(&self) -> ScreenCoordinates {
-        ScreenCoordinates::new(self.x.val, self.y.val).unwrap()
+    fn get_location(&self) -> Result<ScreenCoordinates, Box<dyn Error>> {
+        Ok(ScreenCoordinates::new(self.x.val, self.y.val)?)
+    }
+}
+
+impl TargetFactory {
+    /// The area of the screen the target covers: its match for templates and text, or an empty
+    /// rect at the point for coordinates.
+    pub fn get_rect(&self) -> Result<ScreenRect, Box<dyn Error>> {
+        match self {
+            TargetFactory::TemplateTarget(template) => Ok(template
+                .location_strategy
+                .find_match(Some(template.default_search_region()), None)?
+                .rect),
+            TargetFactory::IndexedTemplateTarget(template, index) => {
+                Ok(nth_match(template, *index)?.rect)
+            }
+            TargetFactory::AbsoluteTarget(_) | TargetFactory::RelativeTarget(..) => {
+                let point = self.get_location()?.point;
+                Ok(ScreenRect {
+                    rect: geometry::Rect::new(point, geometry::Size::new(0.0, 0.0)),
+                })
+            }
+            TargetFactory::TextTarget(strategy) => Ok(strategy.find_match(None, None)?.rect),
+            TargetFactory::CellTarget(table, row, column) => table.cell(*row, column),
+            TargetFactory::ElementTarget(query) => Ok(query.find()?.into()),
+        }
+    }
+}
+
+/// The point `offsets` away from the edges of `anchor`; see `TargetFactory::RelativeTarget`.
+fn offset_from(
+    anchor: ScreenRect,
+    offsets: &[(Side, f64)],
+) -> Result<ScreenCoordinates, Box<dyn Error>> {
+    let size = anchor.rect.size;
+    let (mut dx, mut dy) = (size.width / 2.0, size.height / 2.0);
+    for (side, distance) in offsets {
+        match side {
+            Side::Left => dx = -distance,
+            Side::Right => dx = size.width + distance,
+            Side::Above => dy = -distance,
+            Side::Below => dy = size.height + distance,
+        }
     }
+    // Shifted from the match's corner like `MatchResult::center`, so both agree on scaling.
+    let corner = ScreenCoordinates {
+        point: anchor.rect.origin,
+    };
+    Ok(corner.shift(dx, dy)?)
+}
+
+/// The match at a 0-based `index`, in reading order, of a template that appears several times.
+fn nth_match(template: &ImageTemplate, index: usize) -> Result<MatchResult, Box<dyn Error>> {
+    let matches = template
+        .location_strategy
+        .find_all(Some(template.default_search_region()), None)?;
+    let found = matches.len();
+    matches
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| -> Box<dyn Error> {
+            Box::new(TemplateNotFoundError {
+                message: format!(
+                    "Match {} of template {} requested, but only {} found",
+                    index + 1,
+                    template.name,
+                    found
+                ),
+            })
+        })
 }
 
 impl<'a> GetLocation for TargetFactory {
-    fn get_location(&self) -> ScreenCoordinates {
+    fn get_location(&self) -> Result<ScreenCoordinates, Box<dyn Error>> {
         match self {
             TargetFactory::TemplateTarget(template) => template.get_location(),
+            TargetFactory::IndexedTemplateTarget(template, index) => {
+                Ok(nth_match(template, *index)?.center()?)
+            }
             TargetFactory::AbsoluteTarget(absolute_location) => absolute_location.get_location(),
+            TargetFactory::TextTarget(strategy) => Ok(strategy.find_match(None, None)?.center()?),
+            TargetFactory::RelativeTarget(anchor, offsets) => {
+                offset_from(anchor.get_rect()?, offsets)
+            }
+            TargetFactory::CellTarget(..) | TargetFactory::ElementTarget(_) => {
+                offset_from(self.get_rect()?, &[])
+            }
+        }
+    }
+}
+
+/// Width of a `RelativeRegion` when none is given, enough for a short value.
+const DEFAULT_REGION_WIDTH: f64 = 200.0;
+/// Height of a `RelativeRegion` placed at a point when none is given, enough for a line of text.
+const DEFAULT_REGION_HEIGHT: f64 = 30.0;
+
+/// Side of an anchor that a relative target or region lies on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+    Above,
+    Below,
+}
+
+/// How a `RelativeRegion` is placed against its anchor.
+/// * `Beside`: Next to the anchor's match on the given side. Regions left or right of the anchor
+///   are centred on it vertically.
+/// * `At`: With the given corner, or its centre, at the anchor's location, e.g. at the point a
+///   `RelativeTarget` resolves to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    Beside(Side),
+    At(PointAsRectAnchor),
+}
+
+/// A region placed relative to an element on screen, e.g. the value box right of a lab label. The
+/// anchor is located each time the region is resolved, so the region follows it when the layout
+/// moves.
+/// Parameters:
+/// * `anchor`: The element the region is placed against.
+/// * `placement`: Where the region lies relative to the anchor.
+/// * `width`: Optional. Width of the region. Defaults to the anchor's width above or below it, and
+///   to 200 otherwise.
+/// * `height`: Optional. Height of the region. Defaults to 30 at a point, and to the anchor's
+///   height otherwise.
+pub struct RelativeRegion {
+    anchor: TargetFactory,
+    placement: Placement,
+    width: Option<f64>,
+    height: Option<f64>,
+}
+
+impl RelativeRegion {
+    pub fn new(
+        anchor: TargetFactory,
+        placement: Placement,
+        width: Option<f64>,
+        height: Option<f64>,
+    ) -> Self {
+        RelativeRegion {
+            anchor,
+            placement,
+            width,
+            height,
         }
     }
+
+    /// Locates the anchor and returns the region, truncated to the screen.
+    pub fn resolve(&self) -> Result<ScreenRect, Box<dyn Error>> {
+        let side = match self.placement {
+            Placement::Beside(side) => side,
+            Placement::At(corner) => {
+                return Ok(self.anchor.get_location()?.generate_rect(
+                    self.width.unwrap_or(DEFAULT_REGION_WIDTH) as u64,
+                    self.height.unwrap_or(DEFAULT_REGION_HEIGHT) as u64,
+                    corner,
+                ))
+            }
+        };
+        let anchor = self.anchor.get_rect()?.rect;
+        let (origin, size) = (anchor.origin, anchor.size);
+        let width = self.width.unwrap_or(match side {
+            Side::Left | Side::Right => DEFAULT_REGION_WIDTH,
+            Side::Above | Side::Below => size.width,
+        });
+        let height = self.height.unwrap_or(size.height);
+        let centred_y = origin.y + (size.height - height) / 2.0;
+        let (x, y) = match side {
+            Side::Left => (origin.x - width, centred_y),
+            Side::Right => (origin.x + size.width, centred_y),
+            Side::Above => (origin.x, origin.y - height),
+            Side::Below => (origin.x, origin.y + size.height),
+        };
+
+        Ok(ScreenRect {
+            rect: clip_to_screen(x, y, width, height, screen::size()),
+        })
+    }
+}
+
+/// The part of the `width` x `height` rect at `(x, y)` that lies on a screen of size `screen`. A
+/// region left of or above an anchor near the screen's edge keeps only its on-screen part, so it
+/// still ends at the anchor.
+fn clip_to_screen(
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    screen: geometry::Size,
+) -> geometry::Rect {
+    let (left, top) = (x.max(0.0), y.max(0.0));
+    let right = (x + width).min(screen.width);
+    let bottom = (y + height).min(screen.height);
+    geometry::Rect::new(
+        geometry::Point::new(left, top),
+        geometry::Size::new((right - left).max(0.0), (bottom - top).max(0.0)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clips_regions_to_screen() {
+        let screen = geometry::Size::new(1280.0, 720.0);
+        let parts = |rect: geometry::Rect| {
+            (
+                rect.origin.x,
+                rect.origin.y,
+                rect.size.width,
+                rect.size.height,
+            )
+        };
+
+        // Left of an anchor at x = 50: only the 50 pixels up to the anchor are on screen.
+        assert_eq!(
+            parts(clip_to_screen(-150.0, 100.0, 200.0, 30.0, screen)),
+            (0.0, 100.0, 50.0, 30.0)
+        );
+        // Above an anchor at y = 10.
+        assert_eq!(
+            parts(clip_to_screen(40.0, -20.0, 120.0, 30.0, screen)),
+            (40.0, 0.0, 120.0, 10.0)
+        );
+        assert_eq!(
+            parts(clip_to_screen(1200.0, 700.0, 200.0, 30.0, screen)),
+            (1200.0, 700.0, 80.0, 20.0)
+        );
+        assert_eq!(
+            parts(clip_to_screen(-300.0, 100.0, 200.0, 30.0, screen)),
+            (0.0, 100.0, 0.0, 30.0)
+        );
+    }
 }
@@ -1,3 +1,6 @@
 pub mod coordinate;
+pub mod grid;
 pub mod location;
+pub mod parse;
 pub mod strategy;
+pub mod table;
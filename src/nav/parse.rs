@@ -0,0 +1,450 @@
+//! Screen parsing: turns a screenshot into a hierarchy of GUI elements.
+//! Elements are the outlines found by edge parsing (see `EdgeParsingStrategy`). An element whose
+//! bounding box lies inside another's is its child, so a form panel holds its text fields and a
+//! button holds the text of its label. Text is read with OCR in one pass over the parsed region
+//! and each word given to the deepest element containing its centre.
+use crate::errors::TemplateNotFoundError;
+use crate::nav::coordinate::ScreenRect;
+use crate::nav::strategy::{edge_outlines, reading_order_by, Outline};
+use crate::ocr::{OcrEngine, TesseractOcr, Word};
+use crate::utils::convert_bitmap_to_mat;
+use autopilot::bitmap::capture_screen;
+use opencv::{
+    core::{self, Mat},
+    prelude::*,
+};
+use serde::Serialize;
+use std::error::Error;
+
+/// Outlines with at least this much of their border drawn are bordered controls or panels.
+const BORDERED: f64 = 0.6;
+/// Largest side in pixels of a checkbox or radio button.
+const CHECKBOX_MAX_SIZE: i32 = 24;
+/// Tallest a single-line control, such as a button or text field, is expected to be.
+const CONTROL_MAX_HEIGHT: i32 = 48;
+
+/// What an element appears to be, guessed from its shape and children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ElementKind {
+    Panel,
+    Button,
+    TextField,
+    Checkbox,
+    Text,
+    Unknown,
+}
+
+/// Bounding box of an element in screen coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct BoundingBox {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl BoundingBox {
+    fn contains(&self, other: &BoundingBox) -> bool {
+        other.x >= self.x
+            && other.y >= self.y
+            && other.x + other.width <= self.x + self.width
+            && other.y + other.height <= self.y + self.height
+    }
+
+    fn area(&self) -> i32 {
+        self.width * self.height
+    }
+
+    fn contains_point(&self, x: f64, y: f64) -> bool {
+        x >= self.x as f64
+            && y >= self.y as f64
+            && x <= (self.x + self.width) as f64
+            && y <= (self.y + self.height) as f64
+    }
+}
+
+impl From<BoundingBox> for ScreenRect {
+    fn from(bbox: BoundingBox) -> Self {
+        ScreenRect::new(bbox.x, bbox.y, bbox.width as f64, bbox.height as f64)
+    }
+}
+
+/// One element of the parsed screen.
+/// * `bbox`: Where the element is.
+/// * `kind`: Best guess at what the element is.
+/// * `text`: Text read from the element with OCR, if any.
+/// * `children`: Elements inside this one, in reading order.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Element {
+    pub bbox: BoundingBox,
+    pub kind: ElementKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    pub children: Vec<Element>,
+    #[serde(skip)]
+    border_coverage: f64,
+}
+
+impl Element {
+    /// This element's descendants, depth first and in reading order, not including itself.
+    pub fn descendants(&self) -> Vec<&Element> {
+        let mut found = Vec::new();
+        for child in &self.children {
+            found.push(child);
+            found.extend(child.descendants());
+        }
+        found
+    }
+
+    /// Descendants of the given kind in reading order, e.g. the text fields of a form panel.
+    pub fn descendants_of_kind(&self, kind: ElementKind) -> Vec<&Element> {
+        self.descendants()
+            .into_iter()
+            .filter(|element| element.kind == kind)
+            .collect()
+    }
+
+    /// Whether the element's own text, or that of its first text child, contains `title`.
+    pub fn is_titled(&self, title: &str) -> bool {
+        let first_text = self
+            .children
+            .iter()
+            .find(|child| child.kind == ElementKind::Text);
+        [Some(self), first_text]
+            .into_iter()
+            .flatten()
+            .any(|element| element.text.as_deref().is_some_and(|t| t.contains(title)))
+    }
+}
+
+/// Parses the screen into a hierarchy of elements and reads their text.
+/// Parameters:
+/// * `search_region`: Optional. Part of the screen to parse. Default is the entire screen.
+/// * `engine`: Optional. OCR engine that reads the text. Default is `TesseractOcr`.
+///
+/// Returns:
+/// * The top level elements in reading order, in screen coordinates.
+pub fn parse_screen(
+    search_region: Option<ScreenRect>,
+    engine: Option<Box<dyn OcrEngine>>,
+) -> Result<Vec<Element>, Box<dyn Error>> {
+    let search_region = search_region.unwrap_or_default();
+    let rect: core::Rect = search_region.into();
+    let screenshot = convert_bitmap_to_mat(&capture_screen()?);
+    let roi = Mat::roi(&screenshot, rect)?.try_clone()?;
+    let mut elements = parse_image(&roi, (rect.x, rect.y))?;
+
+    let engine = engine.unwrap_or_else(|| Box::new(TesseractOcr::new(None, None)));
+    for word in engine.read_words(search_region)? {
+        assign_word(&mut elements, &word);
+    }
+    Ok(elements)
+}
+
+/// Appends `word` to the text of the deepest element containing its centre. Words arrive in
+/// reading order, so each element's text reads in order.
+/// Returns:
+/// * Whether any element took the word.
+fn assign_word(elements: &mut [Element], word: &Word) -> bool {
+    let rect = word.rect.rect;
+    let (x, y) = (
+        rect.origin.x + rect.size.width / 2.0,
+        rect.origin.y + rect.size.height / 2.0,
+    );
+    let Some(element) = elements
+        .iter_mut()
+        .find(|element| element.bbox.contains_point(x, y))
+    else {
+        return false;
+    };
+    if !assign_word(&mut element.children, word) {
+        match &mut element.text {
+            Some(text) => {
+                text.push(' ');
+                text.push_str(&word.text);
+            }
+            None => element.text = Some(word.text.clone()),
+        }
+    }
+    true
+}
+
+/// An element picked out of the parsed screen by the panel it is in, e.g. the second text field
+/// of the panel titled "Demographics".
+/// Parameters:
+/// * `panel`: Title of the panel; see `Element::is_titled`.
+/// * `kind`: Kind of the element.
+/// * `index`: 0-based index of the element among the panel's descendants of `kind`, in reading
+///   order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ElementQuery {
+    pub panel: String,
+    pub kind: ElementKind,
+    pub index: usize,
+}
+
+impl ElementQuery {
+    pub fn new(panel: String, kind: ElementKind, index: usize) -> Self {
+        ElementQuery { panel, kind, index }
+    }
+
+    /// Parses the whole screen and finds the element.
+    /// Returns:
+    /// * Where the element is. Errors with `TemplateNotFoundError` if no panel has the title or it
+    ///   holds too few elements of the kind.
+    pub fn find(&self) -> Result<BoundingBox, Box<dyn Error>> {
+        Ok(self.select(&parse_screen(None, None)?)?.bbox)
+    }
+
+    /// The element among parsed `elements`; see `find`. The first titled panel in reading order,
+    /// at any depth, is used.
+    fn select<'a>(&self, elements: &'a [Element]) -> Result<&'a Element, Box<dyn Error>> {
+        let not_found =
+            |message: String| -> Box<dyn Error> { Box::new(TemplateNotFoundError { message }) };
+        let panel = elements
+            .iter()
+            .flat_map(|element| std::iter::once(element).chain(element.descendants()))
+            .find(|element| element.kind == ElementKind::Panel && element.is_titled(&self.panel))
+            .ok_or_else(|| not_found(format!("No panel titled '{}' on screen", self.panel)))?;
+        let found = panel.descendants_of_kind(self.kind);
+        let count = found.len();
+        found.into_iter().nth(self.index).ok_or_else(|| {
+            not_found(format!(
+                "Element {} of kind {:?} in panel '{}' requested, but only {} found",
+                self.index + 1,
+                self.kind,
+                self.panel,
+                count
+            ))
+        })
+    }
+}
+
+/// Parses a BGR image into a hierarchy of elements. `origin` is added to every bounding box, so
+/// elements of a cropped screenshot keep their screen coordinates.
+pub fn parse_image(image: &Mat, origin: (i32, i32)) -> Result<Vec<Element>, Box<dyn Error>> {
+    let outlines = edge_outlines(image)?
+        .into_iter()
+        .map(|outline| Outline {
+            rect: core::Rect::new(
+                outline.rect.x + origin.0,
+                outline.rect.y + origin.1,
+                outline.rect.width,
+                outline.rect.height,
+            ),
+            ..outline
+        })
+        .collect();
+    Ok(build_hierarchy(outlines))
+}
+
+/// Nests outlines by containment, largest first, and guesses the kind of each element once its
+/// children are known.
+fn build_hierarchy(outlines: Vec<Outline>) -> Vec<Element> {
+    let mut elements: Vec<Element> = outlines
+        .into_iter()
+        .map(|outline| Element {
+            bbox: BoundingBox {
+                x: outline.rect.x,
+                y: outline.rect.y,
+                width: outline.rect.width,
+                height: outline.rect.height,
+            },
+            kind: ElementKind::Unknown,
+            text: None,
+            children: Vec::new(),
+            border_coverage: outline.border_coverage,
+        })
+        .collect();
+    elements.sort_by_key(|element| -element.bbox.area());
+
+    let mut roots: Vec<Element> = Vec::new();
+    for element in elements {
+        insert(&mut roots, element);
+    }
+    finish(roots)
+}
+
+/// Adds `element` as a child of the deepest element in `siblings` that contains it, or as a new
+/// sibling if none does.
+fn insert(siblings: &mut Vec<Element>, element: Element) {
+    match siblings
+        .iter_mut()
+        .find(|sibling| sibling.bbox.contains(&element.bbox))
+    {
+        Some(parent) => insert(&mut parent.children, element),
+        None => siblings.push(element),
+    }
+}
+
+/// Sorts every level into reading order and guesses each element's kind.
+fn finish(elements: Vec<Element>) -> Vec<Element> {
+    let row_height = elements
+        .iter()
+        .map(|element| element.bbox.height)
+        .min()
+        .unwrap_or(0);
+    let mut elements = reading_order_by(
+        elements,
+        |element| (element.bbox.x, element.bbox.y),
+        row_height,
+    );
+    for element in &mut elements {
+        element.children = finish(std::mem::take(&mut element.children));
+        element.kind = guess_kind(element);
+    }
+    elements
+}
+
+/// Guesses what an element is. Elements without a drawn border are text, or unknown graphics if
+/// too tall for text. Bordered elements are checkboxes if small and square, buttons or text
+/// fields if a single line tall, and panels otherwise. A button's label is centred in it, while
+/// a text field is empty or its text starts at the left.
+fn guess_kind(element: &Element) -> ElementKind {
+    let bbox = &element.bbox;
+    if element.border_coverage < BORDERED {
+        return if bbox.height <= CONTROL_MAX_HEIGHT {
+            ElementKind::Text
+        } else {
+            ElementKind::Unknown
+        };
+    }
+
+    let is_square = (bbox.width - bbox.height).abs() * 5 <= bbox.height;
+    if is_square && bbox.width <= CHECKBOX_MAX_SIZE {
+        return ElementKind::Checkbox;
+    }
+    if bbox.height > CONTROL_MAX_HEIGHT {
+        return ElementKind::Panel;
+    }
+
+    let left = element.children.iter().map(|child| child.bbox.x).min();
+    let right = element
+        .children
+        .iter()
+        .map(|child| child.bbox.x + child.bbox.width)
+        .max();
+    match left.zip(right) {
+        Some((left, right)) => {
+            let (margin_left, margin_right) = (left - bbox.x, bbox.x + bbox.width - right);
+            if (margin_left - margin_right).abs() * 4 <= bbox.width {
+                ElementKind::Button
+            } else {
+                ElementKind::TextField
+            }
+        }
+        None => ElementKind::TextField,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A "Demographics" panel holding a title, two text fields and an OK button.
+    fn demographics() -> Vec<Element> {
+        let mut elements = build_hierarchy(vec![
+            Outline::at(10, 10, 300, 200, 1.0),
+            Outline::at(20, 15, 100, 14, 0.1),
+            Outline::at(20, 40, 200, 24, 0.9),
+            Outline::at(20, 80, 200, 24, 0.9),
+            Outline::at(110, 150, 60, 24, 0.95),
+            Outline::at(130, 156, 20, 12, 0.2),
+        ]);
+        for word in [
+            Word::at("Demographics", 25.0, 17.0, 0),
+            Word::at("Smith", 24.0, 47.0, 0),
+            Word::at("OK", 135.0, 157.0, 0),
+            Word::at("v1.2", 290.0, 190.0, 0),
+            Word::at("Menu", 400.0, 10.0, 0),
+        ] {
+            assign_word(&mut elements, &word);
+        }
+        elements
+    }
+
+    #[test]
+    fn nests_outlines_by_containment() {
+        let elements = build_hierarchy(vec![
+            // Label of the OK button, the button, an empty text field and the form panel.
+            Outline::at(130, 112, 20, 12, 0.2),
+            Outline::at(110, 106, 60, 24, 0.95),
+            Outline::at(20, 60, 200, 24, 0.9),
+            Outline::at(10, 10, 300, 200, 1.0),
+            Outline::at(400, 10, 10, 10, 0.3),
+        ]);
+
+        assert_eq!(elements.len(), 2);
+        let panel = &elements[0];
+        assert_eq!(panel.kind, ElementKind::Panel);
+        assert_eq!(
+            panel
+                .children
+                .iter()
+                .map(|child| child.kind)
+                .collect::<Vec<_>>(),
+            vec![ElementKind::TextField, ElementKind::Button]
+        );
+        assert_eq!(panel.descendants().len(), 3);
+        assert_eq!(panel.descendants_of_kind(ElementKind::Text)[0].bbox.x, 130);
+        assert_eq!(elements[1].kind, ElementKind::Text);
+    }
+
+    #[test]
+    fn guesses_checkboxes_and_left_aligned_fields() {
+        let elements = build_hierarchy(vec![
+            Outline::at(10, 10, 16, 16, 0.9),
+            Outline::at(50, 10, 200, 24, 0.9),
+            Outline::at(54, 14, 40, 14, 0.1),
+        ]);
+
+        assert_eq!(elements[0].kind, ElementKind::Checkbox);
+        assert_eq!(elements[1].kind, ElementKind::TextField);
+    }
+
+    #[test]
+    fn assigns_words_to_deepest_element() {
+        let elements = demographics();
+        let panel = &elements[0];
+        let children: Vec<Option<&str>> = panel
+            .children
+            .iter()
+            .map(|child| child.text.as_deref())
+            .collect();
+
+        assert_eq!(elements.len(), 1);
+        assert_eq!(panel.text.as_deref(), Some("v1.2"));
+        assert_eq!(
+            children,
+            vec![Some("Demographics"), Some("Smith"), None, None]
+        );
+        assert_eq!(panel.children[3].children[0].text.as_deref(), Some("OK"));
+        assert!(panel.is_titled("Demographics"));
+    }
+
+    #[test]
+    fn selects_elements_of_titled_panel() {
+        let elements = demographics();
+        let second_field = ElementQuery::new("Demographics".to_string(), ElementKind::TextField, 1);
+        assert_eq!(second_field.select(&elements).unwrap().bbox.y, 80);
+
+        let third_field = ElementQuery::new("Demographics".to_string(), ElementKind::TextField, 2);
+        let other_panel = ElementQuery::new("Orders".to_string(), ElementKind::Button, 0);
+        for query in [third_field, other_panel] {
+            assert!(query
+                .select(&elements)
+                .unwrap_err()
+                .is::<TemplateNotFoundError>());
+        }
+    }
+
+    #[test]
+    fn serializes_to_json() {
+        let elements = build_hierarchy(vec![Outline::at(1, 2, 30, 12, 0.1)]);
+        assert_eq!(
+            serde_json::to_string(&elements).unwrap(),
+            r#"[{"bbox":{"x":1,"y":2,"width":30,"height":12},"kind":"text","children":[]}]"#
+        );
+    }
+}
@@ -1,71 +1,320 @@
+use crate::errors::{ScreenCoordinateError, TemplateNotFoundError};
 use crate::nav::coordinate::{ScreenCoordinates, ScreenRect};
+use crate::ocr::{find_phrase, OcrEngine, TesseractOcr, Word};
 use crate::utils::convert_bitmap_to_mat;
 use autopilot::{
     bitmap::{self, capture_screen, Bitmap},
     geometry, screen,
 };
-use image::io::Reader;
+use image::{io::Reader, GenericImageView};
 use opencv::{
     core::{self, min_max_loc, no_array, Mat},
     imgcodecs,
-    imgproc::{self, match_template, resize, INTER_AREA},
+    imgproc::{self, match_template, resize, INTER_AREA, INTER_LINEAR},
     prelude::*,
 };
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::error::Error;
+use std::rc::Rc;
+
+/// Minimum confidence for a match to count as found when none is given.
+pub const DEFAULT_MIN_CONFIDENCE: f64 = 0.8;
+
+/// The best match of a template within a search region.
+/// * `location`: Top left corner of the match.
+/// * `score`: Confidence of the match, from 0.0 to 1.0.
+/// * `rect`: The area of the screen the template matched.
+/// * `scale`: Scale at which the template matched, relative to the screen's scale factor. Always
+///   1.0 for strategies that do not rescale the template.
+#[derive(Debug, Clone, Copy)]
+pub struct MatchResult {
+    pub location: ScreenCoordinates,
+    pub score: f64,
+    pub rect: ScreenRect,
+    pub scale: f64,
+}
+
+impl MatchResult {
+    /// Centre of the area the match covers, which allows for the scale the template matched at
+    /// and for elements sized differently from the template.
+    pub fn center(&self) -> Result<ScreenCoordinates, ScreenCoordinateError> {
+        let size = self.rect.rect.size;
+        self.location.shift(size.width / 2.0, size.height / 2.0)
+    }
+}
 
 pub trait LocationStrategy {
+    /// Finds the best match of the template.
+    /// Parameters:
+    /// * `search_region`: Optional. Where to search. Default is the entire screen.
+    /// * `min_confidence`: Optional. Score the match must reach. Default is
+    ///   `DEFAULT_MIN_CONFIDENCE`.
+    ///
+    /// Returns:
+    /// * `MatchResult` of the best match. Errors with `TemplateNotFoundError` if it scores below
+    ///   `min_confidence`.
+    fn find_match(
+        &self,
+        search_region: Option<ScreenRect>,
+        min_confidence: Option<f64>,
+    ) -> Result<MatchResult, Box<dyn Error>>;
+
+    /// Top left corner of the best match, found with the default minimum confidence.
     fn get_location(
         &self,
         search_region: Option<ScreenRect>,
-    ) -> Result<ScreenCoordinates, Box<dyn Error>>;
+    ) -> Result<ScreenCoordinates, Box<dyn Error>> {
+        Ok(self.find_match(search_region, None)?.location)
+    }
+
+    /// Finds every occurrence of the template, e.g. the same button on each row of a list.
+    /// Takes the same parameters as `find_match`. Overlapping matches of one element are reduced
+    /// to the best scoring one.
+    /// Returns:
+    /// * Matches in reading order: top-to-bottom, then left-to-right. Empty if there are none.
+    fn find_all(
+        &self,
+        search_region: Option<ScreenRect>,
+        min_confidence: Option<f64>,
+    ) -> Result<Vec<MatchResult>, Box<dyn Error>> {
+        match self.find_match(search_region, min_confidence) {
+            Ok(found) => Ok(vec![found]),
+            Err(e) if e.is::<TemplateNotFoundError>() => Ok(vec![]),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Overlap, as intersection over union, above which two matches are the same element.
+const MAX_OVERLAP: f64 = 0.3;
+
+/// A candidate match: top left corner and score. All candidates of one search share a size.
+type Candidate = (i32, i32, f64);
+
+/// Non-maximum suppression: keeps the best scoring candidate of every group of overlapping
+/// `width` x `height` boxes.
+fn suppress_non_maxima(mut candidates: Vec<Candidate>, width: i32, height: i32) -> Vec<Candidate> {
+    let overlap = |a: &Candidate, b: &Candidate| {
+        let overlap_x = (width - (a.0 - b.0).abs()).max(0) as f64;
+        let overlap_y = (height - (a.1 - b.1).abs()).max(0) as f64;
+        let intersection = overlap_x * overlap_y;
+        intersection / (2.0 * width as f64 * height as f64 - intersection)
+    };
+
+    candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+    let mut kept: Vec<Candidate> = Vec::new();
+    for candidate in candidates {
+        if kept.iter().all(|k| overlap(k, &candidate) <= MAX_OVERLAP) {
+            kept.push(candidate);
+        }
+    }
+    kept
+}
+
+/// Sorts candidates top-to-bottom, then left-to-right. Candidates whose tops are within half a
+/// box `height` of the first candidate of a row belong to that row, so slightly misaligned icons
+/// on one line keep their left-to-right order.
+fn sort_reading_order(candidates: Vec<Candidate>, height: i32) -> Vec<Candidate> {
+    reading_order_by(candidates, |c| (c.0, c.1), height)
+}
+
+/// `sort_reading_order` for any item with a top left corner given by `position`.
+pub(crate) fn reading_order_by<T: Clone>(
+    mut items: Vec<T>,
+    position: impl Fn(&T) -> (i32, i32),
+    height: i32,
+) -> Vec<T> {
+    items.sort_by_key(|item| {
+        let (x, y) = position(item);
+        (y, x)
+    });
+    let mut rows: Vec<Vec<T>> = Vec::new();
+    for item in items {
+        match rows.last_mut() {
+            Some(row) if position(&item).1 - position(&row[0]).1 <= height / 2 => row.push(item),
+            _ => rows.push(vec![item]),
+        }
+    }
+    for row in &mut rows {
+        row.sort_by_key(|item| position(item).0);
+    }
+    rows.concat()
+}
+
+fn not_found(template_path: &str, message: String) -> Box<dyn Error> {
+    Box::new(TemplateNotFoundError {
+        message: format!("{}: {}", template_path, message),
+    })
 }
 
+/// Scales, relative to the screen's scale factor, at which `TemplateMatchingStrategy` tries a
+/// template, so templates captured at another DPI setting or window zoom still match.
+/// * `min`, `max`: Smallest and largest scale tried, inclusive.
+/// * `step`: Difference between consecutive scales.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScaleRange {
+    pub min: f64,
+    pub max: f64,
+    pub step: f64,
+}
+
+impl Default for ScaleRange {
+    /// The template's own scale only. Sweeping a range costs one match per scale on every lookup
+    /// of a template that is not on screen, so wider ranges are opt-in.
+    fn default() -> Self {
+        ScaleRange {
+            min: 1.0,
+            max: 1.0,
+            step: 0.1,
+        }
+    }
+}
+
+impl ScaleRange {
+    /// Every scale in the range, nearest to 1.0 first so that ties go to the unscaled template.
+    fn scales(&self) -> Vec<f64> {
+        if self.step <= 0.0 || self.max <= self.min {
+            return vec![self.min];
+        }
+        let count = ((self.max - self.min) / self.step + 1e-9).floor() as usize;
+        let mut scales: Vec<f64> = (0..=count)
+            .map(|i| ((self.min + i as f64 * self.step) * 1e6).round() / 1e6)
+            .collect();
+        scales.sort_by(|a, b| (a - 1.0).abs().total_cmp(&(b - 1.0).abs()));
+        scales
+    }
+}
+
+/// Locates a template with OpenCV's normalised cross-correlation, trying it at every scale in
+/// `scale_range`.
+/// Parameters:
+/// * `template_path`: Path of the template image.
+/// * `scale_range`: Optional. Scales to try. Default is `ScaleRange::default()`.
+/// * `scale_cache`: Optional. Where the scale of the last confident match is kept, to be tried
+///   first on the next lookup, so only the first lookup of a template, or one after the screen's
+///   scaling changed, sweeps the range. Pass every strategy locating a template the same cache, as
+///   `TemplateRegistry` does, for the scale to carry over between statements. Default is a new,
+///   unshared cache.
 pub struct TemplateMatchingStrategy {
     pub template_path: String,
+    pub scale_range: ScaleRange,
+    scale_cache: ScaleCache,
 }
 
+/// Scales templates last matched at confidently, by template path.
+pub type ScaleCache = Rc<RefCell<HashMap<String, f64>>>;
+
+/// Locates a template by comparing it pixel by pixel with the screen, using autopilot's
+/// `find_bitmap`. A match at confidence `c` has every pixel within a colour tolerance of `1 - c`.
+/// Without a minimum confidence, the tolerance is `find_bitmap`'s long-standing 0.8.
 pub struct BitmapNeedleStrategy {
     pub template_path: String,
 }
 
+/// Locates a GUI element by its structure instead of its pixels, so a button is found whatever
+/// its label says. Edges of the template and the screen are grouped into outlines with Canny edge
+/// detection and connected components, and screen outlines are scored against the template's by
+/// height, width and how much of their border is drawn; see `structure_score`.
+/// Parameters:
+/// * `template_path`: Path of an image of one instance of the element, cropped to its border.
+/// * `label`: Optional. Text the element must contain, read with OCR, to pick the right one of
+///   several look-alike buttons.
 pub struct EdgeParsingStrategy {
     pub template_path: String,
+    pub label: Option<String>,
+}
+
+/// Locates text on screen with OCR, so a labelled element can be targeted without a template.
+/// Matching tolerates OCR mistakes; a match's score is the similarity of the words read to
+/// `text`, see `ocr::find_phrase`.
+/// Parameters:
+/// * `text`: The text to find, e.g. `Chart Review`.
+/// * `case_sensitive`: Optional. Whether case must match. Default is false.
+/// * `engine`: Optional. OCR engine that reads the screen. Default is `TesseractOcr`.
+pub struct TextLocationStrategy {
+    pub text: String,
+    pub case_sensitive: bool,
+    engine: Box<dyn OcrEngine>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum LocationStrategyType {
     TemplateMatching,
     BitmapNeedle,
     EdgeParsing,
 }
 
-impl LocationStrategy for TemplateMatchingStrategy {
-    fn get_location(
-        &self,
-        search_region: Option<ScreenRect>,
-    ) -> Result<ScreenCoordinates, Box<dyn Error>> {
+impl TemplateMatchingStrategy {
+    pub fn new(
+        template_path: String,
+        scale_range: Option<ScaleRange>,
+        scale_cache: Option<ScaleCache>,
+    ) -> Self {
+        TemplateMatchingStrategy {
+            template_path,
+            scale_range: scale_range.unwrap_or_default(),
+            scale_cache: scale_cache.unwrap_or_default(),
+        }
+    }
+
+    /// Screenshot of the search region and the template image, both loaded once per lookup.
+    fn load(&self, search_region: core::Rect) -> Result<(Mat, Mat), Box<dyn Error>> {
         let screenshot = capture_screen()?;
-        let search_region = search_region.unwrap_or(ScreenRect::default());
-        let search_region: core::Rect = search_region.into();
         let screenshot = convert_bitmap_to_mat(&screenshot);
-
-        let roi = Mat::roi(&screenshot, search_region)?;
+        let roi = Mat::roi(&screenshot, search_region)?.try_clone()?;
 
         let template = imgcodecs::imread(&self.template_path, imgcodecs::IMREAD_COLOR)?;
-        let mut template_scaled = Mat::default();
-        let dst_size = template_scaled.size()?;
+        if template.empty() {
+            return Err(not_found(
+                &self.template_path,
+                "template image could not be read".to_string(),
+            ));
+        }
+        Ok((roi, template))
+    }
+
+    /// `TM_CCOEFF_NORMED` score of the template at every position in the search region, and the
+    /// size of the template as matched. `scale` is relative to the screen's scale factor.
+    /// Returns `None` if the scaled template is empty or larger than the search region.
+    fn match_scores(
+        roi: &Mat,
+        template: &Mat,
+        scale: f64,
+    ) -> Result<Option<(Mat, core::Size)>, Box<dyn Error>> {
+        let factor = scale / screen::scale();
+        let size = template.size()?;
+        let dst_size = core::Size::new(
+            (size.width as f64 * factor).round() as i32,
+            (size.height as f64 * factor).round() as i32,
+        );
+        let roi_size = roi.size()?;
+        if dst_size.width < 1
+            || dst_size.height < 1
+            || dst_size.width > roi_size.width
+            || dst_size.height > roi_size.height
+        {
+            return Ok(None);
+        }
 
+        let mut template_scaled = Mat::default();
+        let interpolation = if factor < 1.0 {
+            INTER_AREA
+        } else {
+            INTER_LINEAR
+        };
         resize(
-            &template,
+            template,
             &mut template_scaled,
             dst_size,
-            1.0 / screen::scale(),
-            1.0 / screen::scale(),
-            INTER_AREA,
+            0.0,
+            0.0,
+            interpolation,
         )?;
 
         let mut match_result = Mat::default();
         match_template(
-            &roi,
+            roi,
             &template_scaled,
             &mut match_result,
             imgproc::TM_CCOEFF_NORMED,
@@ -98,55 +347,932 @@ impl LocationStrategy for TemplateMatchingStrategy {
         //     &Vector::new(),
         // )?;
 
+        Ok(Some((match_result, dst_size)))
+    }
+
+    /// Best match of the template at one scale, or `None` if it does not fit at that scale.
+    fn best_match(
+        roi: &Mat,
+        template: &Mat,
+        scale: f64,
+    ) -> Result<Option<ScaledMatch>, Box<dyn Error>> {
+        let Some((scores, size)) = Self::match_scores(roi, template, scale)? else {
+            return Ok(None);
+        };
+
+        let mut max_score = 0.0;
         let mut match_location = core::Point::default();
         min_max_loc(
-            &match_result,
-            None,
+            &scores,
             None,
+            Some(&mut max_score),
             None,
             Some(&mut match_location),
             &no_array(),
         )?;
+        Ok(Some(ScaledMatch {
+            location: match_location,
+            score: max_score,
+            size,
+            scale,
+            scores,
+        }))
+    }
 
-        // ScreenCoordinates takes any type convertible into Coordinate
-        // therefore absolute_x and absolute_y will be silently rescaled to be scaled coordinates
-        // instead of physical coordinates
-        let result = ScreenCoordinates::new(match_location.x, match_location.y)?;
+    /// Best match across the scale range. The cached scale is tried first and the sweep is
+    /// skipped if it still reaches `min_confidence`; otherwise the winning scale of the sweep is
+    /// cached when it reaches `min_confidence`.
+    /// Returns `None` if the template does not fit the search region at any scale.
+    fn best_scaled_match(
+        &self,
+        roi: &Mat,
+        template: &Mat,
+        min_confidence: f64,
+    ) -> Result<Option<ScaledMatch>, Box<dyn Error>> {
+        let cached_scale = self.scale_cache.borrow().get(&self.template_path).copied();
+        if let Some(scale) = cached_scale {
+            if let Some(found) = Self::best_match(roi, template, scale)? {
+                if found.score >= min_confidence {
+                    return Ok(Some(found));
+                }
+            }
+        }
+
+        let mut best: Option<ScaledMatch> = None;
+        for scale in self.scale_range.scales() {
+            if let Some(found) = Self::best_match(roi, template, scale)? {
+                if !best
+                    .as_ref()
+                    .is_some_and(|current| current.score >= found.score)
+                {
+                    best = Some(found);
+                }
+            }
+        }
+        if let Some(found) = best.as_ref().filter(|found| found.score >= min_confidence) {
+            self.scale_cache
+                .borrow_mut()
+                .insert(self.template_path.clone(), found.scale);
+        }
+        Ok(best)
+    }
 
-        Ok(result)
+    /// Like `best_scaled_match`, but errors with `TemplateNotFoundError` unless the match reaches
+    /// `min_confidence`.
+    fn confident_match(
+        &self,
+        roi: &Mat,
+        template: &Mat,
+        min_confidence: f64,
+    ) -> Result<ScaledMatch, Box<dyn Error>> {
+        match self.best_scaled_match(roi, template, min_confidence)? {
+            Some(found) if found.score >= min_confidence => Ok(found),
+            Some(found) => Err(not_found(
+                &self.template_path,
+                format!(
+                    "best match scored {:.2} at {:.0}% scale, below the minimum confidence of {:.2}",
+                    found.score,
+                    found.scale * 100.0,
+                    min_confidence
+                ),
+            )),
+            None => Err(not_found(
+                &self.template_path,
+                "template is larger than the search region at every scale".to_string(),
+            )),
+        }
     }
 }
 
-impl LocationStrategy for BitmapNeedleStrategy {
-    fn get_location(
+/// The best match of a template at one scale, relative to the search region, and the scores it
+/// was picked from, for finding the other matches at that scale.
+#[derive(Debug)]
+struct ScaledMatch {
+    location: core::Point,
+    score: f64,
+    size: core::Size,
+    scale: f64,
+    scores: Mat,
+}
+
+/// Screen position of a point found in the search region. Strategies search a copy of the region
+/// cropped from the screenshot, so what they find is relative to the region's origin.
+fn region_to_screen(search_region: core::Rect, point: core::Point) -> core::Point {
+    core::Point::new(point.x + search_region.x, point.y + search_region.y)
+}
+
+/// Builds a `MatchResult` from a match location relative to the search region.
+fn match_result(
+    search_region: core::Rect,
+    match_location: core::Point,
+    score: f64,
+    size: core::Size,
+    scale: f64,
+) -> Result<MatchResult, Box<dyn Error>> {
+    // ScreenCoordinates takes any type convertible into Coordinate
+    // therefore absolute_x and absolute_y will be silently rescaled to be scaled coordinates
+    // instead of physical coordinates
+    let match_location = region_to_screen(search_region, match_location);
+    let location = ScreenCoordinates::new(match_location.x, match_location.y)?;
+    let rect = ScreenRect::new(
+        location.x,
+        location.y,
+        size.width as f64,
+        size.height as f64,
+    );
+
+    Ok(MatchResult {
+        location,
+        score,
+        rect,
+        scale,
+    })
+}
+
+impl LocationStrategy for TemplateMatchingStrategy {
+    fn find_match(
         &self,
         search_region: Option<ScreenRect>,
-    ) -> Result<ScreenCoordinates, Box<dyn Error>> {
-        let needle = Bitmap::new(
-            Reader::open(&self.template_path)
-                .expect("Failed to read image file")
-                .decode()
-                .expect("Failed to read image file"),
+        min_confidence: Option<f64>,
+    ) -> Result<MatchResult, Box<dyn Error>> {
+        let min_confidence = min_confidence.unwrap_or(DEFAULT_MIN_CONFIDENCE);
+        let search_region: core::Rect = search_region.unwrap_or_default().into();
+        let (roi, template) = self.load(search_region)?;
+        let found = self.confident_match(&roi, &template, min_confidence)?;
+
+        match_result(
+            search_region,
+            found.location,
+            found.score,
+            found.size,
+            found.scale,
+        )
+    }
+
+    /// Every match is at the scale of the best match, since the occurrences of one element on a
+    /// screen share a scale.
+    fn find_all(
+        &self,
+        search_region: Option<ScreenRect>,
+        min_confidence: Option<f64>,
+    ) -> Result<Vec<MatchResult>, Box<dyn Error>> {
+        let min_confidence = min_confidence.unwrap_or(DEFAULT_MIN_CONFIDENCE);
+        let search_region: core::Rect = search_region.unwrap_or_default().into();
+        let (roi, template) = self.load(search_region)?;
+        let found = match self.confident_match(&roi, &template, min_confidence) {
+            Ok(found) => found,
+            Err(e) if e.is::<TemplateNotFoundError>() => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+        let size = found.size;
+        let candidates = score_peaks(&found.scores, size, min_confidence)?;
+        let candidates = suppress_non_maxima(candidates, size.width, size.height);
+
+        sort_reading_order(candidates, size.height)
+            .into_iter()
+            .map(|(x, y, score)| {
+                match_result(
+                    search_region,
+                    core::Point::new(x, y),
+                    score,
+                    size,
+                    found.scale,
+                )
+            })
+            .collect()
+    }
+}
+
+/// Candidates of a template matched at `size`: the positions of `scores` above `min_confidence`
+/// that score highest within a window half the template's size. Any candidate this drops would
+/// overlap a better one enough for `suppress_non_maxima` to drop it too, so suppression only
+/// compares the few peaks instead of every position above `min_confidence`.
+fn score_peaks(
+    scores: &Mat,
+    size: core::Size,
+    min_confidence: f64,
+) -> Result<Vec<Candidate>, Box<dyn Error>> {
+    let window = core::Size::new((size.width / 2).max(1), (size.height / 2).max(1));
+    let mut window_max = Mat::default();
+    imgproc::dilate(
+        scores,
+        &mut window_max,
+        &imgproc::get_structuring_element(imgproc::MORPH_RECT, window, core::Point::new(-1, -1))?,
+        core::Point::new(-1, -1),
+        1,
+        core::BORDER_CONSTANT,
+        imgproc::morphology_default_border_value()?,
+    )?;
+    let mut is_peak = Mat::default();
+    core::compare(scores, &window_max, &mut is_peak, core::CMP_GE)?;
+
+    let mut confident = Mat::default();
+    imgproc::threshold(
+        scores,
+        &mut confident,
+        min_confidence,
+        255.0,
+        imgproc::THRESH_BINARY,
+    )?;
+    let mut confident_mask = Mat::default();
+    confident.convert_to(&mut confident_mask, core::CV_8U, 1.0, 0.0)?;
+
+    let mut peaks = Mat::default();
+    core::bitwise_and(&is_peak, &confident_mask, &mut peaks, &no_array())?;
+    let mut positions = core::Vector::<core::Point>::new();
+    core::find_non_zero(&peaks, &mut positions)?;
+    positions
+        .iter()
+        .map(|position| {
+            let score = *scores.at_2d::<f32>(position.y, position.x)? as f64;
+            Ok((position.x, position.y, score))
+        })
+        .collect()
+}
+
+/// Confidence giving `BitmapNeedleStrategy` its default tolerance of 0.8, which is looser than the
+/// `DEFAULT_MIN_CONFIDENCE` of the other strategies.
+const NEEDLE_DEFAULT_CONFIDENCE: f64 = 0.2;
+
+/// Colour tolerance `find_bitmap` must meet for a match at `min_confidence`.
+fn needle_tolerance(min_confidence: f64) -> f64 {
+    (1.0 - min_confidence).clamp(0.0, 1.0)
+}
+
+/// Similarity of pairs of RGB colours from 0.0 to 1.0: one minus their mean distance, measured as
+/// `find_bitmap` measures it, as a fraction of the distance between black and white. A needle
+/// matched within tolerance `t` therefore scores at least `1 - t`.
+fn colour_similarity(pairs: impl Iterator<Item = ([u8; 3], [u8; 3])>) -> f64 {
+    let max_distance = (3.0 * 255.0_f64.powi(2)).sqrt();
+    let (mut total, mut count) = (0.0, 0);
+    for (a, b) in pairs {
+        let distance: f64 = (0..3)
+            .map(|i| (a[i] as f64 - b[i] as f64).powi(2))
+            .sum::<f64>()
+            .sqrt();
+        total += distance / max_distance;
+        count += 1;
+    }
+    if count == 0 {
+        1.0
+    } else {
+        1.0 - total / count as f64
+    }
+}
+
+impl BitmapNeedleStrategy {
+    fn needle(&self) -> Result<Bitmap, Box<dyn Error>> {
+        Ok(Bitmap::new(
+            Reader::open(&self.template_path)?.decode()?,
             Some(screen::scale()),
-        );
+        ))
+    }
+
+    /// Score of the needle found at `location` of the screenshot, so matches can be compared, e.g.
+    /// the checked and unchecked states of a checkbox.
+    fn score_at(screenshot: &Bitmap, needle: &Bitmap, location: geometry::Point) -> f64 {
+        let rgb = |pixel: image::Rgba<u8>| [pixel[0], pixel[1], pixel[2]];
+        let left = (location.x * screenshot.scale).round() as u32;
+        let top = (location.y * screenshot.scale).round() as u32;
+        let (width, height) = needle.image.dimensions();
+        let (screen_width, screen_height) = screenshot.image.dimensions();
+        colour_similarity(
+            (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .filter(|(x, y)| left + x < screen_width && top + y < screen_height)
+                .map(|(x, y)| {
+                    (
+                        rgb(needle.image.get_pixel(x, y)),
+                        rgb(screenshot.image.get_pixel(left + x, top + y)),
+                    )
+                }),
+        )
+    }
+
+    fn match_result(location: geometry::Point, score: f64, size: geometry::Size) -> MatchResult {
+        let location: ScreenCoordinates = location.into();
+        let rect = ScreenRect::new(location.x, location.y, size.width, size.height);
+        MatchResult {
+            location,
+            score,
+            rect,
+            scale: 1.0,
+        }
+    }
+}
+
+impl LocationStrategy for BitmapNeedleStrategy {
+    /// `find_bitmap` only reports whether the needle matched within a tolerance, so the score is
+    /// computed from the pixels of the match; see `colour_similarity`. Default minimum confidence
+    /// is `NEEDLE_DEFAULT_CONFIDENCE`.
+    fn find_match(
+        &self,
+        search_region: Option<ScreenRect>,
+        min_confidence: Option<f64>,
+    ) -> Result<MatchResult, Box<dyn Error>> {
+        let min_confidence = min_confidence.unwrap_or(NEEDLE_DEFAULT_CONFIDENCE);
+        let needle = self.needle()?;
 
         let screenshot = capture_screen()?;
-        let search_region = search_region.unwrap_or(ScreenRect::default());
-        let search_region: geometry::Rect = search_region.into();
+        let search_region: geometry::Rect = search_region.unwrap_or_default().into();
         let found = screenshot
-            .find_bitmap(&needle, Some(0.8), Some(search_region), None)
-            .expect("Template not found in image")
-            .into();
+            .find_bitmap(
+                &needle,
+                Some(needle_tolerance(min_confidence)),
+                Some(search_region),
+                None,
+            )
+            .ok_or_else(|| {
+                not_found(
+                    &self.template_path,
+                    format!("no match with a confidence of {:.2}", min_confidence),
+                )
+            })?;
+
+        let score = Self::score_at(&screenshot, &needle, found);
+        Ok(Self::match_result(found, score, needle.size))
+    }
+
+    fn find_all(
+        &self,
+        search_region: Option<ScreenRect>,
+        min_confidence: Option<f64>,
+    ) -> Result<Vec<MatchResult>, Box<dyn Error>> {
+        let min_confidence = min_confidence.unwrap_or(NEEDLE_DEFAULT_CONFIDENCE);
+        let needle = self.needle()?;
+
+        let screenshot = capture_screen()?;
+        let search_region: geometry::Rect = search_region.unwrap_or_default().into();
+        let candidates = screenshot
+            .find_every_bitmap(
+                &needle,
+                Some(needle_tolerance(min_confidence)),
+                Some(search_region),
+                None,
+            )
+            .into_iter()
+            .map(|point| {
+                let score = Self::score_at(&screenshot, &needle, point);
+                (point.x as i32, point.y as i32, score)
+            })
+            .collect();
+        let (width, height) = (needle.size.width as i32, needle.size.height as i32);
+        let candidates = suppress_non_maxima(candidates, width, height);
+
+        Ok(sort_reading_order(candidates, height)
+            .into_iter()
+            .map(|(x, y, score)| {
+                Self::match_result(geometry::Point::new(x as f64, y as f64), score, needle.size)
+            })
+            .collect())
+    }
+}
+
+/// Canny thresholds for edge parsing. Low enough to pick up the faint borders of flat controls.
+pub(crate) const CANNY_LOW: f64 = 10.0;
+pub(crate) const CANNY_HIGH: f64 = 100.0;
+
+/// Outlines narrower or shorter than this many pixels are noise, not elements.
+const MIN_OUTLINE_SIZE: i32 = 6;
+
+/// Padding added around a template before edge detection, so a border drawn on the very edge of
+/// the image is still detected.
+const TEMPLATE_PADDING: i32 = 4;
+
+/// Bounding box of one connected group of edges, such as a button's border.
+/// * `rect`: Bounding box, in pixels of the image the outline was found in.
+/// * `border_coverage`: Fraction of the bounding box's perimeter lying on an edge, from 0.0 to
+///   1.0. Close to 1.0 for bordered controls; low for text and icons.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Outline {
+    pub rect: core::Rect,
+    pub border_coverage: f64,
+}
+
+#[cfg(test)]
+impl Outline {
+    /// An outline with its bounding box at `(x, y)`, as found by `edge_outlines`.
+    pub fn at(x: i32, y: i32, width: i32, height: i32, border_coverage: f64) -> Outline {
+        Outline {
+            rect: core::Rect::new(x, y, width, height),
+            border_coverage,
+        }
+    }
+}
+
+/// Outlines of every connected group of edges in a BGR image. Edges are dilated before grouping
+/// so borders broken up by anti-aliasing stay connected.
+pub(crate) fn edge_outlines(image: &Mat) -> Result<Vec<Outline>, Box<dyn Error>> {
+    let mut gray = Mat::default();
+    imgproc::cvt_color(image, &mut gray, imgproc::COLOR_BGR2GRAY, 0)?;
+    let mut edges = Mat::default();
+    imgproc::canny(&gray, &mut edges, CANNY_LOW, CANNY_HIGH, 3, false)?;
+
+    let kernel = imgproc::get_structuring_element(
+        imgproc::MORPH_RECT,
+        core::Size::new(3, 3),
+        core::Point::new(-1, -1),
+    )?;
+    let mut dilated = Mat::default();
+    imgproc::dilate(
+        &edges,
+        &mut dilated,
+        &kernel,
+        core::Point::new(-1, -1),
+        1,
+        core::BORDER_CONSTANT,
+        imgproc::morphology_default_border_value()?,
+    )?;
+
+    let mut labels = Mat::default();
+    let mut stats = Mat::default();
+    let mut centroids = Mat::default();
+    let count = imgproc::connected_components_with_stats(
+        &dilated,
+        &mut labels,
+        &mut stats,
+        &mut centroids,
+        8,
+        core::CV_32S,
+    )?;
+
+    let mut outlines = Vec::new();
+    // Label 0 is the background.
+    for label in 1..count {
+        let rect = core::Rect::new(
+            *stats.at_2d::<i32>(label, imgproc::CC_STAT_LEFT)?,
+            *stats.at_2d::<i32>(label, imgproc::CC_STAT_TOP)?,
+            *stats.at_2d::<i32>(label, imgproc::CC_STAT_WIDTH)?,
+            *stats.at_2d::<i32>(label, imgproc::CC_STAT_HEIGHT)?,
+        );
+        if rect.width < MIN_OUTLINE_SIZE || rect.height < MIN_OUTLINE_SIZE {
+            continue;
+        }
+        outlines.push(Outline {
+            rect,
+            border_coverage: border_coverage(&dilated, rect)?,
+        });
+    }
+    Ok(outlines)
+}
 
-        Ok(found)
+/// Fraction of the perimeter of `rect` that lies on a set pixel of `edges`.
+fn border_coverage(edges: &Mat, rect: core::Rect) -> Result<f64, Box<dyn Error>> {
+    let (right, bottom) = (rect.x + rect.width - 1, rect.y + rect.height - 1);
+    let mut perimeter = Vec::new();
+    for x in rect.x..=right {
+        perimeter.push((x, rect.y));
+        perimeter.push((x, bottom));
+    }
+    for y in rect.y + 1..bottom {
+        perimeter.push((rect.x, y));
+        perimeter.push((right, y));
+    }
+
+    let mut on_edge = 0;
+    for &(x, y) in &perimeter {
+        if *edges.at_2d::<u8>(y, x)? > 0 {
+            on_edge += 1;
+        }
+    }
+    Ok(on_edge as f64 / perimeter.len() as f64)
+}
+
+/// Similarity in structure, from 0.0 to 1.0, of a screen outline to the template's. Heights must
+/// agree closely, widths less so since a button grows with its label, and both borders must be
+/// drawn to a similar extent. What lies inside the outlines does not count.
+fn structure_score(template: &Outline, candidate: &Outline) -> f64 {
+    let ratio = |a: i32, b: i32| {
+        if a <= 0 || b <= 0 {
+            0.0
+        } else {
+            a.min(b) as f64 / a.max(b) as f64
+        }
+    };
+    ratio(template.rect.height, candidate.rect.height)
+        * ratio(template.rect.width, candidate.rect.width).sqrt()
+        * (1.0 - (template.border_coverage - candidate.border_coverage).abs())
+}
+
+/// Area of the intersection of two rects.
+fn intersection_area(a: &core::Rect, b: &core::Rect) -> i32 {
+    let width = ((a.x + a.width).min(b.x + b.width) - a.x.max(b.x)).max(0);
+    let height = ((a.y + a.height).min(b.y + b.height) - a.y.max(b.y)).max(0);
+    width * height
+}
+
+impl EdgeParsingStrategy {
+    pub fn new(template_path: String, label: Option<String>) -> Self {
+        EdgeParsingStrategy {
+            template_path,
+            label,
+        }
+    }
+
+    /// Outline of the element in the template: the largest outline, scaled like
+    /// `TemplateMatchingStrategy` scales its template.
+    fn template_outline(&self) -> Result<Outline, Box<dyn Error>> {
+        let template = imgcodecs::imread(&self.template_path, imgcodecs::IMREAD_COLOR)?;
+        if template.empty() {
+            return Err(not_found(
+                &self.template_path,
+                "template image could not be read".to_string(),
+            ));
+        }
+        let mut padded = Mat::default();
+        core::copy_make_border(
+            &template,
+            &mut padded,
+            TEMPLATE_PADDING,
+            TEMPLATE_PADDING,
+            TEMPLATE_PADDING,
+            TEMPLATE_PADDING,
+            core::BORDER_REPLICATE,
+            core::Scalar::default(),
+        )?;
+
+        let outline = edge_outlines(&padded)?
+            .into_iter()
+            .max_by_key(|outline| outline.rect.area())
+            .ok_or_else(|| {
+                not_found(
+                    &self.template_path,
+                    "template has no outline to match on".to_string(),
+                )
+            })?;
+        let factor = 1.0 / screen::scale();
+        Ok(Outline {
+            rect: core::Rect::new(
+                0,
+                0,
+                (outline.rect.width as f64 * factor).round() as i32,
+                (outline.rect.height as f64 * factor).round() as i32,
+            ),
+            border_coverage: outline.border_coverage,
+        })
+    }
+
+    /// Screen outlines scoring at least `min_confidence` against the template, best first.
+    /// Outlines mostly inside a better one, e.g. the inner ring of a focused button, are dropped,
+    /// as are outlines not containing `label` if one is set.
+    fn candidates(
+        &self,
+        search_region: core::Rect,
+        min_confidence: f64,
+    ) -> Result<(Vec<(Outline, f64)>, Outline), Box<dyn Error>> {
+        let template = self.template_outline()?;
+        let screenshot = convert_bitmap_to_mat(&capture_screen()?);
+        let roi = Mat::roi(&screenshot, search_region)?.try_clone()?;
+
+        let mut scored: Vec<(Outline, f64)> = edge_outlines(&roi)?
+            .into_iter()
+            .map(|outline| (outline, structure_score(&template, &outline)))
+            .filter(|(_, score)| *score >= min_confidence)
+            .collect();
+        if let Some(label) = &self.label {
+            let words = TesseractOcr::new(None, None).read_words(search_region.into())?;
+            scored.retain(|(outline, _)| {
+                let rect = ScreenRect::from(core::Rect::from_point_size(
+                    region_to_screen(search_region, outline.rect.tl()),
+                    outline.rect.size(),
+                ));
+                contains_text(&rect, label, &words)
+            });
+        }
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let mut kept: Vec<(Outline, f64)> = Vec::new();
+        for (outline, score) in scored {
+            let overlaps = kept.iter().any(|(k, _)| {
+                let smaller = k.rect.area().min(outline.rect.area()) as f64;
+                intersection_area(&k.rect, &outline.rect) as f64 / smaller > MAX_OVERLAP
+            });
+            if !overlaps {
+                kept.push((outline, score));
+            }
+        }
+        Ok((kept, template))
+    }
+
+    /// `MatchResult` for an outline. The scale is the outline's height relative to the
+    /// template's, as widths vary with the label.
+    fn match_result(
+        search_region: core::Rect,
+        outline: &Outline,
+        score: f64,
+        template: &Outline,
+    ) -> Result<MatchResult, Box<dyn Error>> {
+        match_result(
+            search_region,
+            core::Point::new(outline.rect.x, outline.rect.y),
+            score,
+            outline.rect.size(),
+            outline.rect.height as f64 / template.rect.height as f64,
+        )
     }
 }
 
 impl LocationStrategy for EdgeParsingStrategy {
-    fn get_location(
+    fn find_match(
         &self,
         search_region: Option<ScreenRect>,
-    ) -> Result<ScreenCoordinates, Box<dyn Error>> {
-        unimplemented!();
+        min_confidence: Option<f64>,
+    ) -> Result<MatchResult, Box<dyn Error>> {
+        let min_confidence = min_confidence.unwrap_or(DEFAULT_MIN_CONFIDENCE);
+        let search_region: core::Rect = search_region.unwrap_or_default().into();
+        let (candidates, template) = self.candidates(search_region, min_confidence)?;
+        match candidates.first() {
+            Some((outline, score)) => Self::match_result(search_region, outline, *score, &template),
+            None => Err(not_found(
+                &self.template_path,
+                format!(
+                    "no element with a structure matching with a confidence of {:.2}",
+                    min_confidence
+                ),
+            )),
+        }
+    }
+
+    fn find_all(
+        &self,
+        search_region: Option<ScreenRect>,
+        min_confidence: Option<f64>,
+    ) -> Result<Vec<MatchResult>, Box<dyn Error>> {
+        let min_confidence = min_confidence.unwrap_or(DEFAULT_MIN_CONFIDENCE);
+        let search_region: core::Rect = search_region.unwrap_or_default().into();
+        let (candidates, template) = self.candidates(search_region, min_confidence)?;
+
+        reading_order_by(
+            candidates,
+            |(outline, _)| (outline.rect.x, outline.rect.y),
+            template.rect.height,
+        )
+        .iter()
+        .map(|(outline, score)| Self::match_result(search_region, outline, *score, &template))
+        .collect()
+    }
+}
+
+/// Whether the words centred inside `rect` read `text` with at least `DEFAULT_MIN_CONFIDENCE`.
+fn contains_text(rect: &ScreenRect, text: &str, words: &[Word]) -> bool {
+    let (origin, size) = (rect.rect.origin, rect.rect.size);
+    let inside: Vec<(&str, usize)> = words
+        .iter()
+        .filter(|word| {
+            let x = word.rect.rect.origin.x + word.rect.rect.size.width / 2.0;
+            let y = word.rect.rect.origin.y + word.rect.rect.size.height / 2.0;
+            x >= origin.x
+                && x <= origin.x + size.width
+                && y >= origin.y
+                && y <= origin.y + size.height
+        })
+        .map(|word| (word.text.as_str(), word.line))
+        .collect();
+    find_phrase(text, &inside, false)
+        .iter()
+        .any(|(_, _, score)| *score >= DEFAULT_MIN_CONFIDENCE)
+}
+
+impl TextLocationStrategy {
+    pub fn new(
+        text: String,
+        case_sensitive: Option<bool>,
+        engine: Option<Box<dyn OcrEngine>>,
+    ) -> Self {
+        TextLocationStrategy {
+            text,
+            case_sensitive: case_sensitive.unwrap_or(false),
+            engine: engine.unwrap_or_else(|| Box::new(TesseractOcr::new(None, None))),
+        }
+    }
+
+    /// Runs of words reading the text with at least `min_confidence`, best first. A run sharing
+    /// a word with a better one is dropped.
+    fn matches(
+        &self,
+        search_region: Option<ScreenRect>,
+        min_confidence: f64,
+    ) -> Result<Vec<MatchResult>, Box<dyn Error>> {
+        let words = self.engine.read_words(search_region.unwrap_or_default())?;
+        let texts: Vec<(&str, usize)> = words
+            .iter()
+            .map(|word| (word.text.as_str(), word.line))
+            .collect();
+        let mut found: Vec<(usize, usize, f64)> =
+            find_phrase(&self.text, &texts, self.case_sensitive)
+                .into_iter()
+                .filter(|(_, _, score)| *score >= min_confidence)
+                .collect();
+        found.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+        let mut used = vec![false; words.len()];
+        let mut matches = Vec::new();
+        for (start, length, score) in found {
+            let run = start..start + length;
+            if used[run.clone()].iter().any(|used| *used) {
+                continue;
+            }
+            used[run.clone()].fill(true);
+            matches.push(Self::match_result(&words[run], score));
+        }
+        Ok(matches)
+    }
+
+    /// `MatchResult` covering a run of words. Word rects are already in screen coordinates, so
+    /// their union is not rescaled.
+    fn match_result(words: &[Word], score: f64) -> MatchResult {
+        let rects: Vec<geometry::Rect> = words.iter().map(|word| word.rect.rect).collect();
+        let left = rects.iter().map(|r| r.origin.x).fold(f64::MAX, f64::min);
+        let top = rects.iter().map(|r| r.origin.y).fold(f64::MAX, f64::min);
+        let right = rects
+            .iter()
+            .map(|r| r.origin.x + r.size.width)
+            .fold(f64::MIN, f64::max);
+        let bottom = rects
+            .iter()
+            .map(|r| r.origin.y + r.size.height)
+            .fold(f64::MIN, f64::max);
+        let origin = geometry::Point::new(left, top);
+        MatchResult {
+            location: ScreenCoordinates { point: origin },
+            score,
+            rect: ScreenRect {
+                rect: geometry::Rect::new(origin, geometry::Size::new(right - left, bottom - top)),
+            },
+            scale: 1.0,
+        }
+    }
+}
+
+impl LocationStrategy for TextLocationStrategy {
+    fn find_match(
+        &self,
+        search_region: Option<ScreenRect>,
+        min_confidence: Option<f64>,
+    ) -> Result<MatchResult, Box<dyn Error>> {
+        let min_confidence = min_confidence.unwrap_or(DEFAULT_MIN_CONFIDENCE);
+        self.matches(search_region, min_confidence)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                not_found(
+                    &format!("text<\"{}\">", self.text),
+                    format!(
+                        "no text matching with a confidence of {:.2}",
+                        min_confidence
+                    ),
+                )
+            })
+    }
+
+    fn find_all(
+        &self,
+        search_region: Option<ScreenRect>,
+        min_confidence: Option<f64>,
+    ) -> Result<Vec<MatchResult>, Box<dyn Error>> {
+        let min_confidence = min_confidence.unwrap_or(DEFAULT_MIN_CONFIDENCE);
+        let matches = self.matches(search_region, min_confidence)?;
+        let height = matches
+            .iter()
+            .map(|found| found.rect.rect.size.height as i32)
+            .min()
+            .unwrap_or(0);
+        Ok(reading_order_by(
+            matches,
+            |found| (found.location.x as i32, found.location.y as i32),
+            height,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suppresses_overlapping_matches() {
+        let candidates = vec![
+            (10, 10, 0.9),
+            (11, 10, 0.95),
+            (12, 11, 0.85),
+            (40, 10, 0.82),
+        ];
+        assert_eq!(
+            suppress_non_maxima(candidates, 20, 20),
+            vec![(11, 10, 0.95), (40, 10, 0.82)]
+        );
+    }
+
+    #[test]
+    fn offsets_matches_by_search_region() {
+        let region = core::Rect::new(300, 200, 400, 400);
+        assert_eq!(
+            region_to_screen(region, core::Point::new(15, 40)),
+            core::Point::new(315, 240)
+        );
+        // A search of the whole screen starts at the origin, so nothing moves.
+        assert_eq!(
+            region_to_screen(core::Rect::default(), core::Point::new(15, 40)),
+            core::Point::new(15, 40)
+        );
+    }
+
+    #[test]
+    fn needle_tolerance_mirrors_confidence() {
+        assert_eq!(needle_tolerance(NEEDLE_DEFAULT_CONFIDENCE), 0.8);
+        assert!((needle_tolerance(DEFAULT_MIN_CONFIDENCE) - 0.2).abs() < 1e-9);
+        assert_eq!(needle_tolerance(1.0), 0.0);
+        assert_eq!(needle_tolerance(1.5), 0.0);
+    }
+
+    #[test]
+    fn colour_similarity_scores_pixel_differences() {
+        let white = [255, 255, 255];
+        let black = [0, 0, 0];
+        assert_eq!(colour_similarity([(white, white)].into_iter()), 1.0);
+        assert_eq!(colour_similarity([(white, black)].into_iter()), 0.0);
+        assert_eq!(
+            colour_similarity([(white, white), (white, black)].into_iter()),
+            0.5
+        );
+        // A needle differing in one channel scores in between.
+        let score = colour_similarity([([255, 0, 0], black)].into_iter());
+        assert!((score - (1.0 - 1.0 / 3.0_f64.sqrt())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn scales_start_nearest_to_one() {
+        let range = ScaleRange {
+            min: 0.8,
+            max: 1.25,
+            step: 0.15,
+        };
+        assert_eq!(range.scales(), vec![0.95, 1.1, 0.8, 1.25]);
+        let wide = ScaleRange {
+            min: 0.5,
+            max: 2.0,
+            step: 0.1,
+        };
+        assert_eq!(wide.scales().len(), 16);
+        assert_eq!(wide.scales()[0], 1.0);
+        assert_eq!(ScaleRange::default().scales(), vec![1.0]);
+    }
+
+    #[test]
+    fn structure_score_ignores_label_width() {
+        let template = Outline::at(0, 0, 80, 24, 0.95);
+        assert_eq!(structure_score(&template, &template), 1.0);
+        // Same button with a longer label.
+        assert!(structure_score(&template, &Outline::at(0, 0, 100, 24, 0.9)) > 0.8);
+        // Text of the label itself, and a taller panel.
+        assert!(structure_score(&template, &Outline::at(0, 0, 60, 12, 0.2)) < 0.5);
+        assert!(structure_score(&template, &Outline::at(0, 0, 80, 200, 0.95)) < 0.2);
+    }
+
+    /// A rect built directly, since `ScreenRect::new` clamps to the real screen.
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> ScreenRect {
+        ScreenRect {
+            rect: geometry::Rect::new(
+                geometry::Point::new(x, y),
+                geometry::Size::new(width, height),
+            ),
+        }
+    }
+
+    #[test]
+    fn contains_text_reads_words_centred_in_outline() {
+        let button = rect(100.0, 100.0, 80.0, 30.0);
+        let words = vec![
+            Word::at("Save", 110.0, 108.0, 0),
+            Word::at("As", 145.0, 108.0, 0),
+            Word::at("Cancel", 200.0, 108.0, 0),
+            // Overlaps the button, but centred beyond its right edge.
+            Word::at("Close", 170.0, 108.0, 0),
+        ];
+
+        assert!(contains_text(&button, "Save", &words));
+        assert!(contains_text(&button, "save as", &words));
+        assert!(!contains_text(&button, "Cancel", &words));
+        assert!(!contains_text(&button, "Close", &words));
+        assert!(!contains_text(&button, "Save", &[]));
+    }
+
+    #[test]
+    fn sorts_in_reading_order() {
+        let candidates = vec![
+            (200, 52, 0.9),
+            (10, 100, 0.9),
+            (100, 48, 0.9),
+            (10, 50, 0.9),
+        ];
+        assert_eq!(
+            sort_reading_order(candidates, 20),
+            vec![
+                (10, 50, 0.9),
+                (100, 48, 0.9),
+                (200, 52, 0.9),
+                (10, 100, 0.9)
+            ]
+        );
     }
 }
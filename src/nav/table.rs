@@ -0,0 +1,430 @@
+//! Tables on screen: named columns and repeating rows inside a `ScreenRect`, such as a lab results
+//! grid. Cells can be located to act on, read with OCR, or exported together to CSV.
+use crate::errors::TableError;
+use crate::nav::coordinate::ScreenRect;
+use crate::nav::grid::{detect_grid, detect_image, ruling_lines};
+use crate::ocr::{OcrEngine, TesseractOcr, Word};
+use crate::utils::convert_bitmap_to_mat;
+use autopilot::{bitmap::capture_screen, geometry};
+use opencv::{core::Mat, prelude::*};
+use std::error::Error;
+use std::path::Path;
+
+/// Rows of line pixels at most this far apart belong to the same rule, e.g. the two edges Canny
+/// finds on either side of a 1px line.
+const RULE_GAP: i32 = 2;
+/// Rows shorter than this are the gap inside a doubled rule, not data.
+const MIN_ROW_HEIGHT: f64 = 8.0;
+
+/// A column of a `Table`.
+/// * `name`: Header of the column, used to address its cells.
+/// * `left`: Left edge of the column, from the left edge of the table.
+/// * `width`: Width of the column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub left: f64,
+    pub width: f64,
+}
+
+impl Column {
+    /// Lays out columns side by side from the left edge of a table `table_width` wide.
+    /// Parameters:
+    /// * `columns`: Name and optional width of each column, from left to right. Columns without a
+    ///   width share whatever width the others leave.
+    /// * `table_width`: Width of the table.
+    pub fn side_by_side(columns: &[(String, Option<f64>)], table_width: f64) -> Vec<Column> {
+        let fixed: f64 = columns.iter().filter_map(|(_, width)| *width).sum();
+        let shared = columns.iter().filter(|(_, width)| width.is_none()).count();
+        let share = if shared > 0 {
+            (table_width - fixed).max(0.0) / shared as f64
+        } else {
+            0.0
+        };
+
+        let mut left = 0.0;
+        columns
+            .iter()
+            .map(|(name, width)| {
+                let width = width.unwrap_or(share);
+                let column = Column {
+                    name: name.clone(),
+                    left,
+                    width,
+                };
+                left += width;
+                column
+            })
+            .collect()
+    }
+}
+
+/// How a `Table` finds its rows below the header.
+/// * `Fixed`: Rows of equal height repeating every given number of pixels, as many as fit.
+/// * `Ruled`: Rows separated by horizontal ruling lines, found on screen whenever rows are needed.
+/// * `Detected`: Rows detected on screen whenever rows are needed, split at ruling lines or at the
+///   whitespace between lines of text, so unruled rows of different heights are found too.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RowLayout {
+    Fixed(f64),
+    Ruled,
+    Detected,
+}
+
+/// A table on screen, e.g. a flowsheet of lab results.
+/// Parameters:
+/// * `region`: Rect of the whole table, header included.
+/// * `columns`: The table's columns, left to right.
+/// * `header_height`: Optional. Height of the header row at the top of `region`, which is not a
+///   data row. Default is 0, for a region holding data rows only.
+/// * `rows`: Optional. How data rows are laid out. Default is `RowLayout::Ruled`.
+/// * `engine`: Optional. OCR engine that reads cells. Default is `TesseractOcr`.
+pub struct Table {
+    region: ScreenRect,
+    columns: Vec<Column>,
+    header_height: f64,
+    rows: RowLayout,
+    engine: Box<dyn OcrEngine>,
+}
+
+impl Table {
+    pub fn new(
+        region: ScreenRect,
+        columns: Vec<Column>,
+        header_height: Option<f64>,
+        rows: Option<RowLayout>,
+        engine: Option<Box<dyn OcrEngine>>,
+    ) -> Self {
+        Table {
+            region,
+            columns,
+            header_height: header_height.unwrap_or(0.0),
+            rows: rows.unwrap_or(RowLayout::Ruled),
+            engine: engine.unwrap_or_else(|| Box::new(TesseractOcr::new(None, None))),
+        }
+    }
+
+    /// A table whose structure is detected on screen: its columns and header now, and its rows
+    /// whenever they are needed, as with `RowLayout::Detected`.
+    /// Parameters:
+    /// * `region`: Rect of the whole table, header included.
+    /// * `names`: Optional. Names of the detected columns, left to right. Default is the text of
+    ///   each header cell, or `Column N` where there is no header text.
+    /// * `engine`: Optional. OCR engine that reads header and cells. Default is `TesseractOcr`.
+    ///
+    /// Errors with `TableError` if `names` does not name every detected column.
+    pub fn detected(
+        region: ScreenRect,
+        names: Option<Vec<String>>,
+        engine: Option<Box<dyn OcrEngine>>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let grid = detect_grid(region, None)?;
+        let engine = engine.unwrap_or_else(|| Box::new(TesseractOcr::new(None, None)));
+        let names = match names {
+            Some(names) if names.len() != grid.columns.len() => {
+                return Err(Box::new(TableError {
+                    message: format!(
+                        "Expected {} columns, detected {} on screen",
+                        names.len(),
+                        grid.columns.len()
+                    ),
+                }))
+            }
+            Some(names) => names,
+            None => {
+                let headers = grid
+                    .header_cells()
+                    .into_iter()
+                    .map(|cell| engine.read_text(cell))
+                    .collect::<Result<Vec<_>, _>>()?;
+                (0..grid.columns.len())
+                    .map(|i| {
+                        let header = headers.get(i).map_or(String::new(), |text| {
+                            text.split_whitespace().collect::<Vec<_>>().join(" ")
+                        });
+                        if header.is_empty() {
+                            format!("Column {}", i + 1)
+                        } else {
+                            header
+                        }
+                    })
+                    .collect()
+            }
+        };
+
+        let origin = region.rect.origin;
+        let columns = grid
+            .columns
+            .iter()
+            .zip(names)
+            .map(|((left, width), name)| Column {
+                name,
+                left: left - origin.x,
+                width: *width,
+            })
+            .collect();
+        Ok(Table {
+            region,
+            columns,
+            header_height: grid.header_bottom().map_or(0.0, |bottom| bottom - origin.y),
+            rows: RowLayout::Detected,
+            engine,
+        })
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// The column named `name`, ignoring case. Errors with `TableError` if there is none.
+    pub fn column(&self, name: &str) -> Result<&Column, TableError> {
+        let name = name.trim();
+        self.columns
+            .iter()
+            .find(|column| column.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| TableError {
+                message: format!(
+                    "Table has no column '{}'; columns are {}",
+                    name,
+                    self.columns
+                        .iter()
+                        .map(|column| format!("'{}'", column.name))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            })
+    }
+
+    /// The part of the table below the header, which holds the data rows.
+    fn body(&self) -> ScreenRect {
+        let rect = self.region.rect;
+        let header_height = self.header_height.min(rect.size.height);
+        ScreenRect {
+            rect: geometry::Rect::new(
+                geometry::Point::new(rect.origin.x, rect.origin.y + header_height),
+                geometry::Size::new(rect.size.width, rect.size.height - header_height),
+            ),
+        }
+    }
+
+    /// A new screenshot of the table's body.
+    fn body_image(&self) -> Result<Mat, Box<dyn Error>> {
+        let screenshot = convert_bitmap_to_mat(&capture_screen()?);
+        Ok(Mat::roi(&screenshot, self.body().into())?.try_clone()?)
+    }
+
+    /// Top and height on screen of every data row, from top to bottom. Ruled and detected rows are
+    /// found in a new screenshot on every call, so they follow the table as it scrolls or rows wrap.
+    pub fn rows(&self) -> Result<Vec<(f64, f64)>, Box<dyn Error>> {
+        let body = self.body().rect;
+        let rows = match self.rows {
+            RowLayout::Fixed(pitch) if pitch <= 0.0 => {
+                return Err(Box::new(TableError {
+                    message: format!("Row pitch must be greater than 0, found {}", pitch),
+                }))
+            }
+            RowLayout::Fixed(pitch) => fixed_rows(body.size.height, pitch),
+            RowLayout::Ruled => rows_between(
+                &ruling_lines(&self.body_image()?)?.horizontal,
+                body.size.height,
+            ),
+            RowLayout::Detected => detect_image(&self.body_image()?, (0.0, 0.0))?.rows,
+        };
+        Ok(rows
+            .into_iter()
+            .map(|(top, height)| (body.origin.y + top, height))
+            .collect())
+    }
+
+    /// Rect of the cell in data row `row`, counting from 0, and column `column`. Errors with
+    /// `TableError` if the row or column does not exist.
+    pub fn cell(&self, row: usize, column: &str) -> Result<ScreenRect, Box<dyn Error>> {
+        let column = self.column(column)?;
+        let rows = self.rows()?;
+        let (top, height) = *rows.get(row).ok_or_else(|| TableError {
+            message: format!(
+                "Table has {} rows; row {} does not exist",
+                rows.len(),
+                row + 1
+            ),
+        })?;
+        Ok(ScreenRect {
+            rect: geometry::Rect::new(
+                geometry::Point::new(self.region.rect.origin.x + column.left, top),
+                geometry::Size::new(column.width, height),
+            ),
+        })
+    }
+
+    /// Reads every data row with a single OCR pass over the table.
+    /// Returns:
+    /// * One entry per row, holding the text of each cell in column order.
+    pub fn read_rows(&self) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+        let rows = self.rows()?;
+        let words = self.engine.read_words(self.body())?;
+        Ok(fill_cells(
+            &words,
+            &rows,
+            &self.columns,
+            self.region.rect.origin.x,
+        ))
+    }
+
+    /// Reads the whole table and writes it to a CSV file, with the column names as header.
+    /// Returns:
+    /// * The number of data rows written.
+    pub fn export_csv(&self, path: &Path) -> Result<usize, Box<dyn Error>> {
+        let rows = self.read_rows()?;
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(self.columns.iter().map(|column| &column.name))?;
+        for row in &rows {
+            writer.write_record(row)?;
+        }
+        writer.flush()?;
+        Ok(rows.len())
+    }
+}
+
+/// Rows of `pitch` pixels filling a body `height` pixels tall, as `(top, height)` from the top of
+/// the body. A partial row at the bottom is left out.
+fn fixed_rows(height: f64, pitch: f64) -> Vec<(f64, f64)> {
+    // Allow for rounding, so a body of exactly N pitches holds N rows.
+    let count = ((height + 0.5) / pitch).floor() as usize;
+    (0..count).map(|row| (row as f64 * pitch, pitch)).collect()
+}
+
+/// Rows between the horizontal rules of a body `height` pixels tall, as `(top, height)` from the
+/// top of the body. The top and bottom of the body bound the first and last rows.
+pub(crate) fn rows_between(rules: &[f64], height: f64) -> Vec<(f64, f64)> {
+    let mut bounds = vec![0.0];
+    bounds.extend(rules.iter().filter(|y| **y > 0.0 && **y < height));
+    bounds.push(height);
+    bounds
+        .windows(2)
+        .map(|pair| (pair[0], pair[1] - pair[0]))
+        .filter(|(_, height)| *height >= MIN_ROW_HEIGHT)
+        .collect()
+}
+
+/// Merges runs of nearby rows of line pixels, given in ascending order, into the centre of each
+/// run.
+pub(crate) fn merge_line_rows(rows: &[i32]) -> Vec<f64> {
+    let mut runs: Vec<(i32, i32)> = Vec::new();
+    for &y in rows {
+        match runs.last_mut() {
+            Some((_, end)) if y - *end <= RULE_GAP => *end = y,
+            _ => runs.push((y, y)),
+        }
+    }
+    runs.into_iter()
+        .map(|(start, end)| (start + end) as f64 / 2.0)
+        .collect()
+}
+
+/// Text of every cell, placing each word in the cell containing its centre. Words keep their
+/// reading order within a cell, so text wrapped over several lines reads naturally.
+/// Parameters:
+/// * `words`: Words read from the table, in reading order.
+/// * `rows`: Top and height on screen of each row.
+/// * `columns`: The table's columns.
+/// * `left`: Left edge of the table on screen.
+fn fill_cells(
+    words: &[Word],
+    rows: &[(f64, f64)],
+    columns: &[Column],
+    left: f64,
+) -> Vec<Vec<String>> {
+    let mut cells = vec![vec![Vec::new(); columns.len()]; rows.len()];
+    for word in words {
+        let rect = word.rect.rect;
+        let x = rect.origin.x + rect.size.width / 2.0 - left;
+        let y = rect.origin.y + rect.size.height / 2.0;
+        let row = rows
+            .iter()
+            .position(|(top, height)| y >= *top && y < top + height);
+        let column = columns
+            .iter()
+            .position(|column| x >= column.left && x < column.left + column.width);
+        if let (Some(row), Some(column)) = (row, column) {
+            cells[row][column].push(word.text.as_str());
+        }
+    }
+    cells
+        .into_iter()
+        .map(|row| row.into_iter().map(|cell| cell.join(" ")).collect())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lays_out_columns_side_by_side() {
+        let columns = Column::side_by_side(
+            &[
+                ("Test".to_string(), Some(120.0)),
+                ("Result".to_string(), None),
+                ("Units".to_string(), Some(60.0)),
+                ("Flag".to_string(), None),
+            ],
+            400.0,
+        );
+
+        let bounds: Vec<(f64, f64)> = columns.iter().map(|c| (c.left, c.width)).collect();
+        assert_eq!(
+            bounds,
+            vec![(0.0, 120.0), (120.0, 110.0), (230.0, 60.0), (290.0, 110.0)]
+        );
+        assert_eq!(columns[1].name, "Result");
+    }
+
+    #[test]
+    fn splits_rows_by_pitch_and_rules() {
+        assert_eq!(
+            fixed_rows(60.4, 20.0),
+            vec![(0.0, 20.0), (20.0, 20.0), (40.0, 20.0)]
+        );
+        assert_eq!(fixed_rows(59.0, 20.0).len(), 2);
+
+        assert_eq!(
+            merge_line_rows(&[19, 20, 21, 44, 45, 90]),
+            vec![20.0, 44.5, 90.0]
+        );
+        // A doubled rule at 40 and 43 leaves no row between its lines.
+        assert_eq!(
+            rows_between(&[20.0, 40.0, 43.0, 100.0], 100.0),
+            vec![(0.0, 20.0), (20.0, 20.0), (43.0, 57.0)]
+        );
+    }
+
+    #[test]
+    fn fills_cells_from_words() {
+        let columns = Column::side_by_side(
+            &[
+                ("Test".to_string(), Some(100.0)),
+                ("Result".to_string(), None),
+            ],
+            200.0,
+        );
+        let rows = [(100.0, 20.0), (120.0, 30.0)];
+        let words = [
+            Word::at("Sodium", 10.0, 105.0, 0),
+            Word::at("140", 110.0, 105.0, 0),
+            Word::at("Potassium", 10.0, 122.0, 1),
+            Word::at("4.1", 110.0, 122.0, 1),
+            Word::at("(hemolyzed)", 110.0, 134.0, 2),
+            Word::at("footer", 10.0, 160.0, 3),
+        ];
+
+        assert_eq!(
+            fill_cells(&words, &rows, &columns, 0.0),
+            vec![
+                vec!["Sodium".to_string(), "140".to_string()],
+                vec!["Potassium".to_string(), "4.1 (hemolyzed)".to_string()],
+            ]
+        );
+        // Columns are relative to the table's left edge.
+        assert_eq!(fill_cells(&words, &rows, &columns, 100.0)[0][0], "140");
+    }
+}
@@ -0,0 +1,353 @@
+//! Optical character recognition for verbs and strategies that read text from the screen.
+//! The default engine runs a local Tesseract install, so screen contents never leave the machine.
+use crate::errors::OcrError;
+use crate::nav::coordinate::ScreenRect;
+use autopilot::{bitmap, geometry, screen};
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use uuid::Uuid;
+
+/// A word read from the screen.
+/// * `text`: The word as recognised.
+/// * `rect`: Where the word is on screen.
+/// * `confidence`: Confidence of the recognition, from 0.0 to 1.0.
+/// * `line`: Line the word is on, counting from 0 in reading order.
+#[derive(Debug, Clone)]
+pub struct Word {
+    pub text: String,
+    pub rect: ScreenRect,
+    pub confidence: f64,
+    pub line: usize,
+}
+
+#[cfg(test)]
+impl Word {
+    /// A 30x10 word at `(x, y)`, built directly since `ScreenRect::new` clamps to the real screen.
+    pub fn at(text: &str, x: f64, y: f64, line: usize) -> Word {
+        Word {
+            text: text.to_string(),
+            rect: ScreenRect {
+                rect: geometry::Rect::new(
+                    geometry::Point::new(x, y),
+                    geometry::Size::new(30.0, 10.0),
+                ),
+            },
+            confidence: 0.9,
+            line,
+        }
+    }
+}
+
+pub trait OcrEngine {
+    /// Reads the words inside `region`.
+    /// Returns:
+    /// * The words in reading order. Empty if the region holds no text.
+    fn read_words(&self, region: ScreenRect) -> Result<Vec<Word>, Box<dyn Error>>;
+
+    /// All text inside `region`, with words separated by spaces and lines by newlines.
+    fn read_text(&self, region: ScreenRect) -> Result<String, Box<dyn Error>> {
+        Ok(join_words(&self.read_words(region)?))
+    }
+}
+
+/// Joins words into text, with spaces between words and newlines between lines.
+pub fn join_words(words: &[Word]) -> String {
+    let mut text = String::new();
+    for (i, word) in words.iter().enumerate() {
+        if i > 0 {
+            text.push(if word.line == words[i - 1].line {
+                ' '
+            } else {
+                '\n'
+            });
+        }
+        text.push_str(&word.text);
+    }
+    text
+}
+
+/// `OcrEngine` running the `tesseract` command line tool, which must be installed locally.
+/// Parameters:
+/// * `binary`: Optional. Path of the `tesseract` executable. Default is `tesseract` on the `PATH`.
+/// * `language`: Optional. Tesseract language codes, e.g. `eng+spa`. Default is `eng`.
+pub struct TesseractOcr {
+    binary: PathBuf,
+    language: String,
+}
+
+impl TesseractOcr {
+    pub fn new(binary: Option<PathBuf>, language: Option<String>) -> Self {
+        TesseractOcr {
+            binary: binary.unwrap_or_else(|| PathBuf::from("tesseract")),
+            language: language.unwrap_or_else(|| "eng".to_string()),
+        }
+    }
+
+    /// Runs Tesseract on the image at `path` and returns its TSV output.
+    fn run(&self, path: &Path) -> Result<String, Box<dyn Error>> {
+        let output = Command::new(&self.binary)
+            .arg(path)
+            .arg("stdout")
+            .args(["-l", &self.language])
+            .arg("tsv")
+            .output()
+            .map_err(|e| -> Box<dyn Error> {
+                if e.kind() == io::ErrorKind::NotFound {
+                    Box::new(OcrError {
+                        message: format!(
+                            "Tesseract not found at '{}'; is it installed?",
+                            self.binary.display()
+                        ),
+                    })
+                } else {
+                    Box::new(e)
+                }
+            })?;
+        if !output.status.success() {
+            return Err(Box::new(OcrError {
+                message: format!(
+                    "Tesseract failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            }));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+impl OcrEngine for TesseractOcr {
+    fn read_words(&self, region: ScreenRect) -> Result<Vec<Word>, Box<dyn Error>> {
+        let capture = bitmap::capture_screen_portion(region.into())?;
+        let path = std::env::temp_dir().join(format!("goose-ocr-{}.png", Uuid::new_v4()));
+        capture.image.save(&path)?;
+        let tsv = self.run(&path);
+        let _ = fs::remove_file(&path);
+
+        let scale = screen::scale();
+        Ok(parse_tsv(&tsv?)
+            .into_iter()
+            .map(|word| Word {
+                rect: word_rect(&word, region.rect.origin, scale),
+                text: word.text,
+                confidence: word.confidence,
+                line: word.line,
+            })
+            .collect())
+    }
+}
+
+/// A word from Tesseract's TSV output, positioned in pixels of the image it read.
+#[derive(Debug, PartialEq)]
+struct TsvWord {
+    text: String,
+    left: i32,
+    top: i32,
+    width: i32,
+    height: i32,
+    confidence: f64,
+    line: usize,
+}
+
+/// Where `word` is on screen. Tesseract reports pixels of the capture, which are `scale` times
+/// smaller than the screen's logical coordinates, relative to the captured region's `origin`.
+fn word_rect(word: &TsvWord, origin: geometry::Point, scale: f64) -> ScreenRect {
+    ScreenRect {
+        rect: geometry::Rect::new(
+            geometry::Point::new(
+                origin.x + word.left as f64 / scale,
+                origin.y + word.top as f64 / scale,
+            ),
+            geometry::Size::new(word.width as f64 / scale, word.height as f64 / scale),
+        ),
+    }
+}
+
+/// Level of word rows in Tesseract's TSV output. Other levels describe pages, blocks, paragraphs
+/// and lines.
+const TSV_WORD_LEVEL: &str = "5";
+
+/// Parses the words out of Tesseract's TSV output, skipping the header, blank words and
+/// malformed rows. Lines are numbered by their block, paragraph and line number.
+fn parse_tsv(tsv: &str) -> Vec<TsvWord> {
+    let mut words = Vec::new();
+    let mut current_line = None;
+    let mut line = 0;
+    for row in tsv.lines().skip(1) {
+        let columns: Vec<&str> = row.split('\t').collect();
+        if columns.len() < 12 || columns[0] != TSV_WORD_LEVEL {
+            continue;
+        }
+        let text = columns[11].trim();
+        let numbers: Option<Vec<i32>> = columns[6..10].iter().map(|c| c.parse().ok()).collect();
+        let Some(numbers) = numbers.filter(|_| !text.is_empty()) else {
+            continue;
+        };
+
+        let line_key = (columns[2], columns[3], columns[4]);
+        match current_line {
+            Some(key) if key == line_key => {}
+            Some(_) => {
+                line += 1;
+                current_line = Some(line_key);
+            }
+            None => current_line = Some(line_key),
+        }
+        words.push(TsvWord {
+            text: text.to_string(),
+            left: numbers[0],
+            top: numbers[1],
+            width: numbers[2],
+            height: numbers[3],
+            confidence: columns[10].parse::<f64>().unwrap_or(0.0).max(0.0) / 100.0,
+            line,
+        });
+    }
+    words
+}
+
+/// Similarity of two strings from 0.0 to 1.0: one minus their edit distance divided by the length
+/// of the longer one.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
+
+/// Finds `phrase` among OCR words, given as `(text, line)` pairs, tolerating misread characters
+/// and words that OCR split or merged. Every run of consecutive words on one line, one word
+/// shorter to one word longer than the phrase, is compared with it.
+/// Returns:
+/// * For each word a run can start at, `(start, length, score)` of its best scoring run.
+pub fn find_phrase(
+    phrase: &str,
+    words: &[(&str, usize)],
+    case_sensitive: bool,
+) -> Vec<(usize, usize, f64)> {
+    let normalise = |text: &str| {
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if case_sensitive {
+            text
+        } else {
+            text.to_lowercase()
+        }
+    };
+    let phrase = normalise(phrase);
+    let phrase_length = phrase.split(' ').count();
+
+    let mut found = Vec::new();
+    for start in 0..words.len() {
+        let mut best: Option<(usize, usize, f64)> = None;
+        for length in phrase_length.saturating_sub(1).max(1)..=phrase_length + 1 {
+            let Some(run) = words.get(start..start + length) else {
+                break;
+            };
+            if run.iter().any(|(_, line)| *line != run[0].1) {
+                break;
+            }
+            let text: Vec<&str> = run.iter().map(|(text, _)| *text).collect();
+            let score = similarity(&phrase, &normalise(&text.join(" ")));
+            match best {
+                Some((_, _, best_score)) if best_score >= score => {}
+                _ => best = Some((start, length, score)),
+            }
+        }
+        found.extend(best);
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tesseract_tsv() {
+        let tsv = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n\
+                   1\t1\t0\t0\t0\t0\t0\t0\t400\t100\t-1\t\n\
+                   4\t1\t1\t1\t1\t0\t10\t8\t120\t14\t-1\t\n\
+                   5\t1\t1\t1\t1\t1\t10\t8\t50\t14\t96.5\tChart\n\
+                   5\t1\t1\t1\t1\t2\t64\t8\t66\t14\t91\tReview\n\
+                   5\t1\t1\t1\t1\t3\t140\t8\t4\t14\t12\t \n\
+                   5\t1\t1\t1\t2\t1\t10\t30\t40\t14\t88\tMRN\n";
+        let words = parse_tsv(tsv);
+
+        assert_eq!(words.len(), 3);
+        assert_eq!(
+            words[1],
+            TsvWord {
+                text: "Review".to_string(),
+                left: 64,
+                top: 8,
+                width: 66,
+                height: 14,
+                confidence: 0.91,
+                line: 0,
+            }
+        );
+        assert_eq!(words[2].line, 1);
+    }
+
+    #[test]
+    fn converts_capture_pixels_to_screen_coordinates() {
+        let word = TsvWord {
+            text: "Chart".to_string(),
+            left: 40,
+            top: 20,
+            width: 100,
+            height: 28,
+            confidence: 0.9,
+            line: 0,
+        };
+        let rect = word_rect(&word, geometry::Point::new(300.0, 200.0), 2.0).rect;
+
+        assert_eq!((rect.origin.x, rect.origin.y), (320.0, 210.0));
+        assert_eq!((rect.size.width, rect.size.height), (50.0, 14.0));
+    }
+
+    #[test]
+    fn similarity_is_normalised_edit_distance() {
+        assert_eq!(similarity("chart review", "chart review"), 1.0);
+        assert_eq!(similarity("", ""), 1.0);
+        assert!((similarity("chart review", "chart revlew") - 11.0 / 12.0).abs() < 1e-9);
+        assert_eq!(similarity("abc", "xyz"), 0.0);
+    }
+
+    #[test]
+    fn finds_phrases_despite_ocr_mistakes() {
+        let words = [
+            ("Open", 0),
+            ("Chart", 0),
+            ("Revlew", 0),
+            ("Chart", 1),
+            ("Re", 1),
+            ("view", 1),
+        ];
+        let found = find_phrase("chart review", &words, false);
+
+        let (start, length, score) = found[1];
+        assert_eq!((start, length), (1, 2));
+        assert!(score > 0.9);
+        // Words split by OCR still match, but a run never crosses a line.
+        assert_eq!(found[3], (3, 3, 1.0 - 1.0 / 13.0));
+        assert!(found[2].2 < 0.5);
+
+        let exact = find_phrase("chart review", &words, true);
+        assert!(exact[1].2 < found[1].2);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::nav::grid::Grid;
 use autopilot::{bitmap::Bitmap, geometry, screen};
 use opencv::core::{Mat, Scalar, Vector, CV_8UC3};
 use opencv::{core, imgcodecs, imgproc, prelude::*};
@@ -153,3 +154,70 @@ pub fn generate_template_match_colormap(
 
     Ok(())
 }
+
+/// Draws a detected table grid over the image it was detected in, to check the detection:
+/// header cells are shaded, row boundaries drawn in red and column boundaries in green.
+/// `origin` is the screen position of the image's top-left corner.
+pub fn generate_grid_overlay(
+    input_image: &Mat,
+    grid: &Grid,
+    origin: core::Point,
+    output_path: &str,
+) -> opencv::Result<()> {
+    // Shade the header cells
+    let mut shaded = input_image.try_clone()?;
+    for cell in grid.header_cells() {
+        let rect: core::Rect = cell.into();
+        imgproc::rectangle(
+            &mut shaded,
+            core::Rect::new(
+                rect.x - origin.x,
+                rect.y - origin.y,
+                rect.width,
+                rect.height,
+            ),
+            core::Scalar::new(0.0, 255.0, 255.0, 0.0),
+            imgproc::FILLED,
+            imgproc::LINE_8,
+            0,
+        )?;
+    }
+    let mut output = Mat::default();
+    core::add_weighted(input_image, 0.7, &shaded, 0.3, 0.0, &mut output, -1)?;
+
+    // Draw the edges of every row and column
+    let (width, height) = (input_image.cols(), input_image.rows());
+    for (top, row_height) in &grid.rows {
+        for y in [*top, top + row_height] {
+            let y = y.round() as i32 - origin.y;
+            imgproc::line(
+                &mut output,
+                core::Point::new(0, y),
+                core::Point::new(width - 1, y),
+                core::Scalar::new(0.0, 0.0, 255.0, 0.0),
+                1,
+                imgproc::LINE_8,
+                0,
+            )?;
+        }
+    }
+    for (left, column_width) in &grid.columns {
+        for x in [*left, left + column_width] {
+            let x = x.round() as i32 - origin.x;
+            imgproc::line(
+                &mut output,
+                core::Point::new(x, 0),
+                core::Point::new(x, height - 1),
+                core::Scalar::new(0.0, 255.0, 0.0, 0.0),
+                1,
+                imgproc::LINE_8,
+                0,
+            )?;
+        }
+    }
+
+    // Save the output image
+    imgcodecs::imwrite(output_path, &output, &Vector::new())?;
+
+    Ok(())
+}
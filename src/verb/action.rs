@@ -3,6 +3,7 @@ use crate::nav::coordinate::ScreenRect;
 use autopilot::bitmap;
 use autopilot::bitmap::Bitmap;
 use autopilot::geometry::{Point, Rect};
+use autopilot::mouse::{self, Button};
 use std::error::Error;
 use std::time::{Duration, Instant};
 
@@ -88,3 +89,15 @@ pub trait GuiVerb: GuiAction + CheckUIState {
     /// Passing a `check_zone` is highly recommended since it is likely something unrelated to the action is happening elsewhere on the screen.
     fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>>;
 }
+
+/// Holds `button` down while `action` runs, e.g. to drag. The button is released even if `action`
+/// errors, so a failed move never leaves it pressed.
+pub fn with_button_held<T>(
+    button: Button,
+    action: impl FnOnce() -> Result<T, Box<dyn Error>>,
+) -> Result<T, Box<dyn Error>> {
+    mouse::toggle(button, true);
+    let result = action();
+    mouse::toggle(button, false);
+    result
+}
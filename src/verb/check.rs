@@ -0,0 +1,283 @@
+use crate::errors::AssertionError;
+use crate::nav::coordinate::{ScreenCoordinates, ScreenRect};
+use crate::nav::location::ImageTemplate;
+use crate::ocr::{OcrEngine, TesseractOcr};
+use crate::verb::action::{CheckUIState, GuiAction, GuiVerb};
+use autopilot::bitmap::{self, Bitmap};
+use autopilot::screen;
+use regex::Regex;
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::sync::LazyLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What a `Check` reads from the screen. Every probe produces a string so that one set of
+/// expectations applies to all of them.
+/// * `Template`: `"true"` if the template is found by its `LocationStrategy`, otherwise `"false"`.
+/// * `Text`: The text inside the region, read with OCR.
+/// * `Pixel`: The color of the pixel as lowercase `#rrggbb`.
+pub enum Probe {
+    Template(ImageTemplate),
+    Text(ScreenRect),
+    Pixel(ScreenCoordinates),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn holds(self, ordering: Ordering) -> bool {
+        match self {
+            Comparison::Eq => ordering == Ordering::Equal,
+            Comparison::Ne => ordering != Ordering::Equal,
+            Comparison::Lt => ordering == Ordering::Less,
+            Comparison::Le => ordering != Ordering::Greater,
+            Comparison::Gt => ordering == Ordering::Greater,
+            Comparison::Ge => ordering != Ordering::Less,
+        }
+    }
+}
+
+impl Display for Comparison {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Comparison::Eq => "==",
+            Comparison::Ne => "!=",
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+/// Condition the probed value must satisfy.
+/// * `Present`: The value is `"true"`, i.e. a template probe found its template.
+/// * `Compare`: Compares as dates when both sides contain one (`MM/DD/YYYY` or `YYYY-MM-DD`),
+///   otherwise as numbers when both sides contain one, otherwise as trimmed strings ignoring case.
+///   Strings can only be tested with `==` and `!=`.
+/// * `Contains`: The value contains the text, ignoring case.
+/// * `Is`: The value equals the text, ignoring case and surrounding whitespace.
+/// * `Matches`: The regex matches somewhere in the value.
+pub enum Expectation {
+    Present,
+    Compare(Comparison, String),
+    Contains(String),
+    Is(String),
+    Matches(Regex),
+}
+
+impl Expectation {
+    /// Errors with `AssertionError` when an ordering comparison is applied to values that are
+    /// neither dates nor numbers.
+    pub fn test(&self, value: &str) -> Result<bool, AssertionError> {
+        let holds = match self {
+            Expectation::Present => value == "true",
+            Expectation::Compare(comparison, expected) => match compare(value, expected) {
+                Some(ordering) => comparison.holds(ordering),
+                None if matches!(comparison, Comparison::Eq | Comparison::Ne) => {
+                    let (value, expected) = (value.trim(), expected.trim());
+                    comparison.holds(value.to_lowercase().cmp(&expected.to_lowercase()))
+                }
+                None => {
+                    return Err(AssertionError {
+                        message: format!(
+                            "Cannot compare '{}' {} '{}'; expected dates or numbers",
+                            value, comparison, expected
+                        ),
+                    })
+                }
+            },
+            Expectation::Contains(expected) => {
+                value.to_lowercase().contains(&expected.to_lowercase())
+            }
+            Expectation::Is(expected) => value.trim().eq_ignore_ascii_case(expected.trim()),
+            Expectation::Matches(regex) => regex.is_match(value),
+        };
+        Ok(holds)
+    }
+}
+
+impl Display for Expectation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Expectation::Present => write!(f, "present"),
+            Expectation::Compare(comparison, expected) => {
+                write!(f, "{} \"{}\"", comparison, expected)
+            }
+            Expectation::Contains(expected) => write!(f, "contains \"{}\"", expected),
+            Expectation::Is(expected) => write!(f, "is \"{}\"", expected),
+            Expectation::Matches(regex) => write!(f, "matches /{}/", regex),
+        }
+    }
+}
+
+/// Orders two values as dates, then as numbers. `None` if neither applies to both.
+fn compare(actual: &str, expected: &str) -> Option<Ordering> {
+    if let (Some(a), Some(b)) = (find_date(actual), find_date(expected)) {
+        return Some(a.cmp(&b));
+    }
+    if let (Some(a), Some(b)) = (find_number(actual), find_number(expected)) {
+        return a.partial_cmp(&b);
+    }
+    None
+}
+
+/// `MM/DD/YYYY` dates, compiled once since values are compared on every poll.
+static US_DATE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(\d{1,2})/(\d{1,2})/(\d{4})\b").unwrap());
+/// `YYYY-MM-DD` dates.
+static ISO_DATE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(\d{4})-(\d{1,2})-(\d{1,2})\b").unwrap());
+/// Numbers, optionally negative, with thousands separators and decimals.
+static NUMBER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"-?\d[\d,]*(\.\d+)?").unwrap());
+
+/// First `MM/DD/YYYY` or `YYYY-MM-DD` date in `text`, as a sortable `(year, month, day)`.
+pub(crate) fn find_date(text: &str) -> Option<(u32, u32, u32)> {
+    let (year, month, day) = if let Some(captures) = US_DATE.captures(text) {
+        (
+            captures[3].parse(),
+            captures[1].parse(),
+            captures[2].parse(),
+        )
+    } else {
+        let captures = ISO_DATE.captures(text)?;
+        (
+            captures[1].parse(),
+            captures[2].parse(),
+            captures[3].parse(),
+        )
+    };
+    let date = (year.ok()?, month.ok()?, day.ok()?);
+    ((1..=12).contains(&date.1) && (1..=31).contains(&date.2)).then_some(date)
+}
+
+/// First number in `text`, so values read with units such as `7.2 mg/dL` still compare.
+pub(crate) fn find_number(text: &str) -> Option<f64> {
+    NUMBER.find(text)?.as_str().replace(',', "").parse().ok()
+}
+
+/// Reads a value from the screen and asserts that it meets an expectation.
+/// Use `evaluate` to branch on the result instead of failing.
+/// Parameters:
+/// * `probe`: What to read.
+/// * `expect`: Optional. The condition to test. Default is `Expectation::Present`.
+pub struct Check {
+    probe: Probe,
+    expect: Expectation,
+}
+
+impl Check {
+    pub fn new(probe: Probe, expect: Option<Expectation>) -> Self {
+        Check {
+            probe,
+            expect: expect.unwrap_or(Expectation::Present),
+        }
+    }
+
+    /// Reads the current value of the probe.
+    pub fn read(&self) -> Result<String, Box<dyn Error>> {
+        match &self.probe {
+            Probe::Template(template) => Ok(template.find(None, None)?.is_some().to_string()),
+            Probe::Text(region) => TesseractOcr::new(None, None).read_text(*region),
+            Probe::Pixel(point) => {
+                let [r, g, b, _] = screen::get_color((*point).into())?.0;
+                Ok(format!("#{:02x}{:02x}{:02x}", r, g, b))
+            }
+        }
+    }
+
+    /// Whether the expectation currently holds.
+    pub fn evaluate(&self) -> Result<bool, Box<dyn Error>> {
+        Ok(self.expect.test(&self.read()?)?)
+    }
+
+    fn describe_probe(&self) -> String {
+        match &self.probe {
+            Probe::Template(template) => format!("template {}", template.name),
+            Probe::Text(region) => format!("text in {}", region),
+            Probe::Pixel(point) => format!("pixel at {}", point),
+        }
+    }
+}
+
+impl CheckUIState for Check {}
+
+impl GuiAction for Check {
+    /// Checks do not act on the UI; returns the current screen.
+    fn execute(&self) -> Result<Bitmap, Box<dyn Error>> {
+        Ok(bitmap::capture_screen()?)
+    }
+}
+
+impl GuiVerb for Check {
+    /// Re-reads the probe until the expectation holds or `timeout` ms have passed. The probe is
+    /// always read at least once. Default timeout is 0. Errors with `AssertionError` on failure.
+    fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+        let timeout = Duration::from_millis(timeout.unwrap_or(0));
+        let start = Instant::now();
+        loop {
+            let value = self.read()?;
+            if self.expect.test(&value)? {
+                return Ok(());
+            }
+            if start.elapsed() >= timeout {
+                return Err(Box::new(AssertionError {
+                    message: format!(
+                        "Expected {} {}, found \"{}\"",
+                        self.describe_probe(),
+                        self.expect,
+                        value
+                    ),
+                }));
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_numbers_dates_and_strings() {
+        let at_least = Expectation::Compare(Comparison::Ge, "7.0".to_string());
+        assert!(at_least.test("7.2 mg/dL").unwrap());
+        assert!(!at_least.test("6.9").unwrap());
+
+        let before = Expectation::Compare(Comparison::Lt, "2024-01-01".to_string());
+        assert!(before.test("Admitted 12/31/2023").unwrap());
+        assert!(!before.test("01/02/2024").unwrap());
+
+        let equals = Expectation::Compare(Comparison::Eq, "Final".to_string());
+        assert!(equals.test(" Final ").unwrap());
+        assert!(Expectation::Compare(Comparison::Gt, "Final".to_string())
+            .test("Draft")
+            .is_err());
+    }
+
+    #[test]
+    fn matches_text() {
+        assert!(Expectation::Contains("nephrectomy".to_string())
+            .test("Radical Nephrectomy, left")
+            .unwrap());
+        assert!(Expectation::Is("FINAL".to_string())
+            .test("final\n")
+            .unwrap());
+
+        let date = Expectation::Matches(Regex::new(r"\d{2}/\d{2}/\d{4}").unwrap());
+        assert!(date.test("DOB: 04/17/1962").unwrap());
+        assert!(!date.test("DOB: unknown").unwrap());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::errors::{ControlStateError, TemplateNotFoundError, UIActionTimeOutError};
+use crate::nav::coordinate::{ScreenCoordinates, ScreenRect};
+use crate::nav::location::ImageTemplate;
+use crate::verb::action::{CheckUIState, GuiAction, GuiVerb};
+use autopilot::bitmap::{self, Bitmap};
+use autopilot::{mouse, mouse::Button};
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Puts a checkbox or radio button into a desired state, clicking it only when it is not already
+/// in that state.
+/// The current state is whichever of the two templates matches the control's region with the
+/// higher confidence.
+/// Parameters:
+/// * `checked`: Template of the control when checked.
+/// * `unchecked`: Template of the same control when unchecked.
+/// * `desired`: Whether the control should end up checked.
+/// * `region`: Optional. Rect containing the control and little else. Defaults to the search
+///   region of the `checked` template.
+/// * `min_confidence`: Optional. Confidence the better matching template must reach for the state
+///   to be recognised. Default is 0.8.
+pub struct SetChecked {
+    checked: ImageTemplate,
+    unchecked: ImageTemplate,
+    desired: bool,
+    region: ScreenRect,
+    min_confidence: f64,
+}
+
+impl SetChecked {
+    pub fn new(
+        checked: ImageTemplate,
+        unchecked: ImageTemplate,
+        desired: bool,
+        region: Option<ScreenRect>,
+        min_confidence: Option<f64>,
+    ) -> Self {
+        let region = region.unwrap_or_else(|| {
+            let (x, y, width, height) = checked.search_region;
+            ScreenRect::new(x, y, width as f64, height as f64)
+        });
+        SetChecked {
+            checked,
+            unchecked,
+            desired,
+            region,
+            min_confidence: min_confidence.unwrap_or(0.8),
+        }
+    }
+
+    /// Whether the control is currently checked. Errors with `ControlStateError` if neither
+    /// template matches well enough, or if both match equally well.
+    pub fn is_checked(&self) -> Result<bool, Box<dyn Error>> {
+        let checked = self.score(&self.checked)?;
+        let unchecked = self.score(&self.unchecked)?;
+
+        if checked.max(unchecked) < self.min_confidence {
+            return Err(Box::new(ControlStateError {
+                message: format!(
+                    "Control is neither {} ({:.2}) nor {} ({:.2}); expected a confidence of {:.2}",
+                    self.checked.name, checked, self.unchecked.name, unchecked, self.min_confidence
+                ),
+            }));
+        }
+        if checked == unchecked {
+            return Err(Box::new(ControlStateError {
+                message: format!(
+                    "Control matches {} and {} equally ({:.2}); use templates that differ more",
+                    self.checked.name, self.unchecked.name, checked
+                ),
+            }));
+        }
+        Ok(checked > unchecked)
+    }
+
+    /// Score of the template's best match in the control's region, or 0.0 if it is below the
+    /// minimum confidence.
+    fn score(&self, template: &ImageTemplate) -> Result<f64, Box<dyn Error>> {
+        match template
+            .location_strategy
+            .find_match(Some(self.region), Some(self.min_confidence))
+        {
+            Ok(found) => Ok(found.score),
+            Err(e) if e.is::<TemplateNotFoundError>() => Ok(0.0),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Centre of the template matching the current state, which is where the control is clicked.
+    fn control_location(&self, is_checked: bool) -> Result<ScreenCoordinates, Box<dyn Error>> {
+        let template = if is_checked {
+            &self.checked
+        } else {
+            &self.unchecked
+        };
+        template
+            .find(Some(self.region), Some(self.min_confidence))?
+            .ok_or_else(|| -> Box<dyn Error> {
+                Box::new(ControlStateError {
+                    message: format!("Control {} is no longer on screen", template.name),
+                })
+            })
+    }
+}
+
+impl CheckUIState for SetChecked {}
+
+impl GuiAction for SetChecked {
+    /// Clicks the control, whatever its state.
+    fn execute(&self) -> Result<Bitmap, Box<dyn Error>> {
+        let location = self.control_location(self.is_checked()?)?;
+        mouse::move_to(location.into())?;
+        let screenshot = bitmap::capture_screen()?;
+        mouse::click(Button::Left, None);
+        Ok(screenshot)
+    }
+}
+
+impl GuiVerb for SetChecked {
+    /// Does nothing if the control is already in the desired state. Otherwise clicks it and waits
+    /// up to `timeout` ms (default 1000ms) for the desired state to be recognised.
+    fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+        let timeout = timeout.unwrap_or(1000);
+        if self.is_checked()? == self.desired {
+            return Ok(());
+        }
+        self.check_ui_state(timeout, true, None, Some(self.region))?;
+        self.execute()?;
+
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(timeout) {
+            if matches!(self.is_checked(), Ok(state) if state == self.desired) {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        Err(Box::new(UIActionTimeOutError {
+            message: format!(
+                "Control did not become {} within {}ms",
+                if self.desired { "checked" } else { "unchecked" },
+                timeout
+            ),
+        }))
+    }
+}
@@ -5,8 +5,10 @@ use crate::nav::coordinate::ScreenRect;
 use crate::nav::location::{GetLocation, TargetFactory};
 use crate::nav::strategy::{LocationStrategy, LocationStrategyType};
 use crate::verb::action::{CheckUIState, GuiAction, GuiVerb};
+use crate::verb::press::modifier_key;
 use autopilot::bitmap::{self, Bitmap};
 use autopilot::geometry::Point;
+use autopilot::key::{self, Flag};
 use autopilot::{mouse, mouse::Button};
 use image::GenericImageView;
 use opencv::prelude::*;
@@ -14,24 +16,41 @@ use std::error::Error;
 use std::time::{Duration, Instant};
 
 /// Clicks the mouse at the given location.
-struct Click {
+/// Parameters:
+/// * `target_factory`: Where to click.
+/// * `button`: The mouse button to click with.
+/// * `check_zone`: Optional. Rect indicating where to watch for UI state change. Defaults to the
+///   rect containing the template match
+/// * `clicks`: Optional. Number of clicks in quick succession, e.g. 2 for a double-click. Default
+///   is 1.
+/// * `modifiers`: Optional. Modifier keys held down while clicking, e.g. `Flag::Control` to add a
+///   row to a selection. Default is none.
+pub struct Click {
     target: ScreenCoordinates,
     button: Button,
     check_zone: ScreenRect,
+    clicks: u32,
+    modifiers: Vec<Flag>,
 }
 impl Click {
     pub fn new(
         target_factory: TargetFactory,
         button: Button,
         check_zone: Option<ScreenRect>,
-    ) -> Self {
-        let screenshot = bitmap::capture_screen().expect("Unable to capture screen");
-        let target = target_factory.get_location();
+        clicks: Option<u32>,
+        modifiers: Option<Vec<Flag>>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let target = target_factory.get_location()?;
         let check_zone = check_zone.unwrap_or_else(|| match &target_factory {
-            TargetFactory::AbsoluteTarget(_) => {
+            TargetFactory::AbsoluteTarget(_)
+            | TargetFactory::TextTarget(_)
+            | TargetFactory::RelativeTarget(..)
+            | TargetFactory::CellTarget(..)
+            | TargetFactory::ElementTarget(_) => {
                 target.generate_rect(150, 150, PointAsRectAnchor::Center)
             }
-            TargetFactory::TemplateTarget(template) => {
+            TargetFactory::TemplateTarget(template)
+            | TargetFactory::IndexedTemplateTarget(template, _) => {
                 let (width, height) = (
                     template.image.width() as f64,
                     template.image.height() as f64,
@@ -39,11 +58,13 @@ impl Click {
                 ScreenRect::new(target.x, target.y, width, height)
             }
         });
-        Click {
+        Ok(Click {
             target,
             button,
             check_zone,
-        }
+            clicks: clicks.unwrap_or(1),
+            modifiers: modifiers.unwrap_or_default(),
+        })
     }
 }
 
@@ -55,7 +76,20 @@ impl GuiAction for Click {
 
         mouse::move_to(location)?;
         let screenshot = bitmap::capture_screen_portion(self.check_zone.into())?;
-        mouse::click(self.button, None);
+        let modifiers: Vec<_> = self
+            .modifiers
+            .iter()
+            .filter_map(|flag| modifier_key(*flag))
+            .collect();
+        for modifier in &modifiers {
+            key::toggle(modifier, true, &[], 0);
+        }
+        for _ in 0..self.clicks {
+            mouse::click(self.button, None);
+        }
+        for modifier in modifiers.iter().rev() {
+            key::toggle(modifier, false, &[], 0);
+        }
         Ok(screenshot)
     }
 }
@@ -112,7 +146,10 @@ mod tests {
             }),
             Button::Left,
             None,
-        );
+            None,
+            None,
+        )
+        .expect("Failed to locate click target");
 
         if let Err(e) = click.fire(None) {
             println!("Error: {}", e);
@@ -127,15 +164,24 @@ mod tests {
         setup();
 
         let click = Click::new(
-            TargetFactory::TemplateTarget(ImageTemplate::new(
-                "notepad_close_button".to_string(),
-                Path::new("fixtures/notepad_close_button.png"),
-                None,
-                LocationStrategyType::TemplateMatching,
-            )),
+            TargetFactory::TemplateTarget(
+                ImageTemplate::new(
+                    "notepad_close_button".to_string(),
+                    Path::new("fixtures/notepad_close_button.png"),
+                    None,
+                    LocationStrategyType::TemplateMatching,
+                    None,
+                    None,
+                    None,
+                )
+                .expect("Failed to load template"),
+            ),
             Button::Left,
             None,
-        );
+            None,
+            None,
+        )
+        .expect("Failed to locate click target");
 
         if let Err(e) = click.fire(None) {
             println!("Error: {}", e);
@@ -155,7 +201,10 @@ mod tests {
             }),
             Button::Left,
             None,
-        );
+            None,
+            None,
+        )
+        .expect("Failed to locate click target");
 
         let click_err = click.fire(None).unwrap_err();
         let downcast_err = click_err.downcast_ref::<UIActionTimeOutError>();
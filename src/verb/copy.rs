@@ -0,0 +1,85 @@
+use crate::clipboard::{read_clipboard, write_clipboard, SHORTCUT_MODIFIER};
+use crate::errors::UIActionTimeOutError;
+use crate::nav::coordinate::{ScreenCoordinates, ScreenRect};
+use crate::nav::location::{GetLocation, TargetFactory};
+use crate::verb::action::{with_button_held, CheckUIState, GuiAction, GuiVerb};
+use autopilot::bitmap::{self, Bitmap};
+use autopilot::key::{self, Character};
+use autopilot::{mouse, mouse::Button};
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Drag-selects text between two points and copies it to the clipboard.
+/// Read the copied text with `clipboard::read_clipboard` after firing.
+/// Parameters:
+/// * `start`: Where the selection starts.
+/// * `end`: Where the selection ends.
+/// * `hold`: Optional. Time in ms to keep the button held at `end` before releasing. Text frames
+///   scroll while the cursor rests on their bottom edge, so holding there extends the selection
+///   past the visible area. Default is 0.
+/// * `check_zone`: Optional. Rect that must be stable before the selection starts, e.g. a text
+///   frame that is still loading. Default is no check.
+pub struct CopyText {
+    start: ScreenCoordinates,
+    end: ScreenCoordinates,
+    hold: u64,
+    check_zone: Option<ScreenRect>,
+}
+
+impl CopyText {
+    pub fn new(
+        start: TargetFactory,
+        end: TargetFactory,
+        hold: Option<u64>,
+        check_zone: Option<ScreenRect>,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(CopyText {
+            start: start.get_location()?,
+            end: end.get_location()?,
+            hold: hold.unwrap_or(0),
+            check_zone,
+        })
+    }
+}
+
+impl CheckUIState for CopyText {}
+
+impl GuiAction for CopyText {
+    fn execute(&self) -> Result<Bitmap, Box<dyn Error>> {
+        let screenshot = bitmap::capture_screen()?;
+        mouse::move_to(self.start.into())?;
+        with_button_held(Button::Left, || {
+            mouse::smooth_move(self.end.into(), None)?;
+            thread::sleep(Duration::from_millis(self.hold));
+            Ok(())
+        })?;
+        key::tap(&Character('c'), &[SHORTCUT_MODIFIER], 0, 0);
+        Ok(screenshot)
+    }
+}
+
+impl GuiVerb for CopyText {
+    /// Waits for `check_zone` to be stable and clears the clipboard before selecting, then waits
+    /// up to `timeout` ms (default 1000ms) for text to arrive on it. Errors with
+    /// `UIActionTimeOutError` if nothing was copied.
+    fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+        let timeout = timeout.unwrap_or(1000);
+        if let Some(zone) = self.check_zone {
+            self.check_ui_state(timeout, true, None, Some(zone))?;
+        }
+        write_clipboard("")?;
+        self.execute()?;
+
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(timeout) {
+            if read_clipboard().is_ok_and(|text| !text.is_empty()) {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        Err(Box::new(UIActionTimeOutError {
+            message: format!("Nothing was copied after {}ms", timeout),
+        }))
+    }
+}
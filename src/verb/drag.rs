@@ -0,0 +1,136 @@
+use crate::nav::coordinate::{PointAsRectAnchor, ScreenCoordinates, ScreenRect};
+use crate::nav::location::{GetLocation, TargetFactory};
+use crate::verb::action::{with_button_held, CheckUIState, GuiAction, GuiVerb};
+use autopilot::bitmap::{self, Bitmap};
+use autopilot::geometry::Point;
+use autopilot::{mouse, mouse::Button};
+use std::error::Error;
+use std::thread;
+use std::time::Duration;
+
+/// Time between intermediate cursor moves while dragging.
+const MOVE_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Presses the left button on a source, moves to a destination and releases.
+/// Parameters:
+/// * `source`: Where to press.
+/// * `destination`: Where to release.
+/// * `speed`: Optional. Cursor speed in pixels per second along the straight path between the two.
+///   Default is 800. Slow drags help applications that only accept a drop after hovering.
+/// * `check_zone`: Optional. Rect indicating where to watch for UI state change. Defaults to a
+///   150x150 rect around the destination.
+pub struct Drag {
+    source: ScreenCoordinates,
+    destination: ScreenCoordinates,
+    speed: f64,
+    check_zone: ScreenRect,
+}
+
+impl Drag {
+    pub fn new(
+        source: TargetFactory,
+        destination: TargetFactory,
+        speed: Option<f64>,
+        check_zone: Option<ScreenRect>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let destination = destination.get_location()?;
+        let check_zone = check_zone
+            .unwrap_or_else(|| destination.generate_rect(150, 150, PointAsRectAnchor::Center));
+        Ok(Drag {
+            source: source.get_location()?,
+            destination,
+            speed: speed.unwrap_or(800.0),
+            check_zone,
+        })
+    }
+
+    /// Points along the path from source to destination, one per `MOVE_INTERVAL`, ending exactly
+    /// on the destination.
+    fn path(&self) -> Vec<Point> {
+        let (start, end): (Point, Point) = (self.source.into(), self.destination.into());
+        let (dx, dy) = (end.x - start.x, end.y - start.y);
+        let seconds = (dx * dx + dy * dy).sqrt() / self.speed.max(1.0);
+        let steps = (seconds / MOVE_INTERVAL.as_secs_f64()).ceil().max(1.0) as usize;
+
+        (1..=steps)
+            .map(|step| {
+                let t = step as f64 / steps as f64;
+                Point::new(start.x + dx * t, start.y + dy * t)
+            })
+            .collect()
+    }
+}
+
+impl CheckUIState for Drag {}
+
+impl GuiAction for Drag {
+    fn execute(&self) -> Result<Bitmap, Box<dyn Error>> {
+        let screenshot = bitmap::capture_screen()?;
+
+        mouse::move_to(self.source.into())?;
+        with_button_held(Button::Left, || {
+            // Give the application time to register the press before the cursor moves.
+            thread::sleep(Duration::from_millis(100));
+            for point in self.path() {
+                mouse::move_to(point)?;
+                thread::sleep(MOVE_INTERVAL);
+            }
+            thread::sleep(Duration::from_millis(100));
+            Ok(())
+        })?;
+        Ok(screenshot)
+    }
+}
+
+impl GuiVerb for Drag {
+    fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+        let timeout = timeout.unwrap_or(1000);
+        self.check_ui_state(timeout, true, None, Some(self.check_zone))?;
+        let before = self.execute()?;
+
+        self.check_ui_state(timeout, false, Some(before), Some(self.check_zone))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use autopilot::geometry::{Rect, Size};
+
+    fn drag(source: (f64, f64), destination: (f64, f64), speed: f64) -> Drag {
+        Drag {
+            source: ScreenCoordinates {
+                point: Point::new(source.0, source.1),
+            },
+            destination: ScreenCoordinates {
+                point: Point::new(destination.0, destination.1),
+            },
+            speed,
+            check_zone: ScreenRect {
+                rect: Rect::new(Point::new(0.0, 0.0), Size::new(150.0, 150.0)),
+            },
+        }
+    }
+
+    #[test]
+    fn path_moves_in_even_steps_to_the_destination() {
+        // 500 pixels at 1000 pixels per second take half a second, so 50 moves of 10 pixels.
+        let path = drag((100.0, 100.0), (400.0, 500.0), 1000.0).path();
+
+        assert_eq!(path.len(), 50);
+        assert_eq!((path[0].x, path[0].y), (106.0, 108.0));
+        assert_eq!((path[49].x, path[49].y), (400.0, 500.0));
+        assert_eq!(
+            drag((100.0, 100.0), (400.0, 500.0), 250.0).path().len(),
+            200
+        );
+    }
+
+    #[test]
+    fn zero_length_drag_moves_once() {
+        let path = drag((200.0, 300.0), (200.0, 300.0), 800.0).path();
+
+        assert_eq!(path.len(), 1);
+        assert_eq!((path[0].x, path[0].y), (200.0, 300.0));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::errors::UIActionTimeOutError;
+use crate::nav::coordinate::{ScreenCoordinates, ScreenRect};
+use crate::nav::location::{GetLocation, ImageTemplate, TargetFactory};
+use crate::verb::action::{CheckUIState, GuiAction, GuiVerb};
+use autopilot::bitmap::{self, Bitmap};
+use autopilot::geometry::Point;
+use autopilot::mouse;
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What a hover waits for once the cursor is over the target.
+/// * `Template`: A tooltip or flyout that should appear, located with its own `LocationStrategy`.
+/// * `RegionChange`: A region of the screen that should change, e.g. where a flyout opens.
+///   It should not overlap the hover target so that unrelated highlight effects do not count.
+pub enum HoverExpectation {
+    Template(ImageTemplate),
+    RegionChange(ScreenRect),
+}
+
+/// Mouses over a target and waits for an expected change in a separate search region.
+/// Parameters:
+/// * `target_factory`: Where to hover.
+/// * `expect`: The tooltip template or region change that confirms the hover worked.
+/// * `dwell`: Optional. Time in ms to rest on the target before checking. Default is 500ms.
+pub struct Hover {
+    target: ScreenCoordinates,
+    expect: HoverExpectation,
+    dwell: u64,
+}
+
+impl Hover {
+    pub fn new(
+        target_factory: TargetFactory,
+        expect: HoverExpectation,
+        dwell: Option<u64>,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Hover {
+            target: target_factory.get_location()?,
+            expect,
+            dwell: dwell.unwrap_or(500),
+        })
+    }
+
+    /// Polls the expected template until it is on screen if `visible`, or off screen otherwise.
+    /// Errors with `UIActionTimeOutError` if that takes over `timeout` ms.
+    fn wait_for_template(
+        &self,
+        template: &ImageTemplate,
+        visible: bool,
+        timeout: u64,
+    ) -> Result<(), Box<dyn Error>> {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(timeout) {
+            if template.find(None, None)?.is_some() == visible {
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        Err(Box::new(UIActionTimeOutError {
+            message: if visible {
+                format!(
+                    "Template {} did not appear within {}ms of hovering",
+                    template.name, timeout
+                )
+            } else {
+                format!(
+                    "Template {} was already showing and did not go away within {}ms",
+                    template.name, timeout
+                )
+            },
+        }))
+    }
+}
+
+impl CheckUIState for Hover {}
+
+impl GuiAction for Hover {
+    fn execute(&self) -> Result<Bitmap, Box<dyn Error>> {
+        let location: Point = self.target.into();
+
+        let screenshot = bitmap::capture_screen()?;
+        mouse::move_to(location)?;
+        thread::sleep(Duration::from_millis(self.dwell));
+        Ok(screenshot)
+    }
+}
+
+impl GuiVerb for Hover {
+    /// `timeout` is how long in ms to wait for the expectation after dwelling, and beforehand for
+    /// an expected template to be absent so that its appearance confirms the hover. Default is
+    /// 1000ms.
+    fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+        let timeout = timeout.unwrap_or(1000);
+        match &self.expect {
+            HoverExpectation::Template(template) => {
+                self.wait_for_template(template, false, timeout)?;
+                self.execute()?;
+                self.wait_for_template(template, true, timeout)
+            }
+            HoverExpectation::RegionChange(check_zone) => {
+                self.check_ui_state(timeout, true, None, Some(*check_zone))?;
+                let before = self.execute()?;
+                self.check_ui_state(timeout, false, Some(before), Some(*check_zone))
+            }
+        }
+    }
+}
@@ -20,7 +20,7 @@ use std::error::Error;
 /// * `submit`: Optional. Boolean representing whether `Enter` should be pressed after keyboard input. Default false.
 /// * `check_zone`: Optional. Rect indicating where to watch for UI state change. Defaults to the
 /// rect containing the template match
-struct Input {
+pub struct Input {
     target: ScreenCoordinates,
     input_string: String,
     submit: bool,
@@ -33,18 +33,18 @@ impl Input {
         input_string: String,
         submit: Option<bool>,
         check_zone: Option<ScreenRect>,
-    ) -> Self {
-        let screen = bitmap::capture_screen().expect("Unable to capture screen");
-        screen
-            .image
-            .save("fixtures/screenshots/init_capture.png")
-            .unwrap();
-        let target = target_factory.get_location();
+    ) -> Result<Self, Box<dyn Error>> {
+        let target = target_factory.get_location()?;
         let check_zone = check_zone.unwrap_or_else(|| match &target_factory {
-            TargetFactory::AbsoluteTarget(_) => {
+            TargetFactory::AbsoluteTarget(_)
+            | TargetFactory::TextTarget(_)
+            | TargetFactory::RelativeTarget(..)
+            | TargetFactory::CellTarget(..)
+            | TargetFactory::ElementTarget(_) => {
                 target.generate_rect(150, 150, PointAsRectAnchor::Center)
             }
-            TargetFactory::TemplateTarget(template) => {
+            TargetFactory::TemplateTarget(template)
+            | TargetFactory::IndexedTemplateTarget(template, _) => {
                 let (width, height) = (
                     template.image.width() as f64,
                     template.image.height() as f64,
@@ -54,12 +54,12 @@ impl Input {
                 ScreenRect::new(top_left_x, top_left_y, width, height)
             }
         });
-        Input {
+        Ok(Input {
             target,
             input_string,
             submit: submit.unwrap_or(false),
             check_zone,
-        }
+        })
     }
 }
 
@@ -167,7 +167,8 @@ mod tests {
             "Hello, World!".to_string(),
             None,
             None,
-        );
+        )
+        .expect("Failed to locate input target");
         let result = panic::catch_unwind(|| {
             MockGuiVerb::fire(&input, None, "input_by_coordinates").expect("Failed to input text")
         });
@@ -180,16 +181,23 @@ mod tests {
     fn input_by_template() {
         setup("msedge.exe", Some(2));
         let input = Input::new(
-            TargetFactory::TemplateTarget(ImageTemplate::new(
-                "msedge_omnibox".to_string(),
-                Path::new("fixtures/unit/msedge_omnibox.png"),
-                None,
-                LocationStrategyType::TemplateMatching,
-            )),
+            TargetFactory::TemplateTarget(
+                ImageTemplate::new(
+                    "msedge_omnibox".to_string(),
+                    Path::new("fixtures/unit/msedge_omnibox.png"),
+                    None,
+                    LocationStrategyType::TemplateMatching,
+                    None,
+                    None,
+                    None,
+                )
+                .expect("Failed to load template"),
+            ),
             "foo".to_string(),
             Some(false),
             None,
-        );
+        )
+        .expect("Failed to locate input target");
         let result = panic::catch_unwind(|| {
             MockGuiVerb::fire(&input, None, "input_by_template").expect("Failed to input text")
         });
@@ -203,16 +211,23 @@ mod tests {
     fn submit_input_by_template() {
         setup("msedge.exe", Some(2));
         let input = Input::new(
-            TargetFactory::TemplateTarget(ImageTemplate::new(
-                "msedge_omnibox".to_string(),
-                Path::new("fixtures/unit/msedge_omnibox.png"),
-                None,
-                LocationStrategyType::TemplateMatching,
-            )),
+            TargetFactory::TemplateTarget(
+                ImageTemplate::new(
+                    "msedge_omnibox".to_string(),
+                    Path::new("fixtures/unit/msedge_omnibox.png"),
+                    None,
+                    LocationStrategyType::TemplateMatching,
+                    None,
+                    None,
+                    None,
+                )
+                .expect("Failed to load template"),
+            ),
             "foo".to_string(),
             Some(true),
             None,
-        );
+        )
+        .expect("Failed to locate input target");
 
         let result = panic::catch_unwind(|| {
             MockGuiVerb::fire(&input, None, "submit_input_by_template")
@@ -0,0 +1,86 @@
+use crate::errors::UIActionTimeOutError;
+use crate::nav::coordinate::{PointAsRectAnchor, ScreenCoordinates, ScreenRect};
+use crate::nav::location::{GetLocation, ImageTemplate, TargetFactory};
+use crate::verb::action::{CheckUIState, GuiAction, GuiVerb};
+use autopilot::bitmap::{self, Bitmap};
+use autopilot::{mouse, mouse::Button};
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Right-clicks a target and chooses an item from the context menu that opens.
+/// Parameters:
+/// * `target_factory`: Where to right-click.
+/// * `item`: Template of the menu item to choose. Its search region is ignored; the item is
+///   searched for in `menu_region` instead.
+/// * `menu_region`: Optional. Rect where the menu is expected to open. Defaults to an 800x800 rect
+///   centred on the click point, since menus open towards whichever side has room.
+pub struct ContextMenu {
+    target: ScreenCoordinates,
+    item: ImageTemplate,
+    menu_region: ScreenRect,
+}
+
+impl ContextMenu {
+    pub fn new(
+        target_factory: TargetFactory,
+        item: ImageTemplate,
+        menu_region: Option<ScreenRect>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let target = target_factory.get_location()?;
+        let menu_region = menu_region
+            .unwrap_or_else(|| target.generate_rect(800, 800, PointAsRectAnchor::Center));
+        Ok(ContextMenu {
+            target,
+            item,
+            menu_region,
+        })
+    }
+
+    /// Polls for the menu item until it is found or `timeout` ms have passed.
+    /// Returns the centre of the item.
+    fn find_item(&self, timeout: u64) -> Result<ScreenCoordinates, Box<dyn Error>> {
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(timeout) {
+            if let Some(location) = self.item.find(Some(self.menu_region), None)? {
+                return Ok(location);
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        Err(Box::new(UIActionTimeOutError {
+            message: format!(
+                "Menu item {} did not appear within {}ms of right-clicking",
+                self.item.name, timeout
+            ),
+        }))
+    }
+}
+
+impl CheckUIState for ContextMenu {}
+
+impl GuiAction for ContextMenu {
+    /// Opens the menu. Choosing the item happens in `fire`, once the menu has appeared.
+    fn execute(&self) -> Result<Bitmap, Box<dyn Error>> {
+        mouse::move_to(self.target.into())?;
+        let screenshot = bitmap::capture_screen()?;
+        mouse::click(Button::Right, None);
+        Ok(screenshot)
+    }
+}
+
+impl GuiVerb for ContextMenu {
+    /// `timeout` applies to each stage: the menu opening, the item appearing and the menu closing
+    /// after the item is clicked. Default is 1000ms.
+    fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+        let timeout = timeout.unwrap_or(1000);
+        self.check_ui_state(timeout, true, None, Some(self.menu_region))?;
+        let before = self.execute()?;
+        self.check_ui_state(timeout, false, Some(before), Some(self.menu_region))?;
+
+        let item = self.find_item(timeout)?;
+        mouse::move_to(item.into())?;
+        let opened = bitmap::capture_screen()?;
+        mouse::click(Button::Left, None);
+        self.check_ui_state(timeout, false, Some(opened), Some(self.menu_region))
+    }
+}
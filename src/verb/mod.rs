@@ -1,3 +1,15 @@
-mod action;
-mod click;
-mod input;
+pub mod action;
+pub mod check;
+pub mod checkbox;
+pub mod click;
+pub mod copy;
+pub mod drag;
+pub mod hover;
+pub mod input;
+pub mod menu;
+pub mod paste;
+pub mod press;
+pub mod read;
+pub mod scroll;
+pub mod select;
+pub mod wait;
@@ -0,0 +1,82 @@
+use crate::clipboard::{write_clipboard, SHORTCUT_MODIFIER};
+use crate::nav::coordinate::PointAsRectAnchor;
+use crate::nav::coordinate::{ScreenCoordinates, ScreenRect};
+use crate::nav::location::{GetLocation, TargetFactory};
+use crate::verb::action::{CheckUIState, GuiAction, GuiVerb};
+use autopilot::bitmap::{self, Bitmap};
+use autopilot::key::{self, Character};
+use autopilot::{mouse, mouse::Button};
+use image::GenericImageView;
+use std::error::Error;
+
+/// Clicks into a text area and pastes.
+/// Parameters:
+/// * `target_factory`: The text area to paste into.
+/// * `text`: Optional. Text to place on the clipboard before pasting. When not provided, whatever
+///   is already on the clipboard is pasted.
+/// * `check_zone`: Optional. Rect indicating where to watch for UI state change. Defaults to the
+///   rect containing the template match
+pub struct PasteText {
+    target: ScreenCoordinates,
+    text: Option<String>,
+    check_zone: ScreenRect,
+}
+
+impl PasteText {
+    pub fn new(
+        target_factory: TargetFactory,
+        text: Option<String>,
+        check_zone: Option<ScreenRect>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let target = target_factory.get_location()?;
+        let check_zone = check_zone.unwrap_or_else(|| match &target_factory {
+            TargetFactory::AbsoluteTarget(_)
+            | TargetFactory::TextTarget(_)
+            | TargetFactory::RelativeTarget(..)
+            | TargetFactory::CellTarget(..)
+            | TargetFactory::ElementTarget(_) => {
+                target.generate_rect(150, 150, PointAsRectAnchor::Center)
+            }
+            TargetFactory::TemplateTarget(template)
+            | TargetFactory::IndexedTemplateTarget(template, _) => {
+                let (width, height) = (
+                    template.image.width() as f64,
+                    template.image.height() as f64,
+                );
+                let top_left_x = target.x - width / 2.0;
+                let top_left_y = target.y - height / 2.0;
+                ScreenRect::new(top_left_x, top_left_y, width, height)
+            }
+        });
+        Ok(PasteText {
+            target,
+            text,
+            check_zone,
+        })
+    }
+}
+
+impl CheckUIState for PasteText {}
+
+impl GuiAction for PasteText {
+    /// Pastes whatever is on the clipboard; `fire` places `text` on it first.
+    fn execute(&self) -> Result<Bitmap, Box<dyn Error>> {
+        mouse::move_to(self.target.into())?;
+        mouse::click(Button::Left, None);
+        let screenshot = bitmap::capture_screen()?;
+        key::tap(&Character('v'), &[SHORTCUT_MODIFIER], 0, 0);
+        Ok(screenshot)
+    }
+}
+
+impl GuiVerb for PasteText {
+    fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+        let timeout = timeout.unwrap_or(5000);
+        self.check_ui_state(timeout, true, None, Some(self.check_zone))?;
+        // Held until the pasted text shows, since the target reads the clipboard asynchronously.
+        let _clipboard = self.text.as_deref().map(write_clipboard).transpose()?;
+        let before = self.execute()?;
+
+        self.check_ui_state(timeout, false, Some(before), Some(self.check_zone))
+    }
+}
@@ -0,0 +1,226 @@
+use crate::errors::KeyChordError;
+use crate::nav::coordinate::ScreenRect;
+use crate::verb::action::{CheckUIState, GuiAction, GuiVerb};
+use autopilot::bitmap::{self, Bitmap};
+use autopilot::key::{self, Character, Code, Flag, KeyCode};
+use std::error::Error;
+use std::str::FromStr;
+
+/// The non-modifier key of a chord.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChordKey {
+    Code(KeyCode),
+    Character(char),
+}
+
+/// A key combination written as modifiers and a key joined by `+`, e.g. `ctrl+shift+F5`, `alt+f`
+/// or `tab`. Names are case-insensitive.
+/// * Modifiers: `ctrl`/`control`, `shift`, `alt`/`option`, `meta`/`cmd`/`win`/`super`.
+/// * Keys: `F1`-`F24`, `enter`/`return`, `tab`, `esc`/`escape`, `space`, `backspace`,
+///   `delete`/`del`, `home`, `end`, `pageup`/`pgup`, `pagedown`/`pgdn`, `up`, `down`, `left`,
+///   `right`, `capslock`, or any single character.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chord {
+    pub flags: Vec<Flag>,
+    pub key: ChordKey,
+}
+
+impl FromStr for Chord {
+    type Err = KeyChordError;
+
+    fn from_str(chord: &str) -> Result<Self, Self::Err> {
+        let error = |message: String| KeyChordError { message };
+        let mut parts: Vec<&str> = chord.split('+').map(str::trim).collect();
+        let key = parts
+            .pop()
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| error(format!("Chord '{}' does not end with a key", chord)))?;
+
+        Ok(Chord {
+            flags: parse_modifier_list(&parts)?,
+            key: parse_key(key).ok_or_else(|| error(format!("Unknown key '{}'", key)))?,
+        })
+    }
+}
+
+/// Parses modifiers joined by `+`, e.g. `ctrl+shift`, for actions performed while they are held.
+pub fn parse_modifiers(modifiers: &str) -> Result<Vec<Flag>, KeyChordError> {
+    let parts: Vec<&str> = modifiers.split('+').map(str::trim).collect();
+    parse_modifier_list(&parts)
+}
+
+fn parse_modifier_list(modifiers: &[&str]) -> Result<Vec<Flag>, KeyChordError> {
+    let mut flags = Vec::new();
+    for modifier in modifiers {
+        let flag = match modifier.to_lowercase().as_str() {
+            "ctrl" | "control" => Flag::Control,
+            "shift" => Flag::Shift,
+            "alt" | "option" => Flag::Alt,
+            "meta" | "cmd" | "win" | "super" => Flag::Meta,
+            _ => {
+                return Err(KeyChordError {
+                    message: format!("Unknown modifier '{}'", modifier),
+                })
+            }
+        };
+        if !flags.contains(&flag) {
+            flags.push(flag);
+        }
+    }
+    Ok(flags)
+}
+
+/// The key that produces a modifier flag, used to hold it down across several actions.
+pub fn modifier_key(flag: Flag) -> Option<Code> {
+    let code = match flag {
+        Flag::Control => KeyCode::Control,
+        Flag::Shift => KeyCode::Shift,
+        Flag::Alt => KeyCode::Alt,
+        Flag::Meta => KeyCode::Meta,
+        _ => return None,
+    };
+    Some(Code(code))
+}
+
+fn parse_key(key: &str) -> Option<ChordKey> {
+    let mut chars = key.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Some(ChordKey::Character(c.to_ascii_lowercase()));
+    }
+
+    let code = match key.to_lowercase().as_str() {
+        "enter" | "return" => KeyCode::Return,
+        "tab" => KeyCode::Tab,
+        "esc" | "escape" => KeyCode::Escape,
+        "space" => KeyCode::Space,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" | "pgup" => KeyCode::PageUp,
+        "pagedown" | "pgdn" => KeyCode::PageDown,
+        "up" => KeyCode::UpArrow,
+        "down" => KeyCode::DownArrow,
+        "left" => KeyCode::LeftArrow,
+        "right" => KeyCode::RightArrow,
+        "capslock" => KeyCode::CapsLock,
+        name => function_key(name.strip_prefix('f')?.parse().ok()?)?,
+    };
+    Some(ChordKey::Code(code))
+}
+
+fn function_key(number: u8) -> Option<KeyCode> {
+    const KEYS: [KeyCode; 24] = [
+        KeyCode::F1,
+        KeyCode::F2,
+        KeyCode::F3,
+        KeyCode::F4,
+        KeyCode::F5,
+        KeyCode::F6,
+        KeyCode::F7,
+        KeyCode::F8,
+        KeyCode::F9,
+        KeyCode::F10,
+        KeyCode::F11,
+        KeyCode::F12,
+        KeyCode::F13,
+        KeyCode::F14,
+        KeyCode::F15,
+        KeyCode::F16,
+        KeyCode::F17,
+        KeyCode::F18,
+        KeyCode::F19,
+        KeyCode::F20,
+        KeyCode::F21,
+        KeyCode::F22,
+        KeyCode::F23,
+        KeyCode::F24,
+    ];
+    KEYS.get(usize::from(number).checked_sub(1)?).copied()
+}
+
+/// Presses a key chord, optionally several times in a row.
+/// Parameters:
+/// * `chord`: Chord string such as `ctrl+shift+F5`; see `Chord`.
+/// * `repeat`: Optional. Number of times to press the chord. Default is 1.
+/// * `check_zone`: Optional. Rect indicating where to watch for UI state change. Default is the
+///   entire screen.
+pub struct Press {
+    chord: Chord,
+    repeat: u32,
+    check_zone: Option<ScreenRect>,
+}
+
+impl Press {
+    pub fn new(
+        chord: &str,
+        repeat: Option<u32>,
+        check_zone: Option<ScreenRect>,
+    ) -> Result<Self, Box<dyn Error>> {
+        Ok(Press {
+            chord: chord.parse()?,
+            repeat: repeat.unwrap_or(1),
+            check_zone,
+        })
+    }
+}
+
+impl CheckUIState for Press {}
+
+impl GuiAction for Press {
+    fn execute(&self) -> Result<Bitmap, Box<dyn Error>> {
+        let screenshot = bitmap::capture_screen()?;
+        for _ in 0..self.repeat {
+            match self.chord.key {
+                ChordKey::Code(code) => key::tap(&Code(code), &self.chord.flags, 50, 0),
+                ChordKey::Character(c) => key::tap(&Character(c), &self.chord.flags, 50, 0),
+            }
+        }
+        Ok(screenshot)
+    }
+}
+
+impl GuiVerb for Press {
+    fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+        let timeout = timeout.unwrap_or(1000);
+        self.check_ui_state(timeout, true, None, self.check_zone)?;
+        let before = self.execute()?;
+
+        self.check_ui_state(timeout, false, Some(before), self.check_zone)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_chords() {
+        assert_eq!(
+            "ctrl+shift+F5".parse::<Chord>().unwrap(),
+            Chord {
+                flags: vec![Flag::Control, Flag::Shift],
+                key: ChordKey::Code(KeyCode::F5),
+            }
+        );
+        assert_eq!(
+            "Alt+F".parse::<Chord>().unwrap(),
+            Chord {
+                flags: vec![Flag::Alt],
+                key: ChordKey::Character('f'),
+            }
+        );
+        assert_eq!(
+            "tab".parse::<Chord>().unwrap().key,
+            ChordKey::Code(KeyCode::Tab)
+        );
+        assert!("ctrl+".parse::<Chord>().is_err());
+        assert!("hyper+a".parse::<Chord>().is_err());
+        assert!("F25".parse::<Chord>().is_err());
+        assert_eq!(
+            parse_modifiers("Ctrl+shift+ctrl").unwrap(),
+            vec![Flag::Control, Flag::Shift]
+        );
+        assert!(parse_modifiers("ctrl+a").is_err());
+    }
+}
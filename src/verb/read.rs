@@ -0,0 +1,219 @@
+use crate::errors::{ExtractionError, TemplateNotFoundError};
+use crate::nav::coordinate::ScreenRect;
+use crate::nav::location::RelativeRegion;
+use crate::ocr::{join_words, OcrEngine, TesseractOcr};
+use crate::verb::action::{CheckUIState, GuiAction, GuiVerb};
+use crate::verb::check::{find_date, find_number};
+use autopilot::bitmap::{self, Bitmap};
+use regex::Regex;
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Where `ReadText` reads.
+/// * `Fixed`: A rect on screen.
+/// * `Relative`: A rect next to an element, located each time the text is read.
+pub enum ReadRegion {
+    Fixed(ScreenRect),
+    Relative(RelativeRegion),
+}
+
+/// How `ReadText` converts the text it extracts.
+/// * `Text`: As read, without surrounding whitespace.
+/// * `Number`: The first number, without thousands separators, e.g. `1200` from `1,200 mL`.
+/// * `Date`: The first `MM/DD/YYYY` or `YYYY-MM-DD` date, as `YYYY-MM-DD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    Text,
+    Number,
+    Date,
+}
+
+/// A value read from the screen, kept with what OCR saw so that extracted values can be audited.
+/// * `value`: The extracted and converted value.
+/// * `raw`: All text read in the region.
+/// * `confidence`: Mean OCR confidence of the words read, from 0.0 to 1.0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+    pub value: String,
+    pub raw: String,
+    pub confidence: f64,
+}
+
+/// Reads text from the screen with OCR and extracts a value from it, e.g. the last creatinine.
+/// Parameters:
+/// * `region`: Where to read.
+/// * `pattern`: Optional. Regex locating the value in the text read: its first capture group if
+///   it has one, otherwise the whole match. Default is all text read.
+/// * `value_type`: Optional. How to convert the value. Default is `ValueType::Text`.
+/// * `engine`: Optional. OCR engine that reads the screen. Default is `TesseractOcr`.
+pub struct ReadText {
+    region: ReadRegion,
+    pattern: Option<Regex>,
+    value_type: ValueType,
+    engine: Box<dyn OcrEngine>,
+}
+
+impl ReadText {
+    pub fn new(
+        region: ReadRegion,
+        pattern: Option<Regex>,
+        value_type: Option<ValueType>,
+        engine: Option<Box<dyn OcrEngine>>,
+    ) -> Self {
+        ReadText {
+            region,
+            pattern,
+            value_type: value_type.unwrap_or(ValueType::Text),
+            engine: engine.unwrap_or_else(|| Box::new(TesseractOcr::new(None, None))),
+        }
+    }
+
+    /// Reads the region once. Errors with `ExtractionError` if nothing was read, the pattern does
+    /// not match or the value cannot be converted.
+    pub fn read(&self) -> Result<Reading, Box<dyn Error>> {
+        let region = match &self.region {
+            ReadRegion::Fixed(rect) => *rect,
+            ReadRegion::Relative(relative) => relative.resolve()?,
+        };
+        let words = self.engine.read_words(region)?;
+        let confidence = if words.is_empty() {
+            0.0
+        } else {
+            words.iter().map(|word| word.confidence).sum::<f64>() / words.len() as f64
+        };
+        Ok(extract(
+            &join_words(&words),
+            confidence,
+            self.pattern.as_ref(),
+            self.value_type,
+        )?)
+    }
+
+    /// Re-reads the region until a value is extracted or `timeout` ms have passed, since values
+    /// often render after the view showing them. The region is always read at least once.
+    /// Default timeout is 0. Errors with the last failure.
+    pub fn read_within(&self, timeout: Option<u64>) -> Result<Reading, Box<dyn Error>> {
+        let timeout = Duration::from_millis(timeout.unwrap_or(0));
+        let start = Instant::now();
+        loop {
+            match self.read() {
+                Err(e)
+                    if (e.is::<ExtractionError>() || e.is::<TemplateNotFoundError>())
+                        && start.elapsed() < timeout =>
+                {
+                    thread::sleep(Duration::from_millis(100));
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// Extracts the value from the text read and converts it to `value_type`.
+fn extract(
+    raw: &str,
+    confidence: f64,
+    pattern: Option<&Regex>,
+    value_type: ValueType,
+) -> Result<Reading, ExtractionError> {
+    let text = match pattern {
+        Some(pattern) => {
+            let captures = pattern.captures(raw).ok_or_else(|| ExtractionError {
+                message: format!("/{}/ does not match \"{}\"", pattern, raw),
+            })?;
+            captures
+                .get(1)
+                .or_else(|| captures.get(0))
+                .map_or("", |found| found.as_str())
+        }
+        None => raw,
+    }
+    .trim();
+
+    let value = match value_type {
+        ValueType::Text => Some(text.to_string()).filter(|text| !text.is_empty()),
+        ValueType::Number => find_number(text).map(|number| number.to_string()),
+        ValueType::Date => {
+            find_date(text).map(|(year, month, day)| format!("{:04}-{:02}-{:02}", year, month, day))
+        }
+    };
+    let value = value.ok_or_else(|| ExtractionError {
+        message: match value_type {
+            ValueType::Text => "No text was read".to_string(),
+            ValueType::Number => format!("Expected a number, found \"{}\"", text),
+            ValueType::Date => format!("Expected a date, found \"{}\"", text),
+        },
+    })?;
+    Ok(Reading {
+        value,
+        raw: raw.to_string(),
+        confidence,
+    })
+}
+
+impl CheckUIState for ReadText {}
+
+impl GuiAction for ReadText {
+    /// Reading does not act on the UI; returns the current screen.
+    fn execute(&self) -> Result<Bitmap, Box<dyn Error>> {
+        Ok(bitmap::capture_screen()?)
+    }
+}
+
+impl GuiVerb for ReadText {
+    /// Waits for a value as in `read_within`, discarding it. Use `read_within` to get the value.
+    fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+        self.read_within(timeout).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_capture_groups_and_converts_values() {
+        let raw = "HbA1c 7.2 % 03/14/2024\nCreatinine 1,200";
+        let hba1c = Regex::new(r"HbA1c\s+(\S+)").unwrap();
+        let reading = extract(raw, 0.91, Some(&hba1c), ValueType::Number).unwrap();
+        assert_eq!(
+            reading,
+            Reading {
+                value: "7.2".to_string(),
+                raw: raw.to_string(),
+                confidence: 0.91,
+            }
+        );
+
+        let date = Regex::new(r"\d+/\d+/\d{4}").unwrap();
+        assert_eq!(
+            extract(raw, 0.9, Some(&date), ValueType::Date)
+                .unwrap()
+                .value,
+            "2024-03-14"
+        );
+        let creatinine = Regex::new(r"Creatinine (.*)").unwrap();
+        assert_eq!(
+            extract(raw, 0.9, Some(&creatinine), ValueType::Number)
+                .unwrap()
+                .value,
+            "1200"
+        );
+        assert_eq!(
+            extract(" Dr. Reyes \n", 0.9, None, ValueType::Text)
+                .unwrap()
+                .value,
+            "Dr. Reyes"
+        );
+    }
+
+    #[test]
+    fn fails_when_nothing_can_be_extracted() {
+        let sodium = Regex::new(r"Na (\d+)").unwrap();
+        assert!(extract("K 4.1", 0.9, Some(&sodium), ValueType::Text).is_err());
+        assert!(extract("pending", 0.9, None, ValueType::Number).is_err());
+        assert!(extract("13/45/2024", 0.9, None, ValueType::Date).is_err());
+        assert!(extract("  ", 0.0, None, ValueType::Text).is_err());
+    }
+}
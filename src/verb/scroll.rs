@@ -1,19 +1,243 @@
-use crate::errors::UIActionTimeOutError;
-use crate::nav::location::convert_bitmap_to_mat;
-use crate::nav::location::LocationStrategy;
+use crate::errors::{ScrollLimitError, UIActionTimeOutError};
+use crate::nav::coordinate::ScreenRect;
+use crate::nav::location::ImageTemplate;
 use crate::verb::action::{CheckUIState, GuiAction, GuiVerb};
-use autopilot::bitmap::Bitmap;
+use autopilot::bitmap::{self, Bitmap};
+use autopilot::geometry::Point;
 use autopilot::{
-    geometry::{Point, Rect, Size},
+    key::{self, Code, KeyCode},
     mouse,
-    mouse::Button,
 };
 use std::error::Error;
-use std::{thread, time};
+
+const DEFAULT_STEP: u32 = 3;
+const DEFAULT_MAX_SCROLLS: u32 = 50;
+const DEFAULT_TIMEOUT: u64 = 500;
+
+/// Called after every scroll that moved the watched region, with the 1-based scroll count.
+/// Returning an error stops the scroll and is surfaced by `fire`.
+pub type ScrollCallback = Box<dyn Fn(usize) -> Result<(), Box<dyn Error>>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScrollDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
 
 /// Scrolls the interface, contains two variants:
 /// * `IterativeScroll`: Scrolls an interface until it 'hits the bottom' - has a defined search
-/// region at the bottom of the scroll window interface that determines when the scrolling has 
-/// completed.
+///   region at the bottom of the scroll window interface that determines when the scrolling has
+///   completed.
 /// * `SeekScroll`: Scrolls an interface until a specific visual element appears - has a predefined
-/// image template that it will poll against the screen simultaneously 
\ No newline at end of file
+///   image template that it will poll against the screen simultaneously
+///
+/// Both hover the center of `region` and turn the mouse wheel `step` clicks at a time.
+/// Horizontal scrolling holds `Shift` while turning the wheel.
+struct ScrollMotion {
+    region: ScreenRect,
+    direction: ScrollDirection,
+    step: u32,
+}
+
+impl ScrollMotion {
+    fn center(&self) -> Point {
+        let rect = self.region.rect;
+        Point::new(
+            rect.origin.x + rect.size.width / 2.0,
+            rect.origin.y + rect.size.height / 2.0,
+        )
+    }
+
+    /// Scrolls once and returns a screenshot of the UI BEFORE scrolling.
+    fn scroll(&self) -> Result<Bitmap, Box<dyn Error>> {
+        mouse::move_to(self.center())?;
+        let screenshot = bitmap::capture_screen()?;
+
+        let horizontal = matches!(
+            self.direction,
+            ScrollDirection::Left | ScrollDirection::Right
+        );
+        let wheel = match self.direction {
+            ScrollDirection::Up | ScrollDirection::Left => mouse::ScrollDirection::Up,
+            ScrollDirection::Down | ScrollDirection::Right => mouse::ScrollDirection::Down,
+        };
+
+        if horizontal {
+            key::toggle(&Code(KeyCode::Shift), true, &[], 0);
+        }
+        mouse::scroll(wheel, self.step);
+        if horizontal {
+            key::toggle(&Code(KeyCode::Shift), false, &[], 0);
+        }
+        Ok(screenshot)
+    }
+}
+
+/// Scrolls once and waits for the region to move.
+/// Returns `Ok(false)` when the region did not change within `timeout`, i.e. the end of the
+/// scrollable content has been reached.
+fn scroll_and_watch<T: GuiAction + CheckUIState>(
+    verb: &T,
+    region: ScreenRect,
+    timeout: u64,
+) -> Result<bool, Box<dyn Error>> {
+    let before = verb.execute()?;
+    match verb.check_ui_state(timeout, false, Some(before), Some(region)) {
+        Ok(()) => Ok(true),
+        Err(e) if e.is::<UIActionTimeOutError>() => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Scrolls `region` until it stops changing, calling `on_step` after every scroll so the caller
+/// can act on the newly visible rows.
+/// Parameters:
+/// * `region`: The scrollable viewport. The cursor hovers its center and it is watched for change.
+/// * `direction`: Direction to scroll in.
+/// * `step`: Optional. Mouse wheel clicks per scroll. Default is 3.
+/// * `max_scrolls`: Optional. Stops after this many scrolls even if the region is still moving.
+///   Default is 50.
+/// * `on_step`: Optional. Callback run after every scroll that moved the region.
+pub struct IterativeScroll {
+    motion: ScrollMotion,
+    max_scrolls: u32,
+    on_step: Option<ScrollCallback>,
+}
+
+impl IterativeScroll {
+    pub fn new(
+        region: ScreenRect,
+        direction: ScrollDirection,
+        step: Option<u32>,
+        max_scrolls: Option<u32>,
+        on_step: Option<ScrollCallback>,
+    ) -> Self {
+        IterativeScroll {
+            motion: ScrollMotion {
+                region,
+                direction,
+                step: step.unwrap_or(DEFAULT_STEP),
+            },
+            max_scrolls: max_scrolls.unwrap_or(DEFAULT_MAX_SCROLLS),
+            on_step,
+        }
+    }
+
+    pub fn max_scrolls(&self) -> u32 {
+        self.max_scrolls
+    }
+
+    /// Scrolls once and waits up to `timeout` ms (default 500ms) for the region to move.
+    /// Returns whether it moved; `false` means the end of the content was reached.
+    /// Lets callers that cannot be expressed as a `ScrollCallback` drive the scroll step by step.
+    pub fn scroll_once(&self, timeout: Option<u64>) -> Result<bool, Box<dyn Error>> {
+        scroll_and_watch(self, self.motion.region, timeout.unwrap_or(DEFAULT_TIMEOUT))
+    }
+}
+
+impl CheckUIState for IterativeScroll {}
+
+impl GuiAction for IterativeScroll {
+    fn execute(&self) -> Result<Bitmap, Box<dyn Error>> {
+        self.motion.scroll()
+    }
+}
+
+impl GuiVerb for IterativeScroll {
+    /// Scrolls until the region stops changing or `max_scrolls` is reached; both count as success.
+    /// `timeout` is how long each scroll may take to move the region. Default is 500ms.
+    fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+        for count in 1..=self.max_scrolls as usize {
+            if !self.scroll_once(timeout)? {
+                break;
+            }
+            if let Some(on_step) = &self.on_step {
+                on_step(count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Scrolls until `target` is found on screen.
+/// Parameters:
+/// * `target`: Template to look for after every scroll, located with its own `LocationStrategy`.
+/// * `region`: Optional. The scrollable viewport the cursor hovers. Default is the entire screen.
+/// * `direction`: Direction to scroll in.
+/// * `step`: Optional. Mouse wheel clicks per scroll. Default is 3.
+/// * `max_scrolls`: Optional. Maximum scrolls before giving up. Default is 50.
+/// * `on_step`: Optional. Callback run after every scroll that moved the region.
+pub struct SeekScroll {
+    target: ImageTemplate,
+    motion: ScrollMotion,
+    max_scrolls: u32,
+    on_step: Option<ScrollCallback>,
+}
+
+impl SeekScroll {
+    pub fn new(
+        target: ImageTemplate,
+        region: Option<ScreenRect>,
+        direction: ScrollDirection,
+        step: Option<u32>,
+        max_scrolls: Option<u32>,
+        on_step: Option<ScrollCallback>,
+    ) -> Self {
+        SeekScroll {
+            target,
+            motion: ScrollMotion {
+                region: region.unwrap_or_default(),
+                direction,
+                step: step.unwrap_or(DEFAULT_STEP),
+            },
+            max_scrolls: max_scrolls.unwrap_or(DEFAULT_MAX_SCROLLS),
+            on_step,
+        }
+    }
+
+    /// Searches for the target inside the scroll region only, so matches elsewhere on screen do
+    /// not end the scroll early.
+    fn is_target_visible(&self) -> Result<bool, Box<dyn Error>> {
+        Ok(self.target.find(Some(self.motion.region), None)?.is_some())
+    }
+}
+
+impl CheckUIState for SeekScroll {}
+
+impl GuiAction for SeekScroll {
+    fn execute(&self) -> Result<Bitmap, Box<dyn Error>> {
+        self.motion.scroll()
+    }
+}
+
+impl GuiVerb for SeekScroll {
+    /// Scrolls until the target is found. Errors with `ScrollLimitError` if the end of the content
+    /// or `max_scrolls` is reached first.
+    /// `timeout` is how long each scroll may take to move the region. Default is 500ms.
+    fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+        let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
+
+        for count in 0..=self.max_scrolls as usize {
+            if self.is_target_visible()? {
+                return Ok(());
+            }
+            if count == self.max_scrolls as usize
+                || !scroll_and_watch(self, self.motion.region, timeout)?
+            {
+                break;
+            }
+            if let Some(on_step) = &self.on_step {
+                on_step(count + 1)?;
+            }
+        }
+
+        Err(Box::new(ScrollLimitError {
+            message: format!(
+                "Template {} not found after scrolling {:?}",
+                self.target.name, self.motion.direction
+            ),
+        }))
+    }
+}
@@ -0,0 +1,215 @@
+use crate::errors::{ScrollLimitError, TemplateNotFoundError};
+use crate::nav::coordinate::{PointAsRectAnchor, ScreenCoordinates, ScreenRect};
+use crate::nav::location::{GetLocation, ImageTemplate, TargetFactory};
+use crate::nav::strategy::{LocationStrategy, TextLocationStrategy};
+use crate::verb::action::{CheckUIState, GuiAction, GuiVerb};
+use crate::verb::scroll::{IterativeScroll, ScrollDirection};
+use autopilot::bitmap::{self, Bitmap};
+use autopilot::geometry::{Point, Rect, Size};
+use autopilot::{mouse, mouse::Button, screen};
+use image::GenericImageView;
+use std::error::Error;
+
+/// Width and height of the default popup region.
+const POPUP_SIZE: f64 = 400.0;
+
+/// How `Select` recognises the option to choose inside the open popup.
+/// * `Template`: An image of the option, located with its own `LocationStrategy`.
+/// * `Text`: The option's label, read with OCR.
+pub enum OptionLocator {
+    Template(ImageTemplate),
+    Text(String),
+}
+
+/// Opens a dropdown and chooses one of its options, scrolling the popup until the option shows.
+/// Parameters:
+/// * `target_factory`: The dropdown to open.
+/// * `option`: The option to choose.
+/// * `popup_region`: Optional. Rect where the popup list opens. Defaults to a 400x400 rect below
+///   the dropdown, or above it when there is more room there.
+/// * `value_zone`: Optional. Rect showing the dropdown's current value, which must change once the
+///   option is chosen. Defaults to the rect containing the template match.
+/// * `max_scrolls`: Optional. Maximum scrolls of the popup before giving up. Default is 20.
+pub struct Select {
+    target: ScreenCoordinates,
+    option: OptionLocator,
+    popup_region: ScreenRect,
+    value_zone: ScreenRect,
+    max_scrolls: u32,
+}
+
+impl Select {
+    pub fn new(
+        target_factory: TargetFactory,
+        option: OptionLocator,
+        popup_region: Option<ScreenRect>,
+        value_zone: Option<ScreenRect>,
+        max_scrolls: Option<u32>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let target = target_factory.get_location()?;
+        let value_zone = value_zone.unwrap_or_else(|| match &target_factory {
+            TargetFactory::AbsoluteTarget(_)
+            | TargetFactory::TextTarget(_)
+            | TargetFactory::RelativeTarget(..)
+            | TargetFactory::CellTarget(..)
+            | TargetFactory::ElementTarget(_) => {
+                target.generate_rect(150, 30, PointAsRectAnchor::Center)
+            }
+            TargetFactory::TemplateTarget(template)
+            | TargetFactory::IndexedTemplateTarget(template, _) => {
+                let (width, height) = (
+                    template.image.width() as f64,
+                    template.image.height() as f64,
+                );
+                ScreenRect {
+                    rect: Rect::new(
+                        Point::new(target.x - width / 2.0, target.y - height / 2.0),
+                        Size::new(width, height),
+                    ),
+                }
+            }
+        });
+        let popup_region = popup_region.unwrap_or_else(|| ScreenRect {
+            rect: default_popup_region(value_zone.rect, screen::size()),
+        });
+        Ok(Select {
+            target,
+            option,
+            popup_region,
+            value_zone,
+            max_scrolls: max_scrolls.unwrap_or(20),
+        })
+    }
+
+    /// Looks for the option inside the popup only. Returns its centre, or `None` if it is not
+    /// currently visible.
+    fn find_option(&self) -> Result<Option<ScreenCoordinates>, Box<dyn Error>> {
+        match &self.option {
+            OptionLocator::Template(template) => template.find(Some(self.popup_region), None),
+            OptionLocator::Text(text) => {
+                match TextLocationStrategy::new(text.clone(), None, None)
+                    .find_match(Some(self.popup_region), None)
+                {
+                    Ok(found) => Ok(Some(found.center()?)),
+                    Err(e) if e.is::<TemplateNotFoundError>() => Ok(None),
+                    Err(e) => Err(e),
+                }
+            }
+        }
+    }
+
+    /// Scrolls the popup down until the option shows. Errors with `ScrollLimitError` if the end
+    /// of the list or `max_scrolls` is reached first.
+    fn seek_option(&self) -> Result<ScreenCoordinates, Box<dyn Error>> {
+        let scroll = IterativeScroll::new(
+            self.popup_region,
+            ScrollDirection::Down,
+            Some(1),
+            Some(self.max_scrolls),
+            None,
+        );
+        for count in 0..=self.max_scrolls {
+            if let Some(location) = self.find_option()? {
+                return Ok(location);
+            }
+            if count == self.max_scrolls || !scroll.scroll_once(None)? {
+                break;
+            }
+        }
+        Err(Box::new(ScrollLimitError {
+            message: format!(
+                "Option {} not found in the dropdown",
+                self.describe_option()
+            ),
+        }))
+    }
+
+    fn describe_option(&self) -> String {
+        match &self.option {
+            OptionLocator::Template(template) => format!("template {}", template.name),
+            OptionLocator::Text(text) => format!("\"{}\"", text),
+        }
+    }
+}
+
+/// Where a dropdown whose value shows in `value_zone` opens its popup: below it, or above it when
+/// there is not room for a whole popup below and more room above, as dropdowns near the bottom of
+/// the screen open upwards. Shrunk to the room left on the screen.
+fn default_popup_region(value_zone: Rect, screen: Size) -> Rect {
+    let (top, bottom) = (
+        value_zone.origin.y,
+        value_zone.origin.y + value_zone.size.height,
+    );
+    let (below, above) = ((screen.height - bottom).max(0.0), top.max(0.0));
+    let (y, height) = if below >= POPUP_SIZE || below >= above {
+        (bottom, below.min(POPUP_SIZE))
+    } else {
+        (top - above.min(POPUP_SIZE), above.min(POPUP_SIZE))
+    };
+    let width = (screen.width - value_zone.origin.x).clamp(0.0, POPUP_SIZE);
+    Rect::new(Point::new(value_zone.origin.x, y), Size::new(width, height))
+}
+
+impl CheckUIState for Select {}
+
+impl GuiAction for Select {
+    /// Opens the dropdown. Choosing the option happens in `fire`, once the popup has appeared.
+    fn execute(&self) -> Result<Bitmap, Box<dyn Error>> {
+        mouse::move_to(self.target.into())?;
+        let screenshot = bitmap::capture_screen()?;
+        mouse::click(Button::Left, None);
+        Ok(screenshot)
+    }
+}
+
+impl GuiVerb for Select {
+    /// `timeout` applies to the popup opening and to the displayed value changing after the
+    /// option is clicked. Default is 1000ms.
+    fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+        let timeout = timeout.unwrap_or(1000);
+        self.check_ui_state(timeout, true, None, Some(self.value_zone))?;
+        let before = self.execute()?;
+        self.check_ui_state(
+            timeout,
+            false,
+            Some(before.clone()),
+            Some(self.popup_region),
+        )?;
+
+        let option = self.seek_option()?;
+        mouse::move_to(option.into())?;
+        mouse::click(Button::Left, None);
+        self.check_ui_state(timeout, false, Some(before), Some(self.value_zone))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn zone(y: f64) -> Rect {
+        Rect::new(Point::new(100.0, y), Size::new(150.0, 30.0))
+    }
+
+    #[test]
+    fn popup_opens_below_when_there_is_room() {
+        let popup = default_popup_region(zone(200.0), Size::new(1280.0, 800.0));
+
+        assert_eq!((popup.origin.x, popup.origin.y), (100.0, 230.0));
+        assert_eq!((popup.size.width, popup.size.height), (400.0, 400.0));
+    }
+
+    #[test]
+    fn popup_opens_above_near_the_bottom_of_the_screen() {
+        let popup = default_popup_region(zone(700.0), Size::new(1280.0, 800.0));
+
+        assert_eq!((popup.origin.y, popup.size.height), (300.0, 400.0));
+
+        // Without room for a whole popup either way, the popup takes the larger side.
+        let screen = Size::new(1280.0, 500.0);
+        let popup = default_popup_region(zone(300.0), screen);
+        assert_eq!((popup.origin.y, popup.size.height), (0.0, 300.0));
+        let popup = default_popup_region(zone(100.0), screen);
+        assert_eq!((popup.origin.y, popup.size.height), (130.0, 370.0));
+    }
+}
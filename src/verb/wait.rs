@@ -0,0 +1,188 @@
+use crate::errors::UIActionTimeOutError;
+use crate::nav::coordinate::{ScreenCoordinates, ScreenRect};
+use crate::nav::location::ImageTemplate;
+use crate::verb::action::{CheckUIState, GuiAction, GuiVerb};
+use autopilot::bitmap::{self, Bitmap};
+use std::error::Error;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Default time between polls of the screen.
+const DEFAULT_POLL_INTERVAL: u64 = 250;
+/// Default time to wait for the condition to hold.
+const DEFAULT_TIMEOUT: u64 = 10000;
+
+fn timed_out(message: String) -> Box<dyn Error> {
+    Box::new(UIActionTimeOutError { message })
+}
+
+/// Waits until a template appears on screen, e.g. a page that has finished loading.
+/// Parameters:
+/// * `template`: The template to wait for, located with its own `LocationStrategy`.
+/// * `poll_interval`: Optional. Time in ms between searches. Default is 250ms.
+pub struct WaitFor {
+    template: ImageTemplate,
+    poll_interval: u64,
+}
+
+impl WaitFor {
+    pub fn new(template: ImageTemplate, poll_interval: Option<u64>) -> Self {
+        WaitFor {
+            template,
+            poll_interval: poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL),
+        }
+    }
+
+    /// Polls until the template is found or `timeout` ms have passed (default 10000ms).
+    /// Returns the centre of the template so later steps can act on it.
+    pub fn wait(&self, timeout: Option<u64>) -> Result<ScreenCoordinates, Box<dyn Error>> {
+        let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
+        let start = Instant::now();
+        loop {
+            if let Some(location) = self.template.find(None, None)? {
+                return Ok(location);
+            }
+            if start.elapsed() >= Duration::from_millis(timeout) {
+                return Err(timed_out(format!(
+                    "Template {} did not appear within {}ms",
+                    self.template.name, timeout
+                )));
+            }
+            thread::sleep(Duration::from_millis(self.poll_interval));
+        }
+    }
+}
+
+/// Waits until a template is no longer on screen, e.g. a loading spinner.
+/// Parameters:
+/// * `template`: The template to wait on, located with its own `LocationStrategy`.
+/// * `poll_interval`: Optional. Time in ms between searches. Default is 250ms.
+pub struct WaitUntilGone {
+    template: ImageTemplate,
+    poll_interval: u64,
+}
+
+impl WaitUntilGone {
+    pub fn new(template: ImageTemplate, poll_interval: Option<u64>) -> Self {
+        WaitUntilGone {
+            template,
+            poll_interval: poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL),
+        }
+    }
+
+    /// Polls until the template is not found or `timeout` ms have passed (default 10000ms).
+    /// Returns where the template was last seen, or `None` if it was never on screen.
+    pub fn wait(&self, timeout: Option<u64>) -> Result<Option<ScreenCoordinates>, Box<dyn Error>> {
+        let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
+        let start = Instant::now();
+        let mut last_seen = None;
+        loop {
+            match self.template.find(None, None)? {
+                Some(location) => last_seen = Some(location),
+                None => return Ok(last_seen),
+            }
+            if start.elapsed() >= Duration::from_millis(timeout) {
+                return Err(timed_out(format!(
+                    "Template {} was still visible after {}ms",
+                    self.template.name, timeout
+                )));
+            }
+            thread::sleep(Duration::from_millis(self.poll_interval));
+        }
+    }
+}
+
+/// Waits until a region of the screen stops changing, e.g. a list that fills in over time.
+/// Parameters:
+/// * `region`: The region to watch.
+/// * `quiet`: Optional. Time in ms the region must stay unchanged. Default is 1000ms.
+/// * `poll_interval`: Optional. Time in ms between screenshots. Default is 250ms.
+pub struct WaitStable {
+    region: ScreenRect,
+    quiet: u64,
+    poll_interval: u64,
+}
+
+impl WaitStable {
+    pub fn new(region: ScreenRect, quiet: Option<u64>, poll_interval: Option<u64>) -> Self {
+        WaitStable {
+            region,
+            quiet: quiet.unwrap_or(1000),
+            poll_interval: poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL),
+        }
+    }
+
+    /// Polls until the region has been unchanged for `quiet` ms or `timeout` ms have passed
+    /// (default 10000ms). Returns the region's centre.
+    pub fn wait(&self, timeout: Option<u64>) -> Result<ScreenCoordinates, Box<dyn Error>> {
+        let timeout = timeout.unwrap_or(DEFAULT_TIMEOUT);
+        let start = Instant::now();
+        let mut previous = bitmap::capture_screen_portion(self.region.into())?;
+        let mut stable_since = Instant::now();
+        loop {
+            if stable_since.elapsed() >= Duration::from_millis(self.quiet) {
+                let rect = self.region.rect;
+                return Ok(ScreenCoordinates::new(
+                    rect.origin.x + rect.size.width / 2.0,
+                    rect.origin.y + rect.size.height / 2.0,
+                )?);
+            }
+            if start.elapsed() >= Duration::from_millis(timeout) {
+                return Err(timed_out(format!(
+                    "Region {} did not settle for {}ms within {}ms",
+                    self.region, self.quiet, timeout
+                )));
+            }
+            thread::sleep(Duration::from_millis(self.poll_interval));
+
+            let current = bitmap::capture_screen_portion(self.region.into())?;
+            if !current.bitmap_eq(&previous, Some(0.1)) {
+                stable_since = Instant::now();
+            }
+            previous = current;
+        }
+    }
+}
+
+impl CheckUIState for WaitFor {}
+impl CheckUIState for WaitUntilGone {}
+impl CheckUIState for WaitStable {}
+
+impl GuiAction for WaitFor {
+    /// Waits do not act on the UI; returns the current screen.
+    fn execute(&self) -> Result<Bitmap, Box<dyn Error>> {
+        Ok(bitmap::capture_screen()?)
+    }
+}
+
+impl GuiAction for WaitUntilGone {
+    /// Waits do not act on the UI; returns the current screen.
+    fn execute(&self) -> Result<Bitmap, Box<dyn Error>> {
+        Ok(bitmap::capture_screen()?)
+    }
+}
+
+impl GuiAction for WaitStable {
+    /// Waits do not act on the UI; returns the current screen.
+    fn execute(&self) -> Result<Bitmap, Box<dyn Error>> {
+        Ok(bitmap::capture_screen()?)
+    }
+}
+
+impl GuiVerb for WaitFor {
+    fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+        self.wait(timeout).map(|_| ())
+    }
+}
+
+impl GuiVerb for WaitUntilGone {
+    fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+        self.wait(timeout).map(|_| ())
+    }
+}
+
+impl GuiVerb for WaitStable {
+    fn fire(&self, timeout: Option<u64>) -> Result<(), Box<dyn Error>> {
+        self.wait(timeout).map(|_| ())
+    }
+}